@@ -0,0 +1,62 @@
+use std::thread;
+
+use crate::vari::Vari;
+use crate::{debugger, linter, lsp, profiler, sandbox, test_runner};
+
+const USAGE: &str =
+    "Usage: vari [lsp | lint <file>... | test [path]... | debug <file> | --profile <file> | <file> [arg]...]";
+
+// enough for `DEFAULT_MAX_DEPTH` nested calls even in a debug build
+const STACK_SIZE: usize = 64 * 1024 * 1024;
+
+// the `vari` command line tool, `args` includes the program
+// name, returns the process exit code
+pub fn run(args: &[String]) -> i32 {
+    let args = args.to_vec();
+    let interpreter = thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(move || {
+            sandbox::set_thread_stack_size(STACK_SIZE);
+            dispatch(&args)
+        });
+
+    match interpreter.map(|handle| handle.join()) {
+        Ok(Ok(code)) => code,
+        // the panic message has already been printed
+        Ok(Err(_)) => 101,
+        Err(err) => {
+            println!("vari: {}", err);
+            1
+        }
+    }
+}
+
+fn dispatch(args: &[String]) -> i32 {
+    match args.get(1).map(String::as_str) {
+        Some("lsp") if args.len() == 2 => lsp::run(),
+        Some("lint") => linter::run(&args[2..]),
+        Some("debug") if args.len() == 3 => debugger::run(&args[2]),
+        Some("test") => test_runner::run(&args[2..]),
+        Some("--profile") => profiler::run(&args[2..]),
+        // anything after the script is for the script
+        Some(path) if !path.starts_with('-') && !matches!(path, "lsp" | "debug") => {
+            let mut vari = Vari::new();
+            vari.interpreter.set_args(&args[2..]);
+            vari.run_file(path)
+        }
+        None => {
+            let mut vari = Vari::new();
+            vari.run_prompt();
+            // only set once the prompt couldn't read its input
+            if vari.had_error {
+                1
+            } else {
+                0
+            }
+        }
+        _ => {
+            println!("{}", USAGE);
+            64
+        }
+    }
+}
@@ -0,0 +1,337 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::rc::Rc;
+
+use crate::interpreter::{ExecutionHook, Interpreter};
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+use crate::stmt::Stmt;
+use crate::vari::VariError;
+
+const HELP: &str = "\
+commands:
+  s, step          run until the next line, entering calls
+  n, next          run until the next line in this function
+  o, out           run until the current function returns
+  c, continue      run until a breakpoint is hit
+  b, break [line]  set a breakpoint, or list them
+  d, delete <line> remove a breakpoint
+  l, locals [all]  show variables in every enclosing scope, 'all'
+                   adds the globals
+  p, print <expr>  evaluate an expression in the current scope
+  bt, backtrace    show the active function calls
+  list             show the source around the current line
+  q, quit          stop the program
+an empty line repeats the last command";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StepMode {
+    // only stop at breakpoints
+    Continue,
+    // stop at the next line
+    Step,
+    // stop at the next line with at most this many frames
+    Next(usize),
+    // stop once there are fewer than this many frames
+    Out(usize),
+}
+
+// runs `vari debug <file>`, returning the process exit code
+pub fn run(path: &str) -> i32 {
+    let source = match fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(err) => {
+            println!("{}: {}", path, err);
+            return 1;
+        }
+    };
+
+    let mut lexer = Lexer::new(source.clone());
+    let mut parser = Parser::new(lexer.scan_tokens());
+    let statements = parser.parse();
+
+    let errors: Vec<_> = lexer.errors().iter().chain(parser.errors()).collect();
+    if !errors.is_empty() {
+        for err in errors {
+            println!("{}:{}: {}", path, err.line, err.msg);
+        }
+        return 1;
+    }
+
+    println!("Debugging {}, type 'help' for a list of commands.", path);
+
+    let debugger = Debugger::new(
+        &source,
+        Box::new(io::BufReader::new(io::stdin())),
+        Box::new(io::stdout()),
+    );
+
+    let mut interpreter = Interpreter::new();
+    interpreter.set_hook(Some(Box::new(debugger)));
+
+    let result = interpreter.interpret(statements);
+    if let Err(VariError::Exit(_, code)) = result {
+        println!("Program exited with code {}.", code);
+        return code;
+    }
+
+    match result.err().and_then(VariError::report) {
+        Some((line, msg)) => {
+            println!("Runtime error on line {}: {}", line, msg);
+            1
+        }
+        None => {
+            println!("Program finished.");
+            0
+        }
+    }
+}
+
+// An interactive line debugger driven by `ExecutionHook`,
+// reading commands from `input` whenever execution pauses.
+pub struct Debugger {
+    source: Vec<String>,
+    breakpoints: BTreeSet<usize>,
+    mode: StepMode,
+    // (line, call depth) of the previous statement, a pause
+    // only happens when execution moves on to a new line
+    previous: (usize, usize),
+    last_command: String,
+    input: Box<dyn BufRead>,
+    output: Box<dyn Write>,
+}
+
+impl Debugger {
+    // the debugger pauses before the first statement
+    pub fn new(source: &str, input: Box<dyn BufRead>, output: Box<dyn Write>) -> Self {
+        Self {
+            source: source.lines().map(str::to_owned).collect(),
+            breakpoints: BTreeSet::new(),
+            mode: StepMode::Step,
+            previous: (0, 0),
+            last_command: String::new(),
+            input,
+            output,
+        }
+    }
+
+    fn should_pause(&self, line: usize, depth: usize) -> bool {
+        let new_line = (line, depth) != self.previous;
+
+        if new_line && self.breakpoints.contains(&line) {
+            return true;
+        }
+
+        match self.mode {
+            StepMode::Continue => false,
+            StepMode::Step => new_line,
+            StepMode::Next(max_depth) => new_line && depth <= max_depth,
+            StepMode::Out(below) => depth < below,
+        }
+    }
+
+    fn source_line(&self, line: usize) -> &str {
+        line.checked_sub(1)
+            .and_then(|index| self.source.get(index))
+            .map_or("", String::as_str)
+    }
+
+    // reads and runs commands until one resumes execution, false
+    // when the user quits instead
+    fn pause(&mut self, interpreter: &mut Interpreter, line: usize) -> io::Result<bool> {
+        let text = self.source_line(line).to_owned();
+        writeln!(self.output, "{:>4} | {}", line, text)?;
+
+        loop {
+            write!(self.output, "(vdb) ")?;
+            self.output.flush()?;
+
+            let mut command = String::new();
+            if self.input.read_line(&mut command)? == 0 {
+                // nobody is driving the debugger anymore, let the program finish
+                self.mode = StepMode::Continue;
+                self.breakpoints.clear();
+                writeln!(self.output)?;
+                return Ok(true);
+            }
+
+            let mut command = command.trim().to_owned();
+            if command.is_empty() {
+                command = self.last_command.clone();
+            }
+            self.last_command = command.clone();
+
+            let (name, arg) = match command.split_once(' ') {
+                Some((name, arg)) => (name, arg.trim()),
+                None => (command.as_str(), ""),
+            };
+            let depth = interpreter.frames().len();
+
+            match name {
+                "s" | "step" => {
+                    self.mode = StepMode::Step;
+                    return Ok(true);
+                }
+                "n" | "next" => {
+                    self.mode = StepMode::Next(depth);
+                    return Ok(true);
+                }
+                "o" | "out" | "finish" => {
+                    self.mode = StepMode::Out(depth);
+                    return Ok(true);
+                }
+                "c" | "continue" => {
+                    self.mode = StepMode::Continue;
+                    return Ok(true);
+                }
+                "b" | "break" if arg.is_empty() => {
+                    if self.breakpoints.is_empty() {
+                        writeln!(self.output, "No breakpoints.")?;
+                    }
+                    for line in &self.breakpoints {
+                        writeln!(self.output, "breakpoint at line {}", line)?;
+                    }
+                }
+                "b" | "break" => match arg.parse::<usize>() {
+                    Ok(line) if line >= 1 && line <= self.source.len() => {
+                        self.breakpoints.insert(line);
+                        writeln!(self.output, "Breakpoint set at line {}.", line)?;
+                    }
+                    _ => writeln!(self.output, "No line {} in the program.", arg)?,
+                },
+                "d" | "delete" => match arg.parse::<usize>() {
+                    Ok(line) if self.breakpoints.remove(&line) => {
+                        writeln!(self.output, "Deleted breakpoint at line {}.", line)?
+                    }
+                    _ => writeln!(self.output, "No breakpoint at line {}.", arg)?,
+                },
+                "l" | "locals" => self.print_locals(interpreter, arg == "all")?,
+                "p" | "print" => self.print_expression(interpreter, arg)?,
+                "bt" | "backtrace" => self.print_backtrace(interpreter)?,
+                "list" => {
+                    let first = line.saturating_sub(3).max(1);
+                    let last = (line + 3).min(self.source.len());
+                    for number in first..=last {
+                        let marker = if number == line { "->" } else { "  " };
+                        let text = self.source_line(number).to_owned();
+                        writeln!(self.output, "{} {:>4} | {}", marker, number, text)?;
+                    }
+                }
+                "q" | "quit" => return Ok(false),
+                "h" | "help" => writeln!(self.output, "{}", HELP)?,
+                _ => writeln!(self.output, "Unknown command '{}', try 'help'.", command)?,
+            }
+        }
+    }
+
+    // the globals are mostly the standard library, they're
+    // left out unless asked for
+    fn print_locals(&mut self, interpreter: &Interpreter, globals: bool) -> io::Result<()> {
+        let mut scope = Some(interpreter.environment());
+        let mut depth = 0;
+
+        while let Some(env) = scope {
+            let label = if Rc::ptr_eq(&env, &interpreter.globals) {
+                if !globals {
+                    break;
+                }
+                "globals".to_owned()
+            } else if depth == 0 {
+                "locals".to_owned()
+            } else {
+                format!("enclosing scope {}", depth)
+            };
+
+            writeln!(self.output, "{}:", label)?;
+            for (name, value) in env.borrow().values() {
+                writeln!(self.output, "  {} = {}", name, interpreter.stringify(value))?;
+            }
+
+            scope = env.borrow().enclosing();
+            depth += 1;
+        }
+
+        Ok(())
+    }
+
+    fn print_expression(&mut self, interpreter: &mut Interpreter, source: &str) -> io::Result<()> {
+        let mut source = source.to_owned();
+        if !source.trim_end().ends_with(';') {
+            source.push(';');
+        }
+
+        let mut lexer = Lexer::new(source);
+        let mut parser = Parser::new(lexer.scan_tokens());
+        let statements = parser.parse();
+
+        if let Some(err) = lexer.errors().iter().chain(parser.errors()).next() {
+            return writeln!(self.output, "error: {}", err.msg);
+        }
+
+        // a lone expression prints its value, anything else just runs,
+        // either way the program carries on from the line it paused at
+        let line = interpreter.line();
+        let result = match statements.as_slice() {
+            [Stmt::Expression(expr)] => interpreter
+                .evaluate(expr.clone())
+                .map(|value| Some(interpreter.stringify(*value))),
+            _ => interpreter.interpret(statements).map(|_| None),
+        };
+        interpreter.set_line(line);
+
+        match result {
+            Ok(Some(value)) => writeln!(self.output, "{}", value),
+            Ok(None) => Ok(()),
+            Err(VariError::Exit(..)) => writeln!(self.output, "error: can't exit here"),
+            Err(err) => match err.report() {
+                Some((_, msg)) => writeln!(self.output, "error: {}", msg),
+                None => writeln!(self.output, "error: can't return here"),
+            },
+        }
+    }
+
+    fn print_backtrace(&mut self, interpreter: &Interpreter) -> io::Result<()> {
+        let frames = interpreter.frames();
+        let mut line = interpreter.line();
+
+        for (depth, frame) in frames.iter().enumerate().rev() {
+            writeln!(
+                self.output,
+                "#{} {} (declared on line {}) at line {}",
+                frames.len() - depth - 1,
+                frame.name,
+                frame.def_line,
+                line
+            )?;
+            line = frame.call_line;
+        }
+
+        writeln!(self.output, "#{} <script> at line {}", frames.len(), line)
+    }
+}
+
+impl ExecutionHook for Debugger {
+    fn on_statement(
+        &mut self,
+        interpreter: &mut Interpreter,
+        line: usize,
+    ) -> Result<(), VariError> {
+        let depth = interpreter.frames().len();
+        let pause = self.should_pause(line, depth);
+        self.previous = (line, depth);
+
+        if pause {
+            match self.pause(interpreter, line) {
+                Ok(true) => {}
+                // quitting stops the program the way `exit(0)` would
+                Ok(false) => return Err(VariError::Exit(line, 0)),
+                Err(err) => {
+                    return Err(interpreter.error(format!("Debugger can't go on: {}.", err)))
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,52 @@
+use crate::vari::SyntaxError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+// a problem found by static analysis of a source file,
+// reported by the language server and command line tools
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub line: usize,
+    // zero based byte offset from the start of `line`
+    pub column: usize,
+    // length of the offending source span in bytes
+    pub len: usize,
+    pub severity: Severity,
+    pub msg: String,
+    // id of the lint rule that produced a warning, see `linter::RULES`
+    pub rule: Option<&'static str>,
+}
+
+impl Diagnostic {
+    pub fn error(line: usize, column: usize, len: usize, msg: String) -> Self {
+        Self {
+            line,
+            column,
+            len,
+            severity: Severity::Error,
+            msg,
+            rule: None,
+        }
+    }
+
+    pub fn lint(rule: &'static str, line: usize, column: usize, len: usize, msg: String) -> Self {
+        Self {
+            line,
+            column,
+            len,
+            severity: Severity::Warning,
+            msg,
+            rule: Some(rule),
+        }
+    }
+}
+
+impl From<&SyntaxError> for Diagnostic {
+    fn from(err: &SyntaxError) -> Self {
+        Diagnostic::error(err.line, err.column, 1, err.msg.clone())
+    }
+}
@@ -0,0 +1,228 @@
+use std::fmt;
+use std::fs;
+use std::io::{self, BufRead, Write};
+
+use crate::host::{HostFunction, IntoVari};
+use crate::interpreter::Interpreter;
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+use crate::procedure::Arity;
+use crate::sandbox::{Capability, Limit, Limits};
+use crate::stmt::Stmt;
+use crate::value::Value;
+use crate::vari::{SyntaxError, VariError, VariTypes};
+
+#[derive(Debug)]
+pub enum Error {
+    // every problem found while scanning and parsing the source
+    Syntax(Vec<SyntaxError>),
+    Runtime { line: usize, msg: String },
+    // the script went over one of the engine's `Limits`
+    Limit { line: usize, limit: Limit },
+    // `call_function` couldn't make the call
+    Call(String),
+    // the script called `exit` with this code
+    Exit(i32),
+    Io(io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Syntax(errors) => {
+                let errors: Vec<_> = errors
+                    .iter()
+                    .map(|err| format!("line {}: {}", err.line, err.msg))
+                    .collect();
+                write!(f, "{}", errors.join("\n"))
+            }
+            Error::Runtime { line, msg } => write!(f, "line {}: {}", line, msg),
+            Error::Limit { line, limit } => write!(f, "line {}: {}", line, limit),
+            Error::Call(msg) => write!(f, "{}", msg),
+            Error::Exit(code) => write!(f, "exited with code {}", code),
+            Error::Io(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+// the outcome of running a script or calling one of its functions
+fn finish(result: Result<VariTypes, VariError>) -> Result<Value, Error> {
+    match result {
+        Ok(value) => Ok(value.into()),
+        // a top level `return` ends the script with that value
+        Err(VariError::Return(value)) => Ok(value.into()),
+        Err(VariError::RuntimeError(line, msg)) => Err(Error::Runtime { line, msg }),
+        Err(VariError::LimitExceeded(line, limit)) => Err(Error::Limit { line, limit }),
+        Err(VariError::Exit(_, code)) => Err(Error::Exit(code)),
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+// Runs vari code inside a Rust program. Everything a script
+// defines at top level stays around between calls, so one engine
+// can load a file and then call its functions or read its variables.
+// Scripts can't touch files or the process unless the engine is
+// created with the matching `Capability`.
+pub struct Engine {
+    interpreter: Interpreter,
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Engine {
+    pub fn new() -> Self {
+        Self::with_capabilities(&[])
+    }
+
+    pub fn with_capabilities(capabilities: &[Capability]) -> Self {
+        Self {
+            interpreter: Interpreter::with_capabilities(capabilities),
+        }
+    }
+
+    // applies to each later `eval`, `run_file` and `call_function`
+    // on its own, steps and time start from zero for every call
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.interpreter.set_limits(limits);
+    }
+
+    // where `print` writes, stdout by default
+    pub fn set_output(&mut self, output: impl Write + 'static) {
+        self.interpreter.set_output(Box::new(output));
+    }
+
+    // where `eprint` writes, stderr by default
+    pub fn set_error_output(&mut self, error_output: impl Write + 'static) {
+        self.interpreter.set_error_output(Box::new(error_output));
+    }
+
+    // what scripts read as their input, stdin by default
+    pub fn set_input(&mut self, input: impl BufRead + 'static) {
+        self.interpreter.set_input(Box::new(input));
+    }
+
+    // runs `source`, returning the value of its last statement when
+    // that is an expression, or nil otherwise, the final `;` can be
+    // left out so `engine.eval("1 + 2")` works
+    pub fn eval(&mut self, source: &str) -> Result<Value, Error> {
+        let mut lexer = Lexer::new(source.to_owned());
+        let mut parser = Parser::new(lexer.scan_tokens());
+        parser.allow_bare_expression();
+        let mut statements = parser.parse();
+
+        let errors: Vec<SyntaxError> = lexer
+            .errors()
+            .iter()
+            .chain(parser.errors())
+            .cloned()
+            .collect();
+        if !errors.is_empty() {
+            return Err(Error::Syntax(errors));
+        }
+
+        let last = match statements.last() {
+            Some(Stmt::Expression(_)) => statements.pop(),
+            _ => None,
+        };
+
+        self.interpreter.reset_usage();
+        let result = self
+            .interpreter
+            .interpret(statements)
+            .and_then(|_| match last {
+                Some(Stmt::Expression(expr)) => self.interpreter.evaluate(expr).map(|value| *value),
+                _ => Ok(VariTypes::Nil),
+            });
+
+        finish(result)
+    }
+
+    pub fn run_file(&mut self, path: &str) -> Result<Value, Error> {
+        let source = fs::read_to_string(path)?;
+        self.eval(&source)
+    }
+
+    // defines or replaces a top level variable
+    pub fn set_global(&mut self, name: &str, value: Value) {
+        self.interpreter
+            .environment()
+            .borrow_mut()
+            .define(name.to_owned(), value.into());
+    }
+
+    // a top level variable or one of the builtin functions
+    pub fn get_global(&self, name: &str) -> Option<Value> {
+        self.interpreter
+            .environment()
+            .borrow()
+            .lookup(name)
+            .map(Value::from)
+    }
+
+    // makes a Rust closure callable from vari, its parameter types are
+    // checked on every call, e.g. `engine.register("add", |a: f64, b: f64| a + b)`,
+    // trailing `Option` parameters may be left out by the caller
+    pub fn register<Args, F>(&mut self, name: &str, function: F)
+    where
+        F: HostFunction<Args> + 'static,
+    {
+        let fn_name = name.to_owned();
+        self.interpreter
+            .define_native(name, F::arity(), move |interpreter, args| {
+                let args = args.iter().cloned().map(Value::from).collect();
+                function
+                    .call(&fn_name, args)
+                    .map(VariTypes::from)
+                    .map_err(|msg| interpreter.error(msg))
+            });
+    }
+
+    // like `register` for functions taking any number of arguments,
+    // as long as there are at least `min_args`
+    pub fn register_variadic<F, R>(&mut self, name: &str, min_args: usize, function: F)
+    where
+        F: Fn(&[Value]) -> R + 'static,
+        R: IntoVari,
+    {
+        self.interpreter
+            .define_native(name, Arity::AtLeast(min_args), move |interpreter, args| {
+                let args: Vec<Value> = args.iter().cloned().map(Value::from).collect();
+                function(&args)
+                    .into_vari()
+                    .map(VariTypes::from)
+                    .map_err(|msg| interpreter.error(msg))
+            });
+    }
+
+    pub fn call_function(&mut self, name: &str, args: &[Value]) -> Result<Value, Error> {
+        let procedure = match self.interpreter.environment().borrow().lookup(name) {
+            Some(VariTypes::Callable(procedure)) => procedure,
+            Some(_) => return Err(Error::Call(format!("'{}' is not a function.", name))),
+            None => return Err(Error::Call(format!("Undefined function '{}'.", name))),
+        };
+
+        if !procedure.arity().accepts(args.len()) {
+            return Err(Error::Call(format!(
+                "'{}' expects {} arguments but got {}.",
+                name,
+                procedure.arity(),
+                args.len()
+            )));
+        }
+
+        let args = args.iter().cloned().map(VariTypes::from).collect();
+        self.interpreter.reset_usage();
+        finish(procedure.call(&mut self.interpreter, args))
+    }
+}
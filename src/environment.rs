@@ -1,4 +1,7 @@
-use crate::{token::Token, vari::VariTypes};
+use crate::{
+    token::Token,
+    vari::{VariError, VariTypes},
+};
 use std::{cell::RefCell, collections::HashMap, rc::Rc};
 
 #[derive(Debug, Clone)]
@@ -15,37 +18,68 @@ impl Environment {
         }
     }
 
-    pub fn get(&mut self, token: Token) -> VariTypes {
+    pub fn get(&mut self, token: Token) -> Result<VariTypes, VariError> {
         if let Some(value) = self.values.get(&token.lexeme) {
-            return (*value).clone();
+            return Ok((*value).clone());
         }
 
         if let Some(enclosing_env) = self.enclosing.clone() {
             return (*enclosing_env).borrow_mut().get(token);
         }
 
-        // TODO: syntax error "undefined vairable"
-        panic!("Undefined variable {} in {:?}", token.lexeme, self.values);
+        Err(VariError::RuntimeError(
+            token.line,
+            format!("Undefined variable '{}'.", token.lexeme),
+        ))
     }
 
-    pub fn assign(&mut self, name: String, value: VariTypes) {
-        if self.values.contains_key(&name) {
-            self.values.insert(name.clone(), value.clone());
-            return;
+    pub fn assign(&mut self, name: Token, value: VariTypes) -> Result<(), VariError> {
+        if let Some(slot) = self.values.get_mut(&name.lexeme) {
+            *slot = value;
+            return Ok(());
         }
 
         if let Some(enclosing_env) = self.enclosing.clone() {
-            enclosing_env.borrow_mut().assign(name, value);
-            return;
+            return enclosing_env.borrow_mut().assign(name, value);
         }
 
-        unreachable!()
-        // error: undefined variable
+        Err(VariError::RuntimeError(
+            name.line,
+            format!("Undefined variable '{}'.", name.lexeme),
+        ))
     }
 
     pub fn define(&mut self, name: String, value: VariTypes) {
         self.values.insert(name, value);
     }
+
+    // looks `name` up in this scope and then the enclosing ones
+    pub fn lookup(&self, name: &str) -> Option<VariTypes> {
+        match self.values.get(name) {
+            Some(value) => Some(value.clone()),
+            None => self.enclosing.as_ref()?.borrow().lookup(name),
+        }
+    }
+
+    // names defined directly in this scope
+    pub fn names(&self) -> Vec<String> {
+        self.values.keys().cloned().collect()
+    }
+
+    // bindings defined directly in this scope, sorted by name
+    pub fn values(&self) -> Vec<(String, VariTypes)> {
+        let mut values: Vec<_> = self
+            .values
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        values.sort_by(|(a, _), (b, _)| a.cmp(b));
+        values
+    }
+
+    pub fn enclosing(&self) -> Option<Rc<RefCell<Environment>>> {
+        self.enclosing.clone()
+    }
 }
 
 impl From<&Rc<RefCell<Environment>>> for Environment {
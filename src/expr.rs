@@ -1,5 +1,4 @@
-use std::collections::HashMap;
-
+use crate::fields::Fields;
 use crate::token::Token;
 use crate::vari::VariTypes;
 
@@ -23,7 +22,27 @@ pub enum Expr {
         expr: Box<Expr>,
     },
     Struct {
-        values: HashMap<String, Expr>,
+        values: Fields<Expr>,
+    },
+    // `Point { x: 1 }`, an instance of a declared struct
+    Construct {
+        name: Token,
+        values: Fields<Expr>,
+    },
+    List {
+        bracket: Token, // opening '['
+        items: Vec<Expr>,
+    },
+    Index {
+        expr: Box<Expr>,  // list or string expression
+        bracket: Token,   // opening '['
+        index: Box<Expr>, // position
+    },
+    SetIndex {
+        expr: Box<Expr>,  // list expression
+        bracket: Token,   // opening '['
+        index: Box<Expr>, // position
+        value: Box<Expr>, // value to be assigned
     },
     Get {
         expr: Box<Expr>, // object expression
@@ -49,6 +68,110 @@ pub enum Expr {
         operator: Token,
         rhs: Box<Expr>,
     },
+    Match {
+        keyword: Token,
+        value: Box<Expr>,
+        arms: Vec<MatchArm>,
+    },
+}
+
+// `pattern if guard => body`
+#[derive(Clone)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub guard: Option<Expr>,
+    pub body: Expr,
+}
+
+#[derive(Clone)]
+pub enum Pattern {
+    // a number, string, boolean or nil, compared with `==`
+    Literal(VariTypes),
+    // `_`
+    Wildcard,
+    // a name, matching anything and binding it
+    Binding(Token),
+    // `a | b`, the first one that matches
+    Alternatives(Vec<Pattern>),
+    // `{x: 0, y}` or `Point {x: 0}`, a struct with at least these fields
+    Struct {
+        name: Option<Token>,
+        fields: Vec<(Token, Pattern)>,
+    },
+    // `[first, ..rest]`, the items before a `..`, then the name the
+    // rest is bound to and the items after it
+    List {
+        head: Vec<Pattern>,
+        rest: Option<(Option<Token>, Vec<Pattern>)>,
+    },
+}
+
+impl Pattern {
+    // the names the pattern binds, each once
+    pub fn bindings(&self) -> Vec<Token> {
+        let mut names: Vec<Token> = vec![];
+        self.collect_bindings(&mut names);
+        names
+    }
+
+    fn collect_bindings(&self, names: &mut Vec<Token>) {
+        match self {
+            Pattern::Literal(_) | Pattern::Wildcard => {}
+            Pattern::Binding(name) => {
+                if !names.iter().any(|other| other.lexeme == name.lexeme) {
+                    names.push(name.clone());
+                }
+            }
+            Pattern::Alternatives(alternatives) => {
+                for alternative in alternatives {
+                    alternative.collect_bindings(names);
+                }
+            }
+            Pattern::Struct { fields, .. } => {
+                for (_, field) in fields {
+                    field.collect_bindings(names);
+                }
+            }
+            Pattern::List { head, rest } => {
+                for item in head {
+                    item.collect_bindings(names);
+                }
+                if let Some((name, tail)) = rest {
+                    if let Some(name) = name {
+                        Pattern::Binding(name.clone()).collect_bindings(names);
+                    }
+                    for item in tail {
+                        item.collect_bindings(names);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Expr {
+    // line of the first token in the expression, if it has any
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            Expr::Binary { lhs, op, .. } => lhs.line().or(Some(op.line)),
+            Expr::Call { callee, paren, .. } => callee.line().or(Some(paren.line)),
+            Expr::Unary { op, .. } => Some(op.line),
+            Expr::Grouping { expr } | Expr::Get { expr, .. } | Expr::Set { expr, .. } => {
+                expr.line()
+            }
+            Expr::Struct { values } => values.values().filter_map(Expr::line).min(),
+            Expr::Construct { name, .. } => Some(name.line),
+            Expr::List { bracket, .. } => Some(bracket.line),
+            Expr::Index { expr, bracket, .. } | Expr::SetIndex { expr, bracket, .. } => {
+                expr.line().or(Some(bracket.line))
+            }
+            Expr::Literal { .. } => None,
+            Expr::Variable { value } => Some(value.line),
+            Expr::Assign { name, .. } => Some(name.line),
+            Expr::Logical { lhs, operator, .. } => lhs.line().or(Some(operator.line)),
+            Expr::Match { keyword, .. } => Some(keyword.line),
+        }
+    }
 }
 
 pub trait ExprVisitor<T> {
@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Index;
+
+// A struct's fields in the order they were first set. Setting a field
+// that already exists changes its value but keeps its place.
+#[derive(Clone)]
+pub struct Fields<T> {
+    entries: Vec<(String, T)>,
+    // position of each name in `entries`
+    index: HashMap<String, usize>,
+}
+
+impl<T> Fields<T> {
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            index: HashMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn contains_key(&self, name: &str) -> bool {
+        self.index.contains_key(name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&T> {
+        self.index.get(name).map(|&i| &self.entries[i].1)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut T> {
+        self.index.get(name).map(|&i| &mut self.entries[i].1)
+    }
+
+    // sets `name`, returning the value it had before
+    pub fn insert(&mut self, name: String, value: T) -> Option<T> {
+        match self.index.get(&name) {
+            Some(&i) => Some(std::mem::replace(&mut self.entries[i].1, value)),
+            None => {
+                self.index.insert(name.clone(), self.entries.len());
+                self.entries.push((name, value));
+                None
+            }
+        }
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &String> {
+        self.entries.iter().map(|(name, _)| name)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.entries.iter().map(|(_, value)| value)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &T)> {
+        self.entries.iter().map(|(name, value)| (name, value))
+    }
+}
+
+impl<T> Default for Fields<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// equal when they have the same fields with equal values, in any order
+impl<T: PartialEq> PartialEq for Fields<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && self
+                .iter()
+                .all(|(name, value)| other.get(name) == Some(value))
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Fields<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<T> Index<&str> for Fields<T> {
+    type Output = T;
+
+    fn index(&self, name: &str) -> &T {
+        self.get(name)
+            .unwrap_or_else(|| panic!("no field named '{}'", name))
+    }
+}
+
+// a repeated name keeps its first place and its last value
+impl<T> FromIterator<(String, T)> for Fields<T> {
+    fn from_iter<I: IntoIterator<Item = (String, T)>>(iter: I) -> Self {
+        let mut fields = Fields::new();
+        for (name, value) in iter {
+            fields.insert(name, value);
+        }
+        fields
+    }
+}
+
+impl<T> IntoIterator for Fields<T> {
+    type Item = (String, T);
+    type IntoIter = std::vec::IntoIter<(String, T)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
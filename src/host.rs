@@ -0,0 +1,256 @@
+use std::fmt::Display;
+
+use crate::procedure::Arity;
+use crate::value::Value;
+
+// Converts an argument passed from vari into a Rust value, used by
+// `Engine::register` to check the types of a host function's parameters.
+pub trait FromVari: Sized {
+    // trailing optional parameters may be left out by the caller
+    const OPTIONAL: bool = false;
+
+    // what the parameter accepts, for error messages: "a number"
+    fn expected() -> String;
+
+    fn from_vari(value: Value) -> Option<Self>;
+}
+
+// Converts a host function's result back into a vari value, an `Err`
+// becomes a runtime error on the line that made the call.
+pub trait IntoVari {
+    fn into_vari(self) -> Result<Value, String>;
+}
+
+impl FromVari for Value {
+    fn expected() -> String {
+        "any value".to_owned()
+    }
+
+    fn from_vari(value: Value) -> Option<Self> {
+        Some(value)
+    }
+}
+
+impl FromVari for f64 {
+    fn expected() -> String {
+        "a number".to_owned()
+    }
+
+    fn from_vari(value: Value) -> Option<Self> {
+        match value {
+            Value::Num(n) => Some(n),
+            _ => None,
+        }
+    }
+}
+
+impl FromVari for i64 {
+    fn expected() -> String {
+        "a whole number".to_owned()
+    }
+
+    fn from_vari(value: Value) -> Option<Self> {
+        match value {
+            Value::Num(n) if n.fract() == 0.0 && n.abs() <= i64::MAX as f64 => Some(n as i64),
+            _ => None,
+        }
+    }
+}
+
+impl FromVari for bool {
+    fn expected() -> String {
+        "a boolean".to_owned()
+    }
+
+    fn from_vari(value: Value) -> Option<Self> {
+        match value {
+            Value::Bool(b) => Some(b),
+            _ => None,
+        }
+    }
+}
+
+impl FromVari for String {
+    fn expected() -> String {
+        "a string".to_owned()
+    }
+
+    fn from_vari(value: Value) -> Option<Self> {
+        match value {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+// nil or a missing argument is None
+impl<T: FromVari> FromVari for Option<T> {
+    const OPTIONAL: bool = true;
+
+    fn expected() -> String {
+        format!("{} or nil", T::expected())
+    }
+
+    fn from_vari(value: Value) -> Option<Self> {
+        match value {
+            Value::Nil => Some(None),
+            value => T::from_vari(value).map(Some),
+        }
+    }
+}
+
+// every item has to convert for the list to
+impl<T: FromVari> FromVari for Vec<T> {
+    fn expected() -> String {
+        "a list".to_owned()
+    }
+
+    fn from_vari(value: Value) -> Option<Self> {
+        match value {
+            Value::List(items) => items.into_iter().map(T::from_vari).collect(),
+            _ => None,
+        }
+    }
+}
+
+impl IntoVari for Value {
+    fn into_vari(self) -> Result<Value, String> {
+        Ok(self)
+    }
+}
+
+impl IntoVari for () {
+    fn into_vari(self) -> Result<Value, String> {
+        Ok(Value::Nil)
+    }
+}
+
+impl IntoVari for f64 {
+    fn into_vari(self) -> Result<Value, String> {
+        Ok(Value::Num(self))
+    }
+}
+
+impl IntoVari for i64 {
+    fn into_vari(self) -> Result<Value, String> {
+        Ok(Value::Num(self as f64))
+    }
+}
+
+impl IntoVari for bool {
+    fn into_vari(self) -> Result<Value, String> {
+        Ok(Value::Bool(self))
+    }
+}
+
+impl IntoVari for String {
+    fn into_vari(self) -> Result<Value, String> {
+        Ok(Value::String(self))
+    }
+}
+
+impl IntoVari for &str {
+    fn into_vari(self) -> Result<Value, String> {
+        Ok(Value::String(self.to_owned()))
+    }
+}
+
+impl<T: IntoVari> IntoVari for Option<T> {
+    fn into_vari(self) -> Result<Value, String> {
+        match self {
+            Some(value) => value.into_vari(),
+            None => Ok(Value::Nil),
+        }
+    }
+}
+
+impl<T: IntoVari> IntoVari for Vec<T> {
+    fn into_vari(self) -> Result<Value, String> {
+        self.into_iter()
+            .map(T::into_vari)
+            .collect::<Result<_, _>>()
+            .map(Value::List)
+    }
+}
+
+impl<T: IntoVari, E: Display> IntoVari for Result<T, E> {
+    fn into_vari(self) -> Result<Value, String> {
+        match self {
+            Ok(value) => value.into_vari(),
+            Err(err) => Err(err.to_string()),
+        }
+    }
+}
+
+// A Rust closure that can be called from vari, implemented for
+// closures taking up to six `FromVari` parameters. `Args` is the
+// parameter tuple, it only exists to tell the implementations apart.
+pub trait HostFunction<Args> {
+    fn arity() -> Arity;
+
+    // `name` is the function's vari name, for error messages
+    fn call(&self, name: &str, args: Vec<Value>) -> Result<Value, String>;
+}
+
+// takes the next argument, a missing one is passed on as nil
+// so optional parameters can turn it into None
+fn next_arg<T: FromVari>(
+    name: &str,
+    args: &mut impl Iterator<Item = Value>,
+    position: usize,
+) -> Result<T, String> {
+    let value = args.next().unwrap_or(Value::Nil);
+    let type_name = value.type_name();
+
+    T::from_vari(value).ok_or_else(|| {
+        format!(
+            "'{}' expects {} as argument {}, got {}.",
+            name,
+            T::expected(),
+            position,
+            type_name
+        )
+    })
+}
+
+macro_rules! host_function {
+    ($($param:ident),*) => {
+        impl<Func, R, $($param),*> HostFunction<($($param,)*)> for Func
+        where
+            Func: Fn($($param),*) -> R,
+            R: IntoVari,
+            $($param: FromVari,)*
+        {
+            fn arity() -> Arity {
+                let optional: &[bool] = &[$($param::OPTIONAL),*];
+                // everything up to the last required parameter must be passed
+                let min = optional.iter().rposition(|optional| !optional).map_or(0, |i| i + 1);
+
+                if min == optional.len() {
+                    Arity::Exact(min)
+                } else {
+                    Arity::Range(min, optional.len())
+                }
+            }
+
+            #[allow(non_snake_case, unused_variables, unused_mut)]
+            fn call(&self, name: &str, args: Vec<Value>) -> Result<Value, String> {
+                let mut args = args.into_iter();
+                let mut position = 0;
+                $(
+                    position += 1;
+                    let $param = next_arg::<$param>(name, &mut args, position)?;
+                )*
+                self($($param),*).into_vari()
+            }
+        }
+    };
+}
+
+host_function!();
+host_function!(A);
+host_function!(A, B);
+host_function!(A, B, C);
+host_function!(A, B, C, D);
+host_function!(A, B, C, D, E);
+host_function!(A, B, C, D, E, F);
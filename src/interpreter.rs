@@ -1,51 +1,408 @@
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
 use std::rc::Rc;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use crate::environment::Environment;
-use crate::expr::{Expr, ExprVisitor};
-use crate::procedure::Procedure;
+use crate::expr::{Expr, ExprVisitor, MatchArm, Pattern};
+use crate::fields::Fields;
+use crate::procedure::{Arity, Procedure, StructType};
+use crate::sandbox::{
+    self, stack_position, Capability, Limit, Limits, ALL_CAPABILITIES, MAX_VALUE_DEPTH,
+};
+use crate::stdlib;
 use crate::stmt::{Stmt, StmtVisitor};
-use crate::token::TokenType;
-use crate::vari::VariError;
-use crate::vari::VariTypes;
+use crate::token::{Token, TokenType};
+use crate::vari::{format_number, Items, VariError, VariTypes};
 
+// a call to a user defined function that hasn't returned yet
 #[derive(Debug, Clone)]
+pub struct Frame {
+    pub name: String,
+    // line the function was declared on
+    pub def_line: usize,
+    // line of the statement that made the call
+    pub call_line: usize,
+}
+
+// Lets tools like the debugger observe a running program.
+// The hook is taken out of the interpreter while one of its
+// methods runs, so code it evaluates doesn't trigger it again.
+pub trait ExecutionHook {
+    // called before each statement that has a source line, an error
+    // stops the program there
+    fn on_statement(
+        &mut self,
+        _interpreter: &mut Interpreter,
+        _line: usize,
+    ) -> Result<(), VariError> {
+        Ok(())
+    }
+
+    // called once the callee's frame is on top of `Interpreter::frames`
+    fn on_call(&mut self, _interpreter: &mut Interpreter) {}
+
+    // called just before the returning function's frame is popped
+    fn on_return(&mut self, _interpreter: &mut Interpreter) {}
+}
+
 pub struct Interpreter {
     pub globals: Rc<RefCell<Environment>>,
     env: Rc<RefCell<Environment>>,
+    // line of the statement being executed
+    line: usize,
+    frames: Vec<Frame>,
+    hook: Option<Box<dyn ExecutionHook>>,
+    // where `print`, `eprint` and natives that read input go
+    output: Box<dyn Write>,
+    error_output: Box<dyn Write>,
+    input: Box<dyn BufRead>,
+    capabilities: Vec<Capability>,
+    limits: Limits,
+    // usage counted against `limits` since the last `reset_usage`
+    steps: u64,
+    started: Instant,
+    // where the stack was when the usage was reset
+    stack_base: usize,
+}
+
+type ListPtr = *const Items;
+
+// `open` holds the pairs of lists being compared, meeting a pair
+// again inside itself adds nothing new, so it counts as equal.
+// Values nested past `MAX_VALUE_DEPTH` are a `Limit::Depth`.
+fn values_equal(
+    a: &VariTypes,
+    b: &VariTypes,
+    open: &mut Vec<(ListPtr, ListPtr)>,
+    depth: usize,
+) -> Result<bool, Limit> {
+    let equal = match (a, b) {
+        (VariTypes::Nil, VariTypes::Nil) => true,
+        (VariTypes::Num(l), VariTypes::Num(r)) => l.to_bits() == r.to_bits(),
+        (VariTypes::String(l), VariTypes::String(r)) => l == r,
+        (VariTypes::Boolean(l), VariTypes::Boolean(r)) => l == r,
+        (VariTypes::List(_), VariTypes::List(_))
+        | (VariTypes::Struct(_), VariTypes::Struct(_))
+        | (VariTypes::Instance(..), VariTypes::Instance(..))
+            if depth >= MAX_VALUE_DEPTH =>
+        {
+            return Err(Limit::Depth)
+        }
+        // lists are equal when their items are
+        (VariTypes::List(l), VariTypes::List(r)) => {
+            let pair = (Rc::as_ptr(l), Rc::as_ptr(r));
+            if Rc::ptr_eq(l, r) || open.contains(&pair) {
+                return Ok(true);
+            }
+
+            open.push(pair);
+            let (l, r) = (l.borrow(), r.borrow());
+            let mut equal = l.len() == r.len();
+            for (l, r) in l.iter().zip(r.iter()) {
+                if !equal {
+                    break;
+                }
+                equal = values_equal(l, r, open, depth + 1)?;
+            }
+            open.pop();
+            equal
+        }
+        // structs are equal when they have the same fields with equal
+        // values, in any order, instances also need the same type
+        (VariTypes::Struct(l), VariTypes::Struct(r)) => fields_equal(l, r, open, depth)?,
+        (VariTypes::Instance(l_type, l), VariTypes::Instance(r_type, r)) => {
+            Rc::ptr_eq(l_type, r_type) && fields_equal(l, r, open, depth)?
+        }
+        _ => false,
+    };
+
+    Ok(equal)
 }
-pub fn clock(_: &Vec<VariTypes>) -> VariTypes {
-    VariTypes::Num(
+
+fn fields_equal(
+    l: &Fields<VariTypes>,
+    r: &Fields<VariTypes>,
+    open: &mut Vec<(ListPtr, ListPtr)>,
+    depth: usize,
+) -> Result<bool, Limit> {
+    if l.len() != r.len() {
+        return Ok(false);
+    }
+
+    for (name, l) in l.iter() {
+        match r.get(name) {
+            Some(r) if values_equal(l, r, open, depth + 1)? => {}
+            _ => return Ok(false),
+        }
+    }
+    Ok(true)
+}
+
+pub fn clock(_: &mut Interpreter, _: &[VariTypes]) -> Result<VariTypes, VariError> {
+    Ok(VariTypes::Num(
         SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .unwrap()
             .as_millis() as f64,
-    )
+    ))
+}
+
+// assert(condition, message?)
+pub fn assert(interpreter: &mut Interpreter, args: &[VariTypes]) -> Result<VariTypes, VariError> {
+    if interpreter.is_true(Box::new(args[0].clone())) {
+        return Ok(VariTypes::Nil);
+    }
+
+    match args.get(1) {
+        Some(msg) => {
+            let msg = interpreter.stringify(msg.clone());
+            Err(interpreter.error(format!("Assertion failed: {}", msg)))
+        }
+        None => Err(interpreter.error("Assertion failed.".to_owned())),
+    }
+}
+
+// assert_eq(actual, expected)
+pub fn assert_eq(
+    interpreter: &mut Interpreter,
+    args: &[VariTypes],
+) -> Result<VariTypes, VariError> {
+    if interpreter.is_equal(Box::new(args[0].clone()), Box::new(args[1].clone()))? {
+        return Ok(VariTypes::Nil);
+    }
+
+    let actual = interpreter.stringify(args[0].clone());
+    let expected = interpreter.stringify(args[1].clone());
+    Err(interpreter.error(format!("Assertion failed: {} != {}", actual, expected)))
 }
 
 impl Interpreter {
     pub fn new() -> Self {
+        Self::with_capabilities(ALL_CAPABILITIES)
+    }
+
+    // an interpreter whose natives can only do what `capabilities` allow
+    pub fn with_capabilities(capabilities: &[Capability]) -> Self {
         let globals = Rc::new(RefCell::new(Environment::new()));
 
-        let clock_procedure = Procedure::Native {
-            name: String::from("clock"),
-            arity: 0,
-            body: Box::new(clock),
+        let mut interpreter = Self {
+            globals: Rc::clone(&globals),
+            env: Rc::new(RefCell::new(Environment::from(&globals))),
+            line: 0,
+            frames: vec![],
+            hook: None,
+            output: Box::new(io::stdout()),
+            error_output: Box::new(io::stderr()),
+            input: Box::new(io::BufReader::new(io::stdin())),
+            capabilities: capabilities.to_vec(),
+            limits: Limits::default(),
+            steps: 0,
+            started: Instant::now(),
+            stack_base: stack_position(),
         };
 
-        globals
+        interpreter.define_native("clock", Arity::Exact(0), clock);
+        interpreter.define_native("assert", Arity::Range(1, 2), assert);
+        interpreter.define_native("assert_eq", Arity::Exact(2), assert_eq);
+        stdlib::define(&mut interpreter);
+
+        interpreter
+    }
+
+    // adds a function implemented in Rust to the global scope
+    pub fn define_native<F>(&mut self, name: &str, arity: Arity, body: F)
+    where
+        F: Fn(&mut Interpreter, &[VariTypes]) -> Result<VariTypes, VariError> + 'static,
+    {
+        let procedure = Procedure::Native {
+            name: name.to_owned(),
+            arity,
+            body: Rc::new(body),
+        };
+
+        self.globals
             .borrow_mut()
-            .define("clock".to_owned(), VariTypes::Callable(clock_procedure));
+            .define(name.to_owned(), VariTypes::Callable(procedure));
+    }
 
-        Self {
-            globals: Rc::clone(&globals),
-            env: Rc::new(RefCell::new(Environment::from(&globals))),
+    pub fn set_output(&mut self, output: Box<dyn Write>) {
+        self.output = output;
+    }
+
+    pub fn set_error_output(&mut self, error_output: Box<dyn Write>) {
+        self.error_output = error_output;
+    }
+
+    pub fn set_input(&mut self, input: Box<dyn BufRead>) {
+        self.input = input;
+    }
+
+    pub fn output(&mut self) -> &mut dyn Write {
+        &mut self.output
+    }
+
+    pub fn error_output(&mut self) -> &mut dyn Write {
+        &mut self.error_output
+    }
+
+    pub fn input(&mut self) -> &mut dyn BufRead {
+        &mut self.input
+    }
+
+    // the `args` list scripts see, the command line after the script
+    pub fn set_args(&mut self, args: &[String]) {
+        let args = args
+            .iter()
+            .map(|arg| VariTypes::String(arg.clone()))
+            .collect();
+        self.globals
+            .borrow_mut()
+            .define("args".to_owned(), stdlib::new_list(args));
+    }
+
+    pub fn has_capability(&self, capability: Capability) -> bool {
+        self.capabilities.contains(&capability)
+    }
+
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    // starts counting steps and time from zero again
+    pub fn reset_usage(&mut self) {
+        self.steps = 0;
+        self.started = Instant::now();
+        self.stack_base = stack_position();
+    }
+
+    // recursing any further could overflow the Rust stack
+    fn check_stack(&self) -> Result<(), VariError> {
+        match self.limits.max_stack {
+            Some(max) if self.stack_base.abs_diff(stack_position()) > max => {
+                Err(VariError::LimitExceeded(self.line, Limit::Depth))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn count_step(&mut self) -> Result<(), VariError> {
+        self.steps += 1;
+
+        if matches!(self.limits.max_steps, Some(max) if self.steps > max) {
+            return Err(VariError::LimitExceeded(self.line, Limit::Steps));
+        }
+        if matches!(self.limits.max_time, Some(max) if self.started.elapsed() > max) {
+            return Err(VariError::LimitExceeded(self.line, Limit::Time));
+        }
+
+        Ok(())
+    }
+
+    // sleeps for `duration`, or until the script runs out of time
+    pub fn sleep(&self, duration: Duration) -> Result<(), VariError> {
+        if let Some(max) = self.limits.max_time {
+            let left = max.saturating_sub(self.started.elapsed());
+            if duration > left {
+                thread::sleep(left);
+                return Err(VariError::LimitExceeded(self.line, Limit::Time));
+            }
         }
+
+        thread::sleep(duration);
+        Ok(())
+    }
+
+    // fails if `value` is bigger than `Limits::max_memory` allows
+    // or has structs nested deeper than `MAX_VALUE_DEPTH`
+    pub fn check_size(&self, value: &VariTypes) -> Result<(), VariError> {
+        if sandbox::struct_depth(value) > MAX_VALUE_DEPTH {
+            return Err(VariError::LimitExceeded(self.line, Limit::Depth));
+        }
+        self.check_memory(sandbox::size_of(value))
+    }
+
+    // like `check_size`, for natives to call before building something big
+    pub fn check_memory(&self, bytes: usize) -> Result<(), VariError> {
+        match self.limits.max_memory {
+            Some(max) if bytes > max => Err(VariError::LimitExceeded(self.line, Limit::Memory)),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn set_hook(&mut self, hook: Option<Box<dyn ExecutionHook>>) {
+        self.hook = hook;
+    }
+
+    // the innermost scope of the code being executed
+    pub fn environment(&self) -> Rc<RefCell<Environment>> {
+        Rc::clone(&self.env)
+    }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    // for tools that run code of their own in the middle of a
+    // program to put back the line they interrupted
+    pub fn set_line(&mut self, line: usize) {
+        self.line = line;
+    }
+
+    // user function calls in progress, outermost first
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    pub fn push_frame(&mut self, frame: Frame) -> Result<(), VariError> {
+        if matches!(self.limits.max_depth, Some(max) if self.frames.len() >= max) {
+            return Err(VariError::LimitExceeded(self.line, Limit::Depth));
+        }
+        self.check_stack()?;
+
+        self.frames.push(frame);
+
+        if let Some(mut hook) = self.hook.take() {
+            hook.on_call(self);
+            self.hook = Some(hook);
+        }
+
+        Ok(())
+    }
+
+    pub fn pop_frame(&mut self) {
+        if let Some(mut hook) = self.hook.take() {
+            hook.on_return(self);
+            self.hook = Some(hook);
+        }
+
+        if let Some(frame) = self.frames.pop() {
+            // errors in the rest of the calling statement belong to its line
+            self.line = frame.call_line;
+        }
+    }
+
+    // a runtime error on the line being executed, for
+    // expressions that don't carry a token of their own
+    pub fn error(&self, msg: String) -> VariError {
+        VariError::RuntimeError(self.line, msg)
     }
 
     fn execute(&mut self, statement: Stmt) -> Result<(), VariError> {
+        self.count_step()?;
+        self.check_stack()?;
+
+        if let Some(line) = statement.line() {
+            self.line = line;
+
+            if let Some(mut hook) = self.hook.take() {
+                let result = hook.on_statement(self, line);
+                self.hook = Some(hook);
+                result?;
+            }
+        }
+
         self.visit_stmt(statement)
     }
 
@@ -86,347 +443,771 @@ impl Interpreter {
     }
 
     pub fn stringify(&self, vari_obj: VariTypes) -> String {
-        // TODO: maybe move this to the enums to_string() ?
-        match vari_obj {
-            VariTypes::Nil => {
-                return "nil".to_owned();
-            }
-            VariTypes::Num(v) => return v.to_string(),
-            VariTypes::Boolean(b) => return b.to_string(),
-            VariTypes::String(s) => return s,
-            VariTypes::Struct(map) => {
-                return format!("{:?}", map);
-            }
-            VariTypes::Callable(fun) => match fun {
-                Procedure::User { name, .. } => {
-                    format!("<fn {}>", name.lexeme)
-                }
-                Procedure::Native { name, .. } => {
-                    format!("<native fn {}>", name)
-                }
-            },
-        }
+        vari_obj.to_string()
     }
 
-    pub fn evaluate(&mut self, expr: Expr) -> Box<VariTypes> {
+    pub fn evaluate(&mut self, expr: Expr) -> Result<Box<VariTypes>, VariError> {
+        self.check_stack()?;
         return self.visit_expr(expr);
     }
 
-    // for future error handling
-    fn _check_type(&self, a: Box<VariTypes>, e: VariTypes) -> bool {
-        match e {
-            VariTypes::Nil => return matches!(*a, VariTypes::Nil),
-            VariTypes::Num(_) => return matches!(*a, VariTypes::Num(_)),
-            VariTypes::String(_) => return matches!(*a, VariTypes::String(_)),
-            VariTypes::Boolean(_) => return matches!(*a, VariTypes::Boolean(_)),
-            VariTypes::Struct(_) => return matches!(*a, VariTypes::Struct(_)),
-            VariTypes::Callable(_) => return matches!(*a, VariTypes::Callable(_)),
-        }
+    // evaluates `expr` with `env` as the current scope
+    pub fn evaluate_in(
+        &mut self,
+        expr: Expr,
+        env: Rc<RefCell<Environment>>,
+    ) -> Result<Box<VariTypes>, VariError> {
+        let tmp_env = std::mem::replace(&mut self.env, env);
+        let result = self.evaluate(expr);
+        self.env = tmp_env;
+        return result;
     }
 
     // problematic (true == false)
-    fn is_equal(&self, a: Box<VariTypes>, b: Box<VariTypes>) -> bool {
-        if let (VariTypes::Nil, VariTypes::Nil) = (*a.clone(), *b.clone()) {
-            return true;
+    pub fn is_equal(&self, a: Box<VariTypes>, b: Box<VariTypes>) -> Result<bool, VariError> {
+        values_equal(&a, &b, &mut vec![], 0)
+            .map_err(|limit| VariError::LimitExceeded(self.line, limit))
+    }
+
+    pub fn is_true(&mut self, object: Box<VariTypes>) -> bool {
+        match *object {
+            VariTypes::Nil => return false,
+            VariTypes::Boolean(b) => return b,
+            _ => return true,
         }
+    }
+}
 
-        if let VariTypes::Nil = *a.clone() {
-            return false;
+// Every arm of the visitors below calls out to its own method.
+// The visitors recurse once per nested expression and function call,
+// and keeping the arms' temporaries out of their stack frames lets
+// scripts recurse much deeper before the Rust stack runs out.
+impl Interpreter {
+    fn binary(&mut self, lhs: Expr, op: Token, rhs: Expr) -> Result<Box<VariTypes>, VariError> {
+        // `a + b + c` nests to the left as deep as the chain is long,
+        // walk down to `a` and apply the operators on the way back up
+        let mut operations = vec![(op, rhs)];
+        let mut lhs = lhs;
+        while let Expr::Binary {
+            lhs: inner,
+            op,
+            rhs,
+        } = lhs
+        {
+            operations.push((op, *rhs));
+            lhs = *inner;
         }
 
-        if let VariTypes::Nil = *b.clone() {
-            return false;
+        let mut value = self.evaluate(lhs)?;
+        for (op, rhs) in operations.into_iter().rev() {
+            let right = self.evaluate(rhs)?;
+            value = self.operate(op, *value, *right)?;
         }
 
-        if let (VariTypes::Num(l), VariTypes::Num(r)) = (*a.clone(), *b.clone()) {
-            return l.to_bits() == r.to_bits();
+        Ok(value)
+    }
+
+    fn operate(
+        &mut self,
+        op: Token,
+        left: VariTypes,
+        right: VariTypes,
+    ) -> Result<Box<VariTypes>, VariError> {
+        // one match over both operands keeps this frame small, see above
+        let value = match (op.token_type, left, right) {
+            (TokenType::ISEQ, l, r) => VariTypes::Boolean(self.is_equal(Box::new(l), Box::new(r))?),
+            (TokenType::NE, l, r) => VariTypes::Boolean(!self.is_equal(Box::new(l), Box::new(r))?),
+            // `p is Point`, whether p was built from that struct type
+            (TokenType::IS, value, VariTypes::Callable(Procedure::Struct(struct_type))) => {
+                VariTypes::Boolean(matches!(
+                    value,
+                    VariTypes::Instance(of, _) if Rc::ptr_eq(&of, &struct_type)
+                ))
+            }
+            (TokenType::IS, _, _) => {
+                return Err(VariError::RuntimeError(
+                    op.line,
+                    "Right side of 'is' must be a struct type.".to_owned(),
+                ))
+            }
+            (TokenType::PLUS, VariTypes::Num(l), VariTypes::Num(r)) => VariTypes::Num(l + r),
+            (TokenType::PLUS, VariTypes::String(l), VariTypes::String(r)) => {
+                let value = VariTypes::String(l + &r);
+                self.check_size(&value)?;
+                value
+            }
+            (TokenType::PLUS, VariTypes::List(l), VariTypes::List(r)) => {
+                let mut items = l.borrow().clone();
+                items.extend(r.borrow().iter().cloned());
+                let value = VariTypes::List(Rc::new(Items::new(items)));
+                self.check_size(&value)?;
+                value
+            }
+            (TokenType::PLUS, _, _) => {
+                return Err(VariError::RuntimeError(
+                    op.line,
+                    "Operands must be two numbers, two strings or two lists.".to_owned(),
+                ))
+            }
+            (TokenType::MINUS, VariTypes::Num(l), VariTypes::Num(r)) => VariTypes::Num(l - r),
+            (TokenType::SLASH, VariTypes::Num(l), VariTypes::Num(r)) => VariTypes::Num(l / r),
+            (TokenType::STAR, VariTypes::Num(l), VariTypes::Num(r)) => VariTypes::Num(l * r),
+            (TokenType::MODULO, VariTypes::Num(l), VariTypes::Num(r)) => VariTypes::Num(l % r),
+            (TokenType::GT, VariTypes::Num(l), VariTypes::Num(r)) => VariTypes::Boolean(l > r),
+            (TokenType::LT, VariTypes::Num(l), VariTypes::Num(r)) => VariTypes::Boolean(l < r),
+            (TokenType::GE, VariTypes::Num(l), VariTypes::Num(r)) => VariTypes::Boolean(l >= r),
+            (TokenType::LE, VariTypes::Num(l), VariTypes::Num(r)) => VariTypes::Boolean(l <= r),
+            // strings compare lexicographically, by code point
+            (TokenType::GT, VariTypes::String(l), VariTypes::String(r)) => {
+                VariTypes::Boolean(l > r)
+            }
+            (TokenType::LT, VariTypes::String(l), VariTypes::String(r)) => {
+                VariTypes::Boolean(l < r)
+            }
+            (TokenType::GE, VariTypes::String(l), VariTypes::String(r)) => {
+                VariTypes::Boolean(l >= r)
+            }
+            (TokenType::LE, VariTypes::String(l), VariTypes::String(r)) => {
+                VariTypes::Boolean(l <= r)
+            }
+            (TokenType::GT | TokenType::LT | TokenType::GE | TokenType::LE, _, _) => {
+                return Err(VariError::RuntimeError(
+                    op.line,
+                    format!(
+                        "Operands of '{}' must be two numbers or two strings.",
+                        op.lexeme
+                    ),
+                ))
+            }
+            _ => {
+                return Err(VariError::RuntimeError(
+                    op.line,
+                    format!("Operands of '{}' must be numbers.", op.lexeme),
+                ))
+            }
+        };
+
+        Ok(Box::new(value))
+    }
+
+    fn unary(&mut self, op: Token, rhs: Expr) -> Result<Box<VariTypes>, VariError> {
+        let right = self.evaluate(rhs)?;
+        match op.token_type {
+            TokenType::NOT => {
+                return Ok(Box::new(VariTypes::Boolean(!self.is_true(right))));
+            }
+            TokenType::MINUS => {
+                if let VariTypes::Num(num) = *right {
+                    return Ok(Box::new(VariTypes::Num(-num)));
+                }
+
+                return Err(VariError::RuntimeError(
+                    op.line,
+                    "Operand must be a number.".to_owned(),
+                ));
+            }
+            _ => {}
         }
 
-        if let (VariTypes::String(l), VariTypes::String(r)) = (*a.clone(), *b.clone()) {
-            return l == r;
+        unreachable!();
+    }
+
+    fn assign(&mut self, name: Token, value_expr: Expr) -> Result<Box<VariTypes>, VariError> {
+        let value = self.evaluate(value_expr)?;
+        self.env.borrow_mut().assign(name, (*value).clone())?;
+        return Ok(value);
+    }
+
+    fn logical(
+        &mut self,
+        lhs: Expr,
+        operator: Token,
+        rhs: Expr,
+    ) -> Result<Box<VariTypes>, VariError> {
+        // a chain nests to the left like it does in `binary`
+        let mut operations = vec![(operator, rhs)];
+        let mut lhs = lhs;
+        while let Expr::Logical {
+            lhs: inner,
+            operator,
+            rhs,
+        } = lhs
+        {
+            operations.push((operator, *rhs));
+            lhs = *inner;
         }
 
-        if let (VariTypes::Boolean(l), VariTypes::Boolean(r)) = (*a.clone(), *b.clone()) {
-            return l == r;
+        let mut value = self.evaluate(lhs)?;
+        for (operator, rhs) in operations.into_iter().rev() {
+            // short circuit: `or` stops at the first truthy
+            // operand, `and` at the first falsy one
+            let truthy = self.is_true(value.clone());
+            if truthy != (operator.token_type == TokenType::OR) {
+                value = self.evaluate(rhs)?;
+            }
         }
 
-        // TODO: handle VariTypes::Objects
+        return Ok(value);
+    }
+
+    fn struct_literal(&mut self, values: Fields<Expr>) -> Result<Box<VariTypes>, VariError> {
+        let mut map = Fields::new();
+        for (name, expr) in values {
+            map.insert(name, *self.evaluate(expr)?);
+        }
 
-        return false;
+        let value = VariTypes::Struct(map);
+        self.check_size(&value)?;
+        return Ok(Box::new(value));
     }
 
-    fn is_true(&mut self, object: Box<VariTypes>) -> bool {
-        match *object {
-            VariTypes::Nil => return false,
-            VariTypes::Boolean(b) => return b,
-            _ => return true,
+    fn construct(
+        &mut self,
+        name: Token,
+        values: Fields<Expr>,
+    ) -> Result<Box<VariTypes>, VariError> {
+        let struct_type = match self.env.borrow_mut().get(name.clone())? {
+            VariTypes::Callable(Procedure::Struct(struct_type)) => struct_type,
+            _ => {
+                return Err(VariError::RuntimeError(
+                    name.line,
+                    format!("'{}' isn't a struct type.", name.lexeme),
+                ))
+            }
+        };
+
+        let mut given = Fields::new();
+        for (field, expr) in values {
+            given.insert(field, *self.evaluate(expr)?);
         }
+        return Ok(Box::new(struct_type.construct(self, given)?));
     }
-}
 
-impl ExprVisitor<Box<VariTypes>> for Interpreter {
-    fn visit_expr(&mut self, expr: Expr) -> Box<VariTypes> {
-        match expr {
-            Expr::Binary { lhs, op, rhs } => {
-                let left = self.evaluate(*lhs);
-                let right = self.evaluate(*rhs);
-
-                match op.token_type {
-                    TokenType::MINUS => {
-                        if let (VariTypes::Num(l), VariTypes::Num(r)) = (*left, *right) {
-                            return Box::new(VariTypes::Num(l - r));
-                        }
-                    }
-                    TokenType::SLASH => {
-                        if let (VariTypes::Num(l), VariTypes::Num(r)) = (*left, *right) {
-                            return Box::new(VariTypes::Num(l / r));
-                        }
-                    }
-                    TokenType::STAR => {
-                        if let (VariTypes::Num(l), VariTypes::Num(r)) = (*left, *right) {
-                            return Box::new(VariTypes::Num(l * r));
-                        }
-                    }
-                    TokenType::MODULO => {
-                        if let (VariTypes::Num(l), VariTypes::Num(r)) = (*left, *right) {
-                            return Box::new(VariTypes::Num(l % r));
-                        }
-                    }
-                    TokenType::PLUS => {
-                        // handling addition of numbers
-                        if let (VariTypes::Num(l), VariTypes::Num(r)) =
-                            (*left.clone(), *right.clone())
-                        {
-                            return Box::new(VariTypes::Num(l + r));
-                        }
+    // the first arm whose pattern matches and whose guard is true gives
+    // the value, its bindings are only visible in the guard and body
+    fn match_expr(
+        &mut self,
+        keyword: Token,
+        value: Expr,
+        arms: Vec<MatchArm>,
+    ) -> Result<Box<VariTypes>, VariError> {
+        let value = *self.evaluate(value)?;
 
-                        // handling addition of string type
-                        if let (VariTypes::String(s1), VariTypes::String(s2)) = (*left, *right) {
-                            return Box::new(VariTypes::String(format!("{}{}", s1, s2)));
-                        }
-                    }
+        for arm in arms {
+            let mut bindings = vec![];
+            if !self.match_pattern(&arm.pattern, &value, &mut bindings)? {
+                continue;
+            }
 
-                    TokenType::GT => {
-                        if let (VariTypes::Num(l), VariTypes::Num(r)) = (*left, *right) {
-                            return Box::new(VariTypes::Boolean(l > r));
-                        }
-                    }
-                    TokenType::LT => {
-                        if let (VariTypes::Num(l), VariTypes::Num(r)) = (*left, *right) {
-                            return Box::new(VariTypes::Boolean(l < r));
-                        }
+            let env = Rc::new(RefCell::new(Environment::from(&self.env)));
+            for (name, bound) in bindings {
+                env.borrow_mut().define(name, bound);
+            }
+            if let Some(guard) = arm.guard {
+                let passed = self.evaluate_in(guard, Rc::clone(&env))?;
+                if !self.is_true(passed) {
+                    continue;
+                }
+            }
+            return self.evaluate_in(arm.body, env);
+        }
+
+        Err(VariError::RuntimeError(
+            keyword.line,
+            format!("No arm of 'match' matches {}.", value.repr()),
+        ))
+    }
+
+    // whether `value` fits `pattern`, adding what it binds to `bindings`
+    fn match_pattern(
+        &mut self,
+        pattern: &Pattern,
+        value: &VariTypes,
+        bindings: &mut Vec<(String, VariTypes)>,
+    ) -> Result<bool, VariError> {
+        match pattern {
+            Pattern::Literal(literal) => {
+                self.is_equal(Box::new(literal.clone()), Box::new(value.clone()))
+            }
+            Pattern::Wildcard => Ok(true),
+            Pattern::Binding(name) => {
+                bindings.push((name.lexeme.clone(), value.clone()));
+                Ok(true)
+            }
+            Pattern::Alternatives(alternatives) => {
+                let bound = bindings.len();
+                for alternative in alternatives {
+                    if self.match_pattern(alternative, value, bindings)? {
+                        return Ok(true);
                     }
-                    TokenType::GE => {
-                        if let (VariTypes::Num(l), VariTypes::Num(r)) = (*left, *right) {
-                            return Box::new(VariTypes::Boolean(l >= r));
+                    bindings.truncate(bound);
+                }
+                Ok(false)
+            }
+            Pattern::Struct { name, fields } => {
+                if let Some(name) = name {
+                    let of_type = match self.env.borrow_mut().get(name.clone())? {
+                        VariTypes::Callable(Procedure::Struct(struct_type)) => matches!(
+                            value,
+                            VariTypes::Instance(of, _) if Rc::ptr_eq(of, &struct_type)
+                        ),
+                        _ => {
+                            return Err(VariError::RuntimeError(
+                                name.line,
+                                format!("'{}' isn't a struct type.", name.lexeme),
+                            ))
                         }
+                    };
+                    if !of_type {
+                        return Ok(false);
                     }
-                    TokenType::LE => {
-                        if let (VariTypes::Num(l), VariTypes::Num(r)) = (*left, *right) {
-                            return Box::new(VariTypes::Boolean(l <= r));
+                }
+
+                let Some(values) = value.fields() else {
+                    return Ok(false);
+                };
+                for (field, pattern) in fields {
+                    match values.get(&field.lexeme) {
+                        Some(field_value) => {
+                            if !self.match_pattern(pattern, field_value, bindings)? {
+                                return Ok(false);
+                            }
                         }
+                        None => return Ok(false),
                     }
-                    TokenType::NE => {
-                        return Box::new(VariTypes::Boolean(!self.is_equal(left, right)))
-                    }
-                    TokenType::ISEQ => {
-                        return Box::new(VariTypes::Boolean(self.is_equal(left, right)))
-                    }
-                    _ => unreachable!(),
                 }
-
-                unreachable!()
+                Ok(true)
             }
-            Expr::Unary { op, rhs } => {
-                let right = self.evaluate(*rhs);
-                match op.token_type {
-                    TokenType::NOT => {
-                        return Box::new(VariTypes::Boolean(!self.is_true(right)));
-                    }
-                    TokenType::MINUS => {
-                        if let VariTypes::Num(num) = *right {
-                            return Box::new(VariTypes::Num(-num));
-                        }
+            Pattern::List { head, rest } => {
+                let VariTypes::List(items) = value else {
+                    return Ok(false);
+                };
+                // a copy, so the list isn't borrowed while matching its items
+                let items = items.borrow().clone();
 
-                        unreachable!();
+                let tail: &[Pattern] = rest.as_ref().map_or(&[], |(_, tail)| tail);
+                let fits = match rest {
+                    Some(_) => items.len() >= head.len() + tail.len(),
+                    None => items.len() == head.len(),
+                };
+                if !fits {
+                    return Ok(false);
+                }
+
+                let tail_start = items.len() - tail.len();
+                let patterns = head
+                    .iter()
+                    .zip(&items)
+                    .chain(tail.iter().zip(&items[tail_start..]));
+                for (pattern, item) in patterns {
+                    if !self.match_pattern(pattern, item, bindings)? {
+                        return Ok(false);
                     }
-                    _ => {}
                 }
 
-                unreachable!();
+                if let Some((Some(name), _)) = rest {
+                    let middle = items[head.len()..tail_start].to_vec();
+                    let middle = VariTypes::List(Rc::new(Items::new(middle)));
+                    self.check_size(&middle)?;
+                    bindings.push((name.lexeme.clone(), middle));
+                }
+                Ok(true)
             }
-            Expr::Grouping { expr } => {
-                return self.evaluate(*expr);
+        }
+    }
+
+    fn list_literal(&mut self, items: Vec<Expr>) -> Result<Box<VariTypes>, VariError> {
+        let mut values = vec![];
+        for item in items {
+            values.push(*self.evaluate(item)?);
+        }
+
+        let value = VariTypes::List(Rc::new(Items::new(values)));
+        self.check_size(&value)?;
+        return Ok(Box::new(value));
+    }
+
+    // turns an index into a position in something `len` long,
+    // negative indexes count back from the end
+    fn position(&self, bracket: &Token, index: VariTypes, len: usize) -> Result<usize, VariError> {
+        let index = match index {
+            VariTypes::Num(n) if n.fract() == 0.0 => n,
+            other => {
+                return Err(VariError::RuntimeError(
+                    bracket.line,
+                    format!("Index must be a whole number, got {}.", other.type_name()),
+                ))
             }
-            Expr::Literal { value } => {
-                return value;
+        };
+
+        let position = if index < 0.0 {
+            index + len as f64
+        } else {
+            index
+        };
+        if position < 0.0 || position >= len as f64 {
+            return Err(VariError::RuntimeError(
+                bracket.line,
+                format!(
+                    "Index {} is out of range for length {}.",
+                    format_number(index),
+                    len
+                ),
+            ));
+        }
+
+        Ok(position as usize)
+    }
+
+    fn index(
+        &mut self,
+        expr: Expr,
+        bracket: Token,
+        index: Expr,
+    ) -> Result<Box<VariTypes>, VariError> {
+        let obj = self.evaluate(expr)?;
+        let index = self.evaluate(index)?;
+        match *obj {
+            VariTypes::List(items) => {
+                let items = items.borrow();
+                let position = self.position(&bracket, *index, items.len())?;
+                return Ok(Box::new(items[position].clone()));
             }
-            Expr::Variable { value } => {
-                return Box::new(self.env.borrow_mut().get(value));
+            // the character at that position, as a string
+            VariTypes::String(s) => {
+                let position = self.position(&bracket, *index, s.chars().count())?;
+                let c = s.chars().nth(position).unwrap();
+                return Ok(Box::new(VariTypes::String(c.to_string())));
             }
-            Expr::Assign { name, value_expr } => {
-                let value = self.evaluate(*value_expr);
-                self.env.borrow_mut().assign(name.lexeme, (*value).clone());
-                return value;
+            _ => Err(VariError::RuntimeError(
+                bracket.line,
+                "Only lists and strings can be indexed.".to_owned(),
+            )),
+        }
+    }
+
+    fn set_index(
+        &mut self,
+        expr: Expr,
+        bracket: Token,
+        index: Expr,
+        value: Expr,
+    ) -> Result<Box<VariTypes>, VariError> {
+        let obj = self.evaluate(expr)?;
+        let index = self.evaluate(index)?;
+        let value = self.evaluate(value)?;
+        match *obj {
+            VariTypes::List(items) => {
+                let mut items = items.borrow_mut();
+                let position = self.position(&bracket, *index, items.len())?;
+                items[position] = (*value).clone();
+                return Ok(value);
             }
-            Expr::Logical { lhs, operator, rhs } => {
-                let lhs = self.evaluate(*lhs);
+            VariTypes::String(_) => Err(VariError::RuntimeError(
+                bracket.line,
+                "Strings can't be changed, build a new one instead.".to_owned(),
+            )),
+            _ => Err(VariError::RuntimeError(
+                bracket.line,
+                "Only list items can be assigned to.".to_owned(),
+            )),
+        }
+    }
 
-                if operator.token_type == TokenType::OR {
-                    if self.is_true(lhs.clone()) {
-                        return lhs;
-                    } else {
-                        if !self.is_true(lhs.clone()) {
-                            return lhs;
-                        }
-                    }
+    fn get(&mut self, expr: Expr, name: String) -> Result<Box<VariTypes>, VariError> {
+        let obj = self.evaluate(expr)?;
+        match *obj {
+            VariTypes::Struct(map) | VariTypes::Instance(_, map) => {
+                if map.contains_key(&name) {
+                    return Ok(Box::new(map[&name].clone()));
+                } else {
+                    return Err(
+                        self.error(format!("Property `{}` doesn't exist on this object.", name))
+                    );
                 }
+            }
+            _ => Err(self.error("Only structs have properties.".to_owned())),
+        }
+    }
 
-                return self.evaluate(*rhs);
+    fn set(&mut self, expr: Expr, name: String, value: Expr) -> Result<Box<VariTypes>, VariError> {
+        // like a variable assignment, the assignment's value is the new value
+        let value = self.evaluate(value)?;
+        let mut val = Some((*value).clone());
+        self.update(expr, &mut |interpreter, obj| match obj {
+            VariTypes::Struct(map) => {
+                map.insert(name.clone(), val.take().unwrap());
+                interpreter.check_size(obj)
             }
-            Expr::Struct { values } => {
-                let mut map: HashMap<String, VariTypes> = HashMap::new();
-                for (name, expr) in values {
-                    map.insert(name, *self.evaluate(expr));
+            // a declared struct only has the fields it was declared with
+            VariTypes::Instance(struct_type, map) => {
+                if !map.contains_key(&name) {
+                    return Err(interpreter.error(format!(
+                        "{} has no field '{}'.",
+                        struct_type.name(),
+                        name
+                    )));
                 }
-                return Box::from(VariTypes::Struct(map));
+                map.insert(name.clone(), val.take().unwrap());
+                interpreter.check_size(obj)
             }
-            Expr::Get { expr, name } => {
-                let obj = self.evaluate(*expr);
-                match *obj {
-                    VariTypes::Struct(map) => {
-                        if map.contains_key(&name) {
-                            // FIXME: need a mutable reference to hashmap
-                            // in order to assign properties after initialization
-                            // see Expr::Set
-                            return Box::new(map[&name].clone());
-                        } else {
-                            println!("Property `{}` doesn't exist on this object.", name);
-                            return Box::new(VariTypes::Nil);
-                        }
-                    }
-                    _ => {
-                        // TODO: handle runtime error
-                        println!("Only structs have properties.");
-                        loop {}
-                    }
-                }
+            _ => Err(interpreter.error("Can't assign to a non-struct variable.".to_owned())),
+        })?;
+
+        return Ok(value);
+    }
+
+    // Structs are values, so changing a field means changing the struct
+    // and storing it back where it came from: a variable, a field of
+    // another struct or a list item. `change` gets that struct.
+    fn update(
+        &mut self,
+        target: Expr,
+        change: &mut dyn FnMut(&mut Self, &mut VariTypes) -> Result<(), VariError>,
+    ) -> Result<(), VariError> {
+        match target {
+            Expr::Variable { value: name } => {
+                let mut obj = self.env.borrow_mut().get(name.clone())?;
+                change(self, &mut obj)?;
+                self.env.borrow_mut().assign(name, obj)
             }
-            Expr::Set { expr, name, value } => {
-                let obj = self.evaluate(*expr);
-                let val = self.evaluate(*value);
+            Expr::Grouping { expr } => self.update(*expr, change),
+            Expr::Get { expr, name } => self.update(*expr, &mut |interpreter, obj| match obj {
+                VariTypes::Struct(map) | VariTypes::Instance(_, map) => match map.get_mut(&name) {
+                    Some(field) => change(interpreter, field),
+                    None => Err(interpreter
+                        .error(format!("Property `{}` doesn't exist on this object.", name))),
+                },
+                _ => Err(interpreter.error("Only structs have properties.".to_owned())),
+            }),
+            Expr::Index {
+                expr,
+                bracket,
+                index,
+            } => {
+                let obj = self.evaluate(*expr)?;
+                let index = self.evaluate(*index)?;
                 match *obj {
-                    VariTypes::Struct(mut map) => {
-                        map.insert(name, *val);
-                        return Box::new(VariTypes::Nil);
-                    }
-                    _ => {
-                        // TODO: handle runtime error
-                        println!("Can't assign to a non-struct variable.");
-                        loop {}
+                    VariTypes::List(items) => {
+                        let mut items = items.borrow_mut();
+                        let position = self.position(&bracket, *index, items.len())?;
+                        change(self, &mut items[position])
                     }
+                    _ => Err(VariError::RuntimeError(
+                        bracket.line,
+                        "Only list items can be assigned to.".to_owned(),
+                    )),
                 }
             }
-            Expr::Call { callee, args, .. } => {
-                // should just get the identifier of function name
-                let callee = self.evaluate(*callee);
+            // anything else is a struct nobody else can see
+            _ => Err(self.error(
+                "Only fields of variables, fields and list items can be assigned to.".to_owned(),
+            )),
+        }
+    }
 
-                let mut eval_args = vec![];
+    fn call(
+        &mut self,
+        callee: Expr,
+        paren: Token,
+        args: Vec<Expr>,
+    ) -> Result<Box<VariTypes>, VariError> {
+        // should just get the identifier of function name
+        let callee = self.evaluate(callee)?;
 
-                for arg_expr in args {
-                    eval_args.push(*self.evaluate(arg_expr));
-                }
+        let mut eval_args = vec![];
 
-                match *callee {
-                    VariTypes::Callable(pro) => {
-                        if eval_args.len() == pro.arity() {
-                            return Box::new(pro.call(self, eval_args));
-                        } else {
-                            // TODO: error handling
-                            panic!(
-                                "Expected {} arguments but got {}.",
-                                pro.arity(),
-                                eval_args.len()
-                            );
-                        }
-                    }
-                    _ => {
-                        panic!("Can only call functions.");
-                    }
+        for arg_expr in args {
+            eval_args.push(*self.evaluate(arg_expr)?);
+        }
+
+        match *callee {
+            VariTypes::Callable(pro) => {
+                if pro.arity().accepts(eval_args.len()) {
+                    return Ok(Box::new(pro.call(self, eval_args)?));
+                } else {
+                    return Err(VariError::RuntimeError(
+                        paren.line,
+                        format!(
+                            "Expected {} arguments but got {}.",
+                            pro.arity(),
+                            eval_args.len()
+                        ),
+                    ));
                 }
             }
+            _ => Err(VariError::RuntimeError(
+                paren.line,
+                "Can only call functions.".to_owned(),
+            )),
         }
     }
-}
 
-impl StmtVisitor<Result<(), VariError>> for Interpreter {
-    fn visit_stmt(&mut self, stmt: Stmt) -> Result<(), VariError> {
-        match stmt {
-            Stmt::Expression(expr) => {
-                self.evaluate(expr);
-                Ok(())
-            }
-            Stmt::Print(expr) => {
-                let val = self.evaluate(expr);
-                println!("{}", self.stringify(*val));
+    fn print(&mut self, keyword: Token, expr: Expr) -> Result<(), VariError> {
+        let val = self.evaluate(expr)?;
+        let text = self.stringify(*val);
+
+        let stream = if keyword.token_type == TokenType::EPRINT {
+            &mut self.error_output
+        } else {
+            &mut self.output
+        };
+
+        writeln!(stream, "{}", text).map_err(|err| {
+            VariError::RuntimeError(keyword.line, format!("Couldn't print: {}.", err))
+        })
+    }
+
+    fn var(&mut self, name: Token, initializer: Option<Expr>) -> Result<(), VariError> {
+        match initializer {
+            Some(expr_val) => {
+                let val = self.evaluate(expr_val)?;
+                self.env.borrow_mut().define(name.lexeme, *val);
                 Ok(())
             }
-            Stmt::Var(name, initializer) => match initializer {
-                Some(expr_val) => {
-                    let val = self.evaluate(expr_val);
-                    self.env.borrow_mut().define(name.lexeme, *val);
-                    Ok(())
-                }
-                None => {
-                    self.env.borrow_mut().define(name.lexeme, VariTypes::Nil);
-                    Ok(())
-                }
-            },
-            Stmt::Block(stmt_list) => {
-                // send the *actual* env to `from` method in Env
-                let env_clone = Rc::new(RefCell::new(Environment::from(&self.env)));
-                self.execute_block(stmt_list, env_clone)?;
+            None => {
+                self.env.borrow_mut().define(name.lexeme, VariTypes::Nil);
                 Ok(())
             }
-            Stmt::If(conditional_expr, then_block, else_block) => {
-                let val = self.evaluate(conditional_expr);
-                if self.is_true(val) {
-                    self.execute(*then_block)?;
-                } else if let Some(else_stmt) = else_block {
-                    self.execute(*else_stmt)?;
-                }
+        }
+    }
 
-                Ok(())
-            }
-            Stmt::While(conditional_expr, body) => {
-                let mut val = self.evaluate(conditional_expr.clone());
+    fn block(&mut self, stmt_list: Vec<Stmt>) -> Result<(), VariError> {
+        // send the *actual* env to `from` method in Env
+        let env_clone = Rc::new(RefCell::new(Environment::from(&self.env)));
+        self.execute_block(stmt_list, env_clone)?;
+        Ok(())
+    }
 
-                while self.is_true(val.clone()) {
-                    self.execute((*body).clone())?;
-                    val = self.evaluate(conditional_expr.clone());
-                }
-                Ok(())
-            }
-            Stmt::Function(name, params, body) => {
-                let arity = params.len();
+    fn if_stmt(
+        &mut self,
+        conditional_expr: Expr,
+        then_block: Stmt,
+        else_block: Option<Box<Stmt>>,
+    ) -> Result<(), VariError> {
+        let val = self.evaluate(conditional_expr)?;
+        if self.is_true(val) {
+            self.execute(then_block)?;
+        } else if let Some(else_stmt) = else_block {
+            self.execute(*else_stmt)?;
+        }
 
-                let procedure = Procedure::User {
-                    name: name.clone(),
-                    params,
-                    body,
-                    arity,
-                    closure: Rc::clone(&self.env),
-                };
+        Ok(())
+    }
+
+    fn while_stmt(&mut self, conditional_expr: Expr, body: Stmt) -> Result<(), VariError> {
+        let mut val = self.evaluate(conditional_expr.clone())?;
 
-                self.env
-                    .borrow_mut()
-                    .define(name.lexeme, VariTypes::Callable(procedure));
+        while self.is_true(val.clone()) {
+            self.execute(body.clone())?;
+            val = self.evaluate(conditional_expr.clone())?;
+        }
+        Ok(())
+    }
+
+    fn function(
+        &mut self,
+        name: Token,
+        params: Vec<Token>,
+        body: Vec<Stmt>,
+    ) -> Result<(), VariError> {
+        let arity = params.len();
+
+        let procedure = Procedure::User {
+            name: name.clone(),
+            params,
+            body,
+            arity,
+            closure: Rc::clone(&self.env),
+        };
+
+        self.env
+            .borrow_mut()
+            .define(name.lexeme, VariTypes::Callable(procedure));
+        Ok(())
+    }
+
+    fn struct_decl(
+        &mut self,
+        name: Token,
+        fields: Vec<(Token, Option<Expr>)>,
+    ) -> Result<(), VariError> {
+        let struct_type = StructType {
+            name: name.clone(),
+            fields,
+            closure: Rc::clone(&self.env),
+        };
+
+        self.env.borrow_mut().define(
+            name.lexeme,
+            VariTypes::Callable(Procedure::Struct(Rc::new(struct_type))),
+        );
+        Ok(())
+    }
+
+    fn return_stmt(&mut self, expr: Expr) -> Result<(), VariError> {
+        let retval = *self.evaluate(expr)?;
+        Err(VariError::Return(retval))
+    }
+}
+
+impl ExprVisitor<Result<Box<VariTypes>, VariError>> for Interpreter {
+    fn visit_expr(&mut self, expr: Expr) -> Result<Box<VariTypes>, VariError> {
+        match expr {
+            Expr::Binary { lhs, op, rhs } => self.binary(*lhs, op, *rhs),
+            Expr::Unary { op, rhs } => self.unary(op, *rhs),
+            Expr::Grouping { expr } => self.evaluate(*expr),
+            Expr::Literal { value } => Ok(value),
+            Expr::Variable { value } => Ok(Box::new(self.env.borrow_mut().get(value)?)),
+            Expr::Assign { name, value_expr } => self.assign(name, *value_expr),
+            Expr::Logical { lhs, operator, rhs } => self.logical(*lhs, operator, *rhs),
+            Expr::Struct { values } => self.struct_literal(values),
+            Expr::Construct { name, values } => self.construct(name, values),
+            Expr::List { items, .. } => self.list_literal(items),
+            Expr::Index {
+                expr,
+                bracket,
+                index,
+            } => self.index(*expr, bracket, *index),
+            Expr::SetIndex {
+                expr,
+                bracket,
+                index,
+                value,
+            } => self.set_index(*expr, bracket, *index, *value),
+            Expr::Get { expr, name } => self.get(*expr, name),
+            Expr::Set { expr, name, value } => self.set(*expr, name, *value),
+            Expr::Call {
+                callee,
+                paren,
+                args,
+            } => self.call(*callee, paren, args),
+            Expr::Match {
+                keyword,
+                value,
+                arms,
+            } => self.match_expr(keyword, *value, arms),
+        }
+    }
+}
+
+impl StmtVisitor<Result<(), VariError>> for Interpreter {
+    fn visit_stmt(&mut self, stmt: Stmt) -> Result<(), VariError> {
+        match stmt {
+            Stmt::Expression(expr) => {
+                self.evaluate(expr)?;
                 Ok(())
             }
-            Stmt::Return(_, expr) => {
-                let retval = *self.evaluate(expr);
-                Err(VariError::Return(retval))
+            Stmt::Print(keyword, expr) => self.print(keyword, expr),
+            Stmt::Var(name, initializer) => self.var(name, initializer),
+            Stmt::Block(stmt_list) => self.block(stmt_list),
+            Stmt::If(_, conditional_expr, then_block, else_block) => {
+                self.if_stmt(conditional_expr, *then_block, else_block)
             }
+            Stmt::While(_, conditional_expr, body) => self.while_stmt(conditional_expr, *body),
+            Stmt::Function(name, params, body) => self.function(name, params, body),
+            Stmt::Struct(name, fields) => self.struct_decl(name, fields),
+            Stmt::Return(_, expr) => self.return_stmt(expr),
         }
     }
 }
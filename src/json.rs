@@ -0,0 +1,452 @@
+use std::fmt;
+
+// A JSON document. Objects keep their keys in source order.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+#[derive(Debug, Clone)]
+pub struct JsonError {
+    pub line: usize,
+    pub column: usize,
+    pub msg: String,
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at line {} column {}", self.msg, self.line, self.column)
+    }
+}
+
+impl Json {
+    pub fn parse(source: &str) -> Result<Json, JsonError> {
+        let mut parser = JsonParser {
+            source: source.as_bytes(),
+            current: 0,
+            depth: 0,
+        };
+
+        parser.skip_whitespace();
+        let value = parser.value()?;
+        parser.skip_whitespace();
+
+        if parser.current < parser.source.len() {
+            return Err(parser.error("Unexpected trailing characters"));
+        }
+
+        Ok(value)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Json::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Json::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Vec<Json>> {
+        match self {
+            Json::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    // builds an object from borrowed keys, handy for constructing messages
+    pub fn object(members: Vec<(&str, Json)>) -> Json {
+        Json::Object(
+            members
+                .into_iter()
+                .map(|(name, value)| (name.to_owned(), value))
+                .collect(),
+        )
+    }
+
+    // serializes the value, `indent` spaces per nesting level
+    // or everything on one line when `indent` is 0
+    pub fn stringify(&self, indent: usize) -> String {
+        let mut out = String::new();
+        self.write(&mut out, indent, 0);
+        out
+    }
+
+    fn write(&self, out: &mut String, indent: usize, level: usize) {
+        match self {
+            Json::Null => out.push_str("null"),
+            Json::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+            Json::Number(n) => out.push_str(&format_number(*n)),
+            Json::String(s) => write_string(out, s),
+            Json::Array(items) => {
+                if items.is_empty() {
+                    out.push_str("[]");
+                    return;
+                }
+
+                out.push('[');
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    newline(out, indent, level + 1);
+                    item.write(out, indent, level + 1);
+                }
+                newline(out, indent, level);
+                out.push(']');
+            }
+            Json::Object(members) => {
+                if members.is_empty() {
+                    out.push_str("{}");
+                    return;
+                }
+
+                out.push('{');
+                for (i, (name, value)) in members.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    newline(out, indent, level + 1);
+                    write_string(out, name);
+                    out.push(':');
+                    if indent > 0 {
+                        out.push(' ');
+                    }
+                    value.write(out, indent, level + 1);
+                }
+                newline(out, indent, level);
+                out.push('}');
+            }
+        }
+    }
+}
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.stringify(0))
+    }
+}
+
+fn newline(out: &mut String, indent: usize, level: usize) {
+    if indent > 0 {
+        out.push('\n');
+        out.push_str(&" ".repeat(indent * level));
+    }
+}
+
+fn format_number(n: f64) -> String {
+    if !n.is_finite() {
+        // not representable in JSON
+        return "null".to_owned();
+    }
+
+    if n.fract() == 0.0 && n.abs() < 1e15 {
+        return format!("{}", n as i64);
+    }
+
+    n.to_string()
+}
+
+fn write_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+// deeper documents are rejected rather than overflowing the stack
+const MAX_DEPTH: usize = 512;
+
+struct JsonParser<'a> {
+    source: &'a [u8],
+    current: usize,
+    // arrays and objects currently open
+    depth: usize,
+}
+
+impl JsonParser<'_> {
+    fn error(&self, msg: &str) -> JsonError {
+        let consumed = &self.source[..self.current.min(self.source.len())];
+        let line = consumed.iter().filter(|&&b| b == b'\n').count() + 1;
+        let line_start = consumed
+            .iter()
+            .rposition(|&b| b == b'\n')
+            .map_or(0, |i| i + 1);
+
+        JsonError {
+            line,
+            column: self.current - line_start + 1,
+            msg: msg.to_owned(),
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.source.get(self.current).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.peek() {
+            self.current += 1;
+        }
+    }
+
+    fn expect(&mut self, expected: u8) -> Result<(), JsonError> {
+        if self.peek() == Some(expected) {
+            self.current += 1;
+            return Ok(());
+        }
+
+        Err(self.error(&format!("Expected '{}'", expected as char)))
+    }
+
+    fn keyword(&mut self, word: &str, value: Json) -> Result<Json, JsonError> {
+        if self.source[self.current..].starts_with(word.as_bytes()) {
+            self.current += word.len();
+            return Ok(value);
+        }
+
+        Err(self.error("Unexpected character"))
+    }
+
+    fn value(&mut self) -> Result<Json, JsonError> {
+        match self.peek() {
+            None => Err(self.error("Unexpected end of input")),
+            Some(b'n') => self.keyword("null", Json::Null),
+            Some(b't') => self.keyword("true", Json::Bool(true)),
+            Some(b'f') => self.keyword("false", Json::Bool(false)),
+            Some(b'"') => Ok(Json::String(self.string()?)),
+            Some(b'[' | b'{') if self.depth == MAX_DEPTH => Err(self.error("Nested too deeply")),
+            Some(b'[') => self.nested(Self::array),
+            Some(b'{') => self.nested(Self::object),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            Some(_) => Err(self.error("Unexpected character")),
+        }
+    }
+
+    fn nested(
+        &mut self,
+        parse: fn(&mut Self) -> Result<Json, JsonError>,
+    ) -> Result<Json, JsonError> {
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn array(&mut self) -> Result<Json, JsonError> {
+        self.expect(b'[')?;
+        let mut items = vec![];
+
+        self.skip_whitespace();
+        if self.peek() == Some(b']') {
+            self.current += 1;
+            return Ok(Json::Array(items));
+        }
+
+        loop {
+            self.skip_whitespace();
+            items.push(self.value()?);
+            self.skip_whitespace();
+
+            match self.peek() {
+                Some(b',') => self.current += 1,
+                Some(b']') => {
+                    self.current += 1;
+                    return Ok(Json::Array(items));
+                }
+                _ => return Err(self.error("Expected ',' or ']' in array")),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, JsonError> {
+        self.expect(b'{')?;
+        let mut members = vec![];
+
+        self.skip_whitespace();
+        if self.peek() == Some(b'}') {
+            self.current += 1;
+            return Ok(Json::Object(members));
+        }
+
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some(b'"') {
+                return Err(self.error("Expected string key in object"));
+            }
+            let name = self.string()?;
+
+            self.skip_whitespace();
+            self.expect(b':')?;
+            self.skip_whitespace();
+            members.push((name, self.value()?));
+            self.skip_whitespace();
+
+            match self.peek() {
+                Some(b',') => self.current += 1,
+                Some(b'}') => {
+                    self.current += 1;
+                    return Ok(Json::Object(members));
+                }
+                _ => return Err(self.error("Expected ',' or '}' in object")),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<Json, JsonError> {
+        let start = self.current;
+
+        if self.peek() == Some(b'-') {
+            self.current += 1;
+        }
+
+        match self.peek() {
+            Some(b'0') => self.current += 1,
+            Some(b'1'..=b'9') => self.digits(),
+            _ => return Err(self.error("Expected digit")),
+        }
+
+        if self.peek() == Some(b'.') {
+            self.current += 1;
+            if !matches!(self.peek(), Some(b'0'..=b'9')) {
+                return Err(self.error("Expected digit after decimal point"));
+            }
+            self.digits();
+        }
+
+        if let Some(b'e' | b'E') = self.peek() {
+            self.current += 1;
+            if let Some(b'+' | b'-') = self.peek() {
+                self.current += 1;
+            }
+            if !matches!(self.peek(), Some(b'0'..=b'9')) {
+                return Err(self.error("Expected digit in exponent"));
+            }
+            self.digits();
+        }
+
+        // the scanned bytes are all ascii so this can't fail
+        let text = std::str::from_utf8(&self.source[start..self.current]).unwrap();
+        text.parse::<f64>()
+            .map(Json::Number)
+            .map_err(|_| self.error("Invalid number"))
+    }
+
+    fn digits(&mut self) {
+        while let Some(b'0'..=b'9') = self.peek() {
+            self.current += 1;
+        }
+    }
+
+    fn hex_escape(&mut self) -> Result<u32, JsonError> {
+        let digits = self
+            .source
+            .get(self.current..self.current + 4)
+            .and_then(|bytes| std::str::from_utf8(bytes).ok())
+            .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+            .ok_or_else(|| self.error("Invalid unicode escape"))?;
+
+        self.current += 4;
+        Ok(digits)
+    }
+
+    fn string(&mut self) -> Result<String, JsonError> {
+        self.expect(b'"')?;
+        let mut bytes = vec![];
+
+        loop {
+            match self.peek() {
+                None => return Err(self.error("Unterminated string")),
+                Some(b'"') => {
+                    self.current += 1;
+                    break;
+                }
+                Some(b'\\') => {
+                    self.current += 1;
+                    let escaped = match self.peek() {
+                        Some(b'"') => '"',
+                        Some(b'\\') => '\\',
+                        Some(b'/') => '/',
+                        Some(b'b') => '\u{8}',
+                        Some(b'f') => '\u{c}',
+                        Some(b'n') => '\n',
+                        Some(b'r') => '\r',
+                        Some(b't') => '\t',
+                        Some(b'u') => {
+                            self.current += 1;
+                            let mut code = self.hex_escape()?;
+
+                            // surrogate pair
+                            if (0xD800..0xDC00).contains(&code)
+                                && self.source[self.current..].starts_with(b"\\u")
+                            {
+                                self.current += 2;
+                                let low = self.hex_escape()?;
+                                if !(0xDC00..0xE000).contains(&low) {
+                                    return Err(self.error("Invalid unicode escape"));
+                                }
+                                code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+                            }
+
+                            let c = char::from_u32(code)
+                                .ok_or_else(|| self.error("Invalid unicode escape"))?;
+                            let mut buf = [0; 4];
+                            bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                            continue;
+                        }
+                        _ => return Err(self.error("Invalid escape sequence")),
+                    };
+
+                    self.current += 1;
+                    let mut buf = [0; 4];
+                    bytes.extend_from_slice(escaped.encode_utf8(&mut buf).as_bytes());
+                }
+                Some(b) if b < 0x20 => {
+                    return Err(self.error("Control character in string"));
+                }
+                Some(b) => {
+                    bytes.push(b);
+                    self.current += 1;
+                }
+            }
+        }
+
+        String::from_utf8(bytes).map_err(|_| self.error("Invalid utf-8 in string"))
+    }
+}
@@ -1,7 +1,29 @@
 use std::collections::HashMap;
 
 use crate::token::{Token, TokenType};
-use crate::vari::{Vari, VariTypes};
+use crate::vari::{SyntaxError, VariTypes};
+
+pub const KEYWORDS: &[(&str, TokenType)] = &[
+    ("and", TokenType::AND),
+    ("else", TokenType::ELSE),
+    ("eprint", TokenType::EPRINT),
+    ("false", TokenType::FALSE),
+    ("for", TokenType::FOR),
+    ("fn", TokenType::FUN),
+    ("if", TokenType::IF),
+    ("is", TokenType::IS),
+    ("nil", TokenType::NIL),
+    ("or", TokenType::OR),
+    ("print", TokenType::PRINT),
+    ("return", TokenType::RETURN),
+    ("super", TokenType::SUPER),
+    ("struct", TokenType::STRUCT),
+    ("this", TokenType::THIS),
+    ("true", TokenType::TRUE),
+    ("let", TokenType::LET),
+    ("match", TokenType::MATCH),
+    ("while", TokenType::WHILE),
+];
 
 pub struct Lexer {
     keywords: HashMap<String, TokenType>,
@@ -10,28 +32,17 @@ pub struct Lexer {
     line: usize,
     current: usize,
     start: usize,
-    vari: Vari,
+    // byte offset of the first character on the current line
+    line_start: usize,
+    errors: Vec<SyntaxError>,
 }
 
 impl Lexer {
-    pub fn new(src: String, vari: Vari) -> Self {
-        let mut keywords_map = HashMap::new();
-        keywords_map.insert("and".to_owned(), TokenType::AND);
-        keywords_map.insert("else".to_owned(), TokenType::ELSE);
-        keywords_map.insert("false".to_owned(), TokenType::FALSE);
-        keywords_map.insert("for".to_owned(), TokenType::FOR);
-        keywords_map.insert("fn".to_owned(), TokenType::FUN);
-        keywords_map.insert("if".to_owned(), TokenType::IF);
-        keywords_map.insert("nil".to_owned(), TokenType::NIL);
-        keywords_map.insert("or".to_owned(), TokenType::OR);
-        keywords_map.insert("print".to_owned(), TokenType::PRINT);
-        keywords_map.insert("return".to_owned(), TokenType::RETURN);
-        keywords_map.insert("super".to_owned(), TokenType::SUPER);
-        keywords_map.insert("struct".to_owned(), TokenType::STRUCT);
-        keywords_map.insert("this".to_owned(), TokenType::THIS);
-        keywords_map.insert("true".to_owned(), TokenType::TRUE);
-        keywords_map.insert("let".to_owned(), TokenType::LET);
-        keywords_map.insert("while".to_owned(), TokenType::WHILE);
+    pub fn new(src: String) -> Self {
+        let keywords_map = KEYWORDS
+            .iter()
+            .map(|(name, token_type)| (name.to_string(), token_type.clone()))
+            .collect();
 
         Self {
             keywords: keywords_map,
@@ -40,7 +51,8 @@ impl Lexer {
             line: 1,
             current: 0,
             start: 0,
-            vari,
+            line_start: 0,
+            errors: vec![],
         }
     }
 
@@ -55,11 +67,12 @@ impl Lexer {
 
     fn add_token_with_literal(&mut self, tk_type: TokenType, literal: Option<Box<VariTypes>>) {
         let strval = self.source[self.start..self.current].to_owned();
+        let column = self.start.saturating_sub(self.line_start);
         self.tokens
-            .push(Token::new(tk_type, strval, self.line, literal));
+            .push(Token::new(tk_type, strval, self.line, column, literal));
     }
 
-    fn add_token(&mut self, tk_type: TokenType) -> () {
+    fn add_token(&mut self, tk_type: TokenType) {
         self.add_token_with_literal(tk_type, None);
     }
 
@@ -80,10 +93,10 @@ impl Lexer {
     }
 
     fn is_digit(&self, c: char) -> bool {
-        c >= '0' && c <= '9'
+        c.is_ascii_digit()
     }
     fn is_alpha(&self, c: char) -> bool {
-        (c >= 'a' && c <= 'z') || (c >= 'A' && c <= 'Z') || c == '_'
+        c.is_ascii_alphabetic() || c == '_'
     }
 
     fn is_alphanumeric(&self, c: char) -> bool {
@@ -119,7 +132,7 @@ impl Lexer {
                 self.add_token_with_literal(TokenType::NUMBER, Some(Box::new(VariTypes::Num(val))));
             }
             _ => {
-                self.vari.error(self.line, "Invalid number type");
+                self.error("Invalid number type");
             }
         }
     }
@@ -127,12 +140,17 @@ impl Lexer {
     fn consume_string_literal(&mut self) {
         while self.peek() != '"' && !self.done() {
             if self.peek() == '\n' {
-                self.line += 1;
+                self.new_line(self.current + 1);
             }
 
             self.advance();
         }
 
+        if self.done() {
+            self.error("Unterminated string.");
+            return;
+        }
+
         // closing "
         self.advance();
 
@@ -160,14 +178,40 @@ impl Lexer {
         }
     }
 
-    fn scan_token(&mut self) -> () {
+    fn new_line(&mut self, line_start: usize) {
+        self.line += 1;
+        self.line_start = line_start;
+    }
+
+    fn error(&mut self, msg: &str) {
+        self.errors.push(SyntaxError {
+            line: self.line,
+            column: self.start.saturating_sub(self.line_start),
+            msg: msg.to_owned(),
+        });
+    }
+
+    // lexical errors encountered by `scan_tokens`
+    pub fn errors(&self) -> &[SyntaxError] {
+        &self.errors
+    }
+
+    fn scan_token(&mut self) {
         match self.advance() {
             '(' => self.add_token(TokenType::LPAREN),
             ')' => self.add_token(TokenType::RPAREN),
             '{' => self.add_token(TokenType::LBRACE),
             '}' => self.add_token(TokenType::RBRACE),
+            '[' => self.add_token(TokenType::LBRACKET),
+            ']' => self.add_token(TokenType::RBRACKET),
             ',' => self.add_token(TokenType::COMMA),
-            '.' => self.add_token(TokenType::DOT),
+            '.' => {
+                if self.match_expected('.') {
+                    self.add_token(TokenType::DOTDOT);
+                } else {
+                    self.add_token(TokenType::DOT);
+                }
+            }
             '*' => self.add_token(TokenType::STAR),
             '/' => self.add_token(TokenType::SLASH),
             '+' => self.add_token(TokenType::PLUS),
@@ -175,17 +219,20 @@ impl Lexer {
             '%' => self.add_token(TokenType::MODULO),
             ';' => self.add_token(TokenType::SEMICOLON),
             ':' => self.add_token(TokenType::COLON),
+            '|' => self.add_token(TokenType::PIPE),
 
             // ignore whitespace
             ' ' => {}
             '\r' => {}
             '\t' => {}
-            '\n' => self.line += 1,
+            '\n' => self.new_line(self.current),
 
             // operators
             '=' => {
                 if self.match_expected('=') {
                     self.add_token(TokenType::ISEQ);
+                } else if self.match_expected('>') {
+                    self.add_token(TokenType::ARROW);
                 } else {
                     self.add_token(TokenType::EQUAL);
                 }
@@ -228,8 +275,7 @@ impl Lexer {
                 } else if self.is_alpha(c) {
                     self.consume_identifier();
                 } else {
-                    self.vari
-                        .error(self.line, format!("Unexpected character").as_str());
+                    self.error("Unexpected character");
                 }
             }
         }
@@ -245,6 +291,7 @@ impl Lexer {
             token_type: TokenType::EOF,
             lexeme: "".to_owned(),
             line: self.line,
+            column: self.current.saturating_sub(self.line_start),
             literal: None,
         });
 
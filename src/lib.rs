@@ -0,0 +1,38 @@
+// explicit returns and boxed values are the house style
+#![allow(clippy::needless_return, clippy::boxed_local)]
+
+mod debugger;
+mod diagnostic;
+mod engine;
+mod environment;
+mod expr;
+mod fields;
+mod host;
+mod interpreter;
+mod json;
+mod lexer;
+mod linter;
+mod lsp;
+mod parser;
+mod procedure;
+mod profiler;
+mod regex;
+mod resolver;
+mod sandbox;
+mod stdlib;
+mod stmt;
+mod test_runner;
+mod tests;
+mod token;
+mod value;
+mod vari;
+
+pub mod cli;
+
+pub use engine::{Engine, Error};
+pub use fields::Fields;
+pub use host::{FromVari, HostFunction, IntoVari};
+pub use procedure::Arity;
+pub use sandbox::{Capability, Limit, Limits, DEFAULT_MAX_DEPTH, DEFAULT_MAX_STACK};
+pub use value::{Function, Value};
+pub use vari::SyntaxError;
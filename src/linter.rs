@@ -0,0 +1,440 @@
+use std::collections::HashSet;
+use std::fs;
+
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::expr::{Expr, ExprVisitor};
+use crate::interpreter::Interpreter;
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+use crate::resolver::{Resolution, Resolver, SymbolKind};
+use crate::stmt::{Stmt, StmtVisitor};
+use crate::token::{Token, TokenType};
+use crate::vari::VariTypes;
+
+// rule id and a short description, a rule is silenced for
+// a line with `# vari-lint: allow(rule-id)` on that line or
+// alone on the line above it
+pub const RULES: &[(&str, &str)] = &[
+    ("unused-variable", "a `let` binding that is never read"),
+    ("unused-parameter", "a function parameter that is never read"),
+    ("shadowing", "a binding that hides one from an enclosing scope"),
+    ("unreachable-code", "statements following a `return` in the same block"),
+    ("assign-in-condition", "an assignment used as an `if` or `while` condition"),
+    ("wrong-arity", "calling a known function with the wrong number of arguments"),
+    ("self-comparison", "comparing a value to itself"),
+];
+
+const SUPPRESS_MARKER: &str = "vari-lint:";
+
+// lints every file, printing problems as `file:line:column: ...`,
+// returns the exit code for the `vari lint` command
+pub fn run(paths: &[String]) -> i32 {
+    if paths.is_empty() {
+        println!("Usage: vari lint <file>...");
+        for (rule, description) in RULES {
+            println!("  {:<20} {}", rule, description);
+        }
+        return 64;
+    }
+
+    let builtins = Interpreter::new().globals.borrow().names();
+    let mut found_problems = false;
+
+    for path in paths {
+        let source = match fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(err) => {
+                println!("{}: {}", path, err);
+                found_problems = true;
+                continue;
+            }
+        };
+
+        let (_, diagnostics) = check(&source, &builtins);
+        for diagnostic in diagnostics {
+            found_problems = true;
+
+            let kind = match (diagnostic.severity, diagnostic.rule) {
+                (Severity::Warning, Some(rule)) => format!("warning[{}]", rule),
+                (Severity::Warning, None) => "warning".to_owned(),
+                (Severity::Error, _) => "error".to_owned(),
+            };
+            println!(
+                "{}:{}:{}: {}: {}",
+                path,
+                diagnostic.line,
+                diagnostic.column + 1,
+                kind,
+                diagnostic.msg
+            );
+        }
+    }
+
+    if found_problems {
+        1
+    } else {
+        0
+    }
+}
+
+// runs every static check on `source`: syntax errors, name
+// resolution and the lint rules, sorted by position
+pub fn check(source: &str, builtins: &[String]) -> (Resolution, Vec<Diagnostic>) {
+    let mut lexer = Lexer::new(source.to_owned());
+    let tokens = lexer.scan_tokens();
+
+    let mut parser = Parser::new(tokens);
+    let statements = parser.parse();
+
+    let resolution = Resolver::new(builtins.to_vec()).resolve(&statements);
+
+    let mut diagnostics: Vec<Diagnostic> = lexer
+        .errors()
+        .iter()
+        .chain(parser.errors())
+        .map(Diagnostic::from)
+        .collect();
+    diagnostics.extend(resolution.diagnostics.iter().cloned());
+    diagnostics.extend(lint(source, &statements, &resolution));
+    diagnostics.sort_by_key(|diagnostic| (diagnostic.line, diagnostic.column));
+
+    (resolution, diagnostics)
+}
+
+pub fn lint(source: &str, statements: &[Stmt], resolution: &Resolution) -> Vec<Diagnostic> {
+    let mut linter = Linter {
+        resolution,
+        lines: source.lines().collect(),
+        warnings: vec![],
+    };
+
+    linter.check_bindings();
+    linter.check_body(statements.to_vec());
+
+    let suppressed = suppressions(source);
+    linter
+        .warnings
+        .into_iter()
+        .filter(|warning| match warning.rule {
+            Some(rule) => !suppressed.contains(&(warning.line, rule.to_owned())),
+            None => true,
+        })
+        .collect()
+}
+
+// (line, rule) pairs silenced by `# vari-lint: allow(...)` comments
+fn suppressions(source: &str) -> HashSet<(usize, String)> {
+    let mut suppressed = HashSet::new();
+
+    for (index, text) in source.lines().enumerate() {
+        let line = index + 1;
+        let comment = match text.find('#') {
+            Some(start) => &text[start + 1..],
+            None => continue,
+        };
+
+        let directive = match comment.trim_start().strip_prefix(SUPPRESS_MARKER) {
+            Some(directive) => directive.trim(),
+            None => continue,
+        };
+
+        let rules = match directive
+            .strip_prefix("allow(")
+            .and_then(|rest| rest.strip_suffix(')'))
+        {
+            Some(rules) => rules,
+            None => continue,
+        };
+
+        // a comment on a line of its own covers the next line
+        let target = if text.trim_start().starts_with('#') {
+            line + 1
+        } else {
+            line
+        };
+
+        for rule in rules.split(',') {
+            suppressed.insert((target, rule.trim().to_owned()));
+        }
+    }
+
+    suppressed
+}
+
+// expressions without side effects that always produce the same value
+fn same_value(a: &Expr, b: &Expr) -> bool {
+    match (a, b) {
+        (Expr::Grouping { expr }, other) | (other, Expr::Grouping { expr }) => {
+            same_value(expr, other)
+        }
+        (Expr::Variable { value: l }, Expr::Variable { value: r }) => l.lexeme == r.lexeme,
+        (Expr::Get { expr: l, name: ln }, Expr::Get { expr: r, name: rn }) => {
+            ln == rn && same_value(l, r)
+        }
+        (
+            Expr::Index {
+                expr: l, index: li, ..
+            },
+            Expr::Index {
+                expr: r, index: ri, ..
+            },
+        ) => same_value(l, r) && same_value(li, ri),
+        (Expr::Literal { value: l }, Expr::Literal { value: r }) => match (&**l, &**r) {
+            (VariTypes::Nil, VariTypes::Nil) => true,
+            (VariTypes::Num(l), VariTypes::Num(r)) => l.to_bits() == r.to_bits(),
+            (VariTypes::String(l), VariTypes::String(r)) => l == r,
+            (VariTypes::Boolean(l), VariTypes::Boolean(r)) => l == r,
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+struct Linter<'a> {
+    resolution: &'a Resolution,
+    // the source, for warnings about whole lines
+    lines: Vec<&'a str>,
+    warnings: Vec<Diagnostic>,
+}
+
+// the first line of `stmt` that holds code, looking into blocks
+fn first_line(stmt: &Stmt) -> Option<usize> {
+    match stmt {
+        Stmt::Block(statements) => statements.iter().find_map(first_line),
+        stmt => stmt.line(),
+    }
+}
+
+impl Linter<'_> {
+    fn warn(&mut self, rule: &'static str, token: &Token, msg: String) {
+        self.warnings.push(Diagnostic::lint(
+            rule,
+            token.line,
+            token.column,
+            token.lexeme.len(),
+            msg,
+        ));
+    }
+
+    // rules that only need the resolved bindings
+    fn check_bindings(&mut self) {
+        let symbols = &self.resolution.symbols;
+
+        for symbol in symbols {
+            let name = &symbol.name;
+
+            // a leading underscore marks a binding as intentionally unused
+            if symbol.reads == 0 && !name.lexeme.starts_with('_') {
+                match symbol.kind {
+                    SymbolKind::Variable => self.warn(
+                        "unused-variable",
+                        name,
+                        format!("Variable '{}' is never used.", name.lexeme),
+                    ),
+                    SymbolKind::Parameter => self.warn(
+                        "unused-parameter",
+                        name,
+                        format!("Parameter '{}' is never used.", name.lexeme),
+                    ),
+                    SymbolKind::Function | SymbolKind::Struct => {}
+                }
+            }
+
+            if let Some(shadowed) = symbol.shadows {
+                self.warn(
+                    "shadowing",
+                    name,
+                    format!(
+                        "'{}' shadows the binding declared on line {}.",
+                        name.lexeme, symbols[shadowed].name.line
+                    ),
+                );
+            }
+        }
+    }
+
+    // marks the code on `line`, without its indentation
+    fn warn_line(&mut self, rule: &'static str, line: usize, msg: String) {
+        let text = self.lines.get(line - 1).copied().unwrap_or("");
+        let column = text.len() - text.trim_start().len();
+        let len = text.trim().len();
+        self.warnings.push(Diagnostic::lint(rule, line, column, len, msg));
+    }
+
+    fn check_body(&mut self, statements: Vec<Stmt>) {
+        // the first statement after a `return` is the one reported,
+        // so an allow comment for it goes on the dead code
+        let dead = statements.windows(2).find_map(|pair| match pair {
+            [Stmt::Return(keyword, _), next] => Some((keyword.clone(), first_line(next))),
+            _ => None,
+        });
+        let msg = "Code after 'return' is never executed.".to_owned();
+        match dead {
+            Some((_, Some(line))) => self.warn_line("unreachable-code", line, msg),
+            Some((keyword, None)) => self.warn("unreachable-code", &keyword, msg),
+            None => {}
+        }
+
+        for stmt in statements {
+            self.visit_stmt(stmt);
+        }
+    }
+
+    fn check_condition(&mut self, condition: &Expr) {
+        match condition {
+            Expr::Assign { name, .. } => self.warn(
+                "assign-in-condition",
+                name,
+                format!(
+                    "Assignment to '{}' used as a condition, did you mean '=='?",
+                    name.lexeme
+                ),
+            ),
+            Expr::Grouping { expr } | Expr::Unary { rhs: expr, .. } => {
+                self.check_condition(expr)
+            }
+            Expr::Logical { lhs, rhs, .. } => {
+                self.check_condition(lhs);
+                self.check_condition(rhs);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl StmtVisitor<()> for Linter<'_> {
+    fn visit_stmt(&mut self, stmt: Stmt) {
+        match stmt {
+            Stmt::Block(statements) => self.check_body(statements),
+            Stmt::Expression(expr) | Stmt::Print(_, expr) | Stmt::Return(_, expr) => {
+                self.visit_expr(expr)
+            }
+            Stmt::Var(_, initializer) => {
+                if let Some(expr) = initializer {
+                    self.visit_expr(expr);
+                }
+            }
+            Stmt::If(_, condition, then_branch, else_branch) => {
+                self.check_condition(&condition);
+                self.visit_expr(condition);
+                self.visit_stmt(*then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.visit_stmt(*else_branch);
+                }
+            }
+            Stmt::While(_, condition, body) => {
+                self.check_condition(&condition);
+                self.visit_expr(condition);
+                self.visit_stmt(*body);
+            }
+            Stmt::Function(_, _, body) => self.check_body(body),
+            Stmt::Struct(_, fields) => {
+                for (_, default) in fields {
+                    if let Some(expr) = default {
+                        self.visit_expr(expr);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl ExprVisitor<()> for Linter<'_> {
+    fn visit_expr(&mut self, expr: Expr) {
+        match expr {
+            Expr::Binary { lhs, op, rhs } => {
+                let comparison = matches!(
+                    op.token_type,
+                    TokenType::ISEQ
+                        | TokenType::NE
+                        | TokenType::GT
+                        | TokenType::GE
+                        | TokenType::LT
+                        | TokenType::LE
+                        | TokenType::IS
+                );
+                if comparison && same_value(&lhs, &rhs) {
+                    self.warn(
+                        "self-comparison",
+                        &op,
+                        format!("Both sides of '{}' are the same value.", op.lexeme),
+                    );
+                }
+
+                self.visit_expr(*lhs);
+                self.visit_expr(*rhs);
+            }
+            Expr::Logical { lhs, rhs, .. } => {
+                self.visit_expr(*lhs);
+                self.visit_expr(*rhs);
+            }
+            Expr::Call { callee, args, .. } => {
+                if let Expr::Variable { value } = &*callee {
+                    let params = self
+                        .resolution
+                        .symbol_at(value.line, value.column)
+                        .and_then(|index| self.resolution.symbols[index].params.as_ref());
+
+                    if let Some(params) = params {
+                        if params.len() != args.len() {
+                            self.warn(
+                                "wrong-arity",
+                                value,
+                                format!(
+                                    "'{}' takes {} argument(s) but {} were given.",
+                                    value.lexeme,
+                                    params.len(),
+                                    args.len()
+                                ),
+                            );
+                        }
+                    }
+                }
+
+                self.visit_expr(*callee);
+                for arg in args {
+                    self.visit_expr(arg);
+                }
+            }
+            Expr::Unary { rhs, .. } => self.visit_expr(*rhs),
+            Expr::Grouping { expr } => self.visit_expr(*expr),
+            Expr::Struct { values } | Expr::Construct { values, .. } => {
+                for (_, value) in values {
+                    self.visit_expr(value);
+                }
+            }
+            Expr::List { items, .. } => {
+                for item in items {
+                    self.visit_expr(item);
+                }
+            }
+            Expr::Index { expr, index, .. } => {
+                self.visit_expr(*expr);
+                self.visit_expr(*index);
+            }
+            Expr::SetIndex {
+                expr, index, value, ..
+            } => {
+                self.visit_expr(*expr);
+                self.visit_expr(*index);
+                self.visit_expr(*value);
+            }
+            Expr::Get { expr, .. } => self.visit_expr(*expr),
+            Expr::Set { expr, value, .. } => {
+                self.visit_expr(*expr);
+                self.visit_expr(*value);
+            }
+            Expr::Assign { value_expr, .. } => self.visit_expr(*value_expr),
+            Expr::Match { value, arms, .. } => {
+                self.visit_expr(*value);
+                for arm in arms {
+                    if let Some(guard) = arm.guard {
+                        self.visit_expr(guard);
+                    }
+                    self.visit_expr(arm.body);
+                }
+            }
+            Expr::Literal { .. } | Expr::Variable { .. } => {}
+        }
+    }
+}
@@ -0,0 +1,557 @@
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::interpreter::Interpreter;
+use crate::json::Json;
+use crate::lexer::KEYWORDS;
+use crate::linter;
+use crate::resolver::{Resolution, SymbolKind};
+use crate::token::Token;
+
+// JSON-RPC error codes used by the protocol
+const PARSE_ERROR: f64 = -32700.0;
+const INVALID_PARAMS: f64 = -32602.0;
+const METHOD_NOT_FOUND: f64 = -32601.0;
+
+// runs the language server on stdin/stdout until the client
+// sends `exit`, returning the process exit code
+pub fn run() -> i32 {
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+
+    match serve(stdin.lock(), stdout.lock()) {
+        Ok(code) => code,
+        Err(err) => {
+            eprintln!("vari lsp: {}", err);
+            1
+        }
+    }
+}
+
+// speaks the protocol over any pair of streams
+pub fn serve<R: BufRead, W: Write>(reader: R, writer: W) -> io::Result<i32> {
+    Server::new(reader, writer).serve()
+}
+
+// an open file and everything we know about it
+struct Document {
+    text: String,
+    resolution: Resolution,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Document {
+    fn analyze(text: String, builtins: &[String]) -> Self {
+        let (resolution, diagnostics) = linter::check(&text, builtins);
+
+        Self {
+            text,
+            resolution,
+            diagnostics,
+        }
+    }
+
+    fn line_text(&self, line: usize) -> &str {
+        self.text.lines().nth(line).unwrap_or("")
+    }
+
+    // LSP positions count utf-16 code units, tokens count bytes
+    fn to_byte_column(&self, line: usize, character: usize) -> usize {
+        let mut units = 0;
+        for (offset, c) in self.line_text(line).char_indices() {
+            if units >= character {
+                return offset;
+            }
+            units += c.len_utf16();
+        }
+        self.line_text(line).len()
+    }
+
+    fn to_character(&self, line: usize, column: usize) -> usize {
+        let text = self.line_text(line);
+        let column = column.min(text.len());
+        text.get(..column)
+            .map_or(column, |prefix| prefix.encode_utf16().count())
+    }
+
+    fn range(&self, line: usize, column: usize, len: usize) -> Json {
+        // tokens count lines from 1, the protocol from 0
+        let line = line.saturating_sub(1);
+        Json::object(vec![
+            ("start", self.position(line, column)),
+            ("end", self.position(line, column + len)),
+        ])
+    }
+
+    fn position(&self, line: usize, column: usize) -> Json {
+        Json::object(vec![
+            ("line", Json::Number(line as f64)),
+            (
+                "character",
+                Json::Number(self.to_character(line, column) as f64),
+            ),
+        ])
+    }
+
+    fn token_range(&self, token: &Token) -> Json {
+        self.range(token.line, token.column, token.lexeme.len())
+    }
+}
+
+struct Server<R: BufRead, W: Write> {
+    reader: R,
+    writer: W,
+    documents: HashMap<String, Document>,
+    // names the interpreter defines before running a script
+    builtins: Vec<String>,
+    shutdown_requested: bool,
+}
+
+impl<R: BufRead, W: Write> Server<R, W> {
+    fn new(reader: R, writer: W) -> Self {
+        let builtins = Interpreter::new().globals.borrow().names();
+
+        Self {
+            reader,
+            writer,
+            documents: HashMap::new(),
+            builtins,
+            shutdown_requested: false,
+        }
+    }
+
+    fn serve(&mut self) -> io::Result<i32> {
+        loop {
+            let body = match self.read_message()? {
+                Some(body) => body,
+                // client went away without saying goodbye
+                None => return Ok(1),
+            };
+
+            let message = match Json::parse(&body) {
+                Ok(message) => message,
+                Err(err) => {
+                    self.respond_error(Json::Null, PARSE_ERROR, &err.to_string())?;
+                    continue;
+                }
+            };
+
+            if let Some(code) = self.handle(message)? {
+                return Ok(code);
+            }
+        }
+    }
+
+    // reads one `Content-Length` framed message body, None on end of input
+    fn read_message(&mut self) -> io::Result<Option<String>> {
+        let mut content_length = None;
+
+        loop {
+            let mut header = String::new();
+            if self.reader.read_line(&mut header)? == 0 {
+                return Ok(None);
+            }
+
+            let header = header.trim_end();
+            if header.is_empty() {
+                break;
+            }
+
+            if let Some((name, value)) = header.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse::<usize>().ok();
+                }
+            }
+        }
+
+        let len = content_length.ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, "missing Content-Length header")
+        })?;
+
+        let mut body = vec![0; len];
+        self.reader.read_exact(&mut body)?;
+
+        String::from_utf8(body)
+            .map(Some)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    fn send(&mut self, message: Json) -> io::Result<()> {
+        let body = message.to_string();
+        write!(self.writer, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+        self.writer.flush()
+    }
+
+    fn respond(&mut self, id: Json, result: Json) -> io::Result<()> {
+        self.send(Json::object(vec![
+            ("jsonrpc", Json::String("2.0".to_owned())),
+            ("id", id),
+            ("result", result),
+        ]))
+    }
+
+    fn respond_error(&mut self, id: Json, code: f64, msg: &str) -> io::Result<()> {
+        self.send(Json::object(vec![
+            ("jsonrpc", Json::String("2.0".to_owned())),
+            ("id", id),
+            (
+                "error",
+                Json::object(vec![
+                    ("code", Json::Number(code)),
+                    ("message", Json::String(msg.to_owned())),
+                ]),
+            ),
+        ]))
+    }
+
+    fn notify(&mut self, method: &str, params: Json) -> io::Result<()> {
+        self.send(Json::object(vec![
+            ("jsonrpc", Json::String("2.0".to_owned())),
+            ("method", Json::String(method.to_owned())),
+            ("params", params),
+        ]))
+    }
+
+    // handles one message, returning the exit code once the client asks us to exit
+    fn handle(&mut self, message: Json) -> io::Result<Option<i32>> {
+        let method = message.get("method").and_then(Json::as_str).unwrap_or("");
+        let params = message.get("params").cloned().unwrap_or(Json::Null);
+
+        let id = match message.get("id") {
+            Some(id) => id.clone(),
+            None => {
+                return self.handle_notification(method, &params);
+            }
+        };
+
+        let result = match method {
+            "initialize" => Some(capabilities()),
+            "shutdown" => {
+                self.shutdown_requested = true;
+                Some(Json::Null)
+            }
+            "textDocument/definition" => self
+                .locate(&params)
+                .map(|(doc, uri, line, column)| definition(doc, &uri, line, column)),
+            "textDocument/references" => {
+                let include_declaration = params
+                    .get("context")
+                    .and_then(|context| context.get("includeDeclaration"))
+                    .and_then(Json::as_bool)
+                    .unwrap_or(true);
+                self.locate(&params).map(|(doc, uri, line, column)| {
+                    references(doc, &uri, line, column, include_declaration)
+                })
+            }
+            "textDocument/hover" => self
+                .locate(&params)
+                .map(|(doc, _, line, column)| hover(doc, line, column)),
+            "textDocument/completion" => self
+                .locate(&params)
+                .map(|(doc, _, _, _)| completion(doc, &self.builtins)),
+            "textDocument/documentSymbol" => {
+                let uri = params
+                    .get("textDocument")
+                    .and_then(|doc| doc.get("uri"))
+                    .and_then(Json::as_str)
+                    .unwrap_or("");
+                Some(
+                    self.documents
+                        .get(uri)
+                        .map_or(Json::Array(vec![]), document_symbols),
+                )
+            }
+            _ => {
+                self.respond_error(id, METHOD_NOT_FOUND, &format!("Unknown method {}", method))?;
+                return Ok(None);
+            }
+        };
+
+        match result {
+            Some(result) => self.respond(id, result)?,
+            None => self.respond_error(id, INVALID_PARAMS, "Unknown document or position")?,
+        }
+
+        Ok(None)
+    }
+
+    fn handle_notification(&mut self, method: &str, params: &Json) -> io::Result<Option<i32>> {
+        let uri = params
+            .get("textDocument")
+            .and_then(|doc| doc.get("uri"))
+            .and_then(Json::as_str)
+            .unwrap_or("")
+            .to_owned();
+
+        match method {
+            "exit" => return Ok(Some(if self.shutdown_requested { 0 } else { 1 })),
+            "textDocument/didOpen" => {
+                let text = params
+                    .get("textDocument")
+                    .and_then(|doc| doc.get("text"))
+                    .and_then(Json::as_str)
+                    .unwrap_or("");
+                self.update(uri, text.to_owned())?;
+            }
+            "textDocument/didChange" => {
+                // we only advertise full document sync,
+                // so the last change holds the whole text
+                let text = params
+                    .get("contentChanges")
+                    .and_then(Json::as_array)
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change.get("text"))
+                    .and_then(Json::as_str);
+                if let Some(text) = text {
+                    self.update(uri, text.to_owned())?;
+                }
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                self.publish_diagnostics(&uri)?;
+            }
+            // `initialized`, `$/cancelRequest` and friends need no answer
+            _ => {}
+        }
+
+        Ok(None)
+    }
+
+    fn update(&mut self, uri: String, text: String) -> io::Result<()> {
+        let document = Document::analyze(text, &self.builtins);
+        self.documents.insert(uri.clone(), document);
+        self.publish_diagnostics(&uri)
+    }
+
+    fn publish_diagnostics(&mut self, uri: &str) -> io::Result<()> {
+        let diagnostics = self.documents.get(uri).map_or(vec![], |doc| {
+            doc.diagnostics
+                .iter()
+                .map(|diagnostic| {
+                    let severity = match diagnostic.severity {
+                        Severity::Error => 1.0,
+                        Severity::Warning => 2.0,
+                    };
+                    let mut fields = vec![
+                        (
+                            "range",
+                            doc.range(diagnostic.line, diagnostic.column, diagnostic.len),
+                        ),
+                        ("severity", Json::Number(severity)),
+                        ("source", Json::String("vari".to_owned())),
+                        ("message", Json::String(diagnostic.msg.clone())),
+                    ];
+                    if let Some(rule) = diagnostic.rule {
+                        fields.push(("code", Json::String(rule.to_owned())));
+                    }
+                    Json::object(fields)
+                })
+                .collect()
+        });
+
+        self.notify(
+            "textDocument/publishDiagnostics",
+            Json::object(vec![
+                ("uri", Json::String(uri.to_owned())),
+                ("diagnostics", Json::Array(diagnostics)),
+            ]),
+        )
+    }
+
+    // looks up the document and cursor position of a `TextDocumentPositionParams`
+    // request, the position as a 1 based line and byte column
+    fn locate(&self, params: &Json) -> Option<(&Document, String, usize, usize)> {
+        let uri = params.get("textDocument")?.get("uri")?.as_str()?;
+        let position = params.get("position")?;
+        let line = position.get("line")?.as_f64()? as usize;
+        let character = position.get("character")?.as_f64()? as usize;
+
+        let document = self.documents.get(uri)?;
+        let column = document.to_byte_column(line, character);
+
+        Some((document, uri.to_owned(), line + 1, column))
+    }
+}
+
+fn capabilities() -> Json {
+    Json::object(vec![
+        (
+            "capabilities",
+            Json::object(vec![
+                // full document sync
+                ("textDocumentSync", Json::Number(1.0)),
+                ("definitionProvider", Json::Bool(true)),
+                ("referencesProvider", Json::Bool(true)),
+                ("hoverProvider", Json::Bool(true)),
+                ("documentSymbolProvider", Json::Bool(true)),
+                ("completionProvider", Json::object(vec![])),
+            ]),
+        ),
+        (
+            "serverInfo",
+            Json::object(vec![
+                ("name", Json::String("vari".to_owned())),
+                ("version", Json::String(env!("CARGO_PKG_VERSION").to_owned())),
+            ]),
+        ),
+    ])
+}
+
+fn location(document: &Document, uri: &str, token: &Token) -> Json {
+    Json::object(vec![
+        ("uri", Json::String(uri.to_owned())),
+        ("range", document.token_range(token)),
+    ])
+}
+
+fn definition(document: &Document, uri: &str, line: usize, column: usize) -> Json {
+    match document.resolution.symbol_at(line, column) {
+        Some(index) => location(document, uri, &document.resolution.symbols[index].name),
+        None => Json::Null,
+    }
+}
+
+fn references(
+    document: &Document,
+    uri: &str,
+    line: usize,
+    column: usize,
+    include_declaration: bool,
+) -> Json {
+    let index = match document.resolution.symbol_at(line, column) {
+        Some(index) => index,
+        None => return Json::Array(vec![]),
+    };
+
+    let symbol = &document.resolution.symbols[index];
+    let mut locations = vec![];
+
+    if include_declaration {
+        locations.push(location(document, uri, &symbol.name));
+    }
+    for token in &symbol.references {
+        locations.push(location(document, uri, token));
+    }
+
+    Json::Array(locations)
+}
+
+fn hover(document: &Document, line: usize, column: usize) -> Json {
+    let index = match document.resolution.symbol_at(line, column) {
+        Some(index) => index,
+        None => return Json::Null,
+    };
+
+    let symbol = &document.resolution.symbols[index];
+    let text = match (symbol.kind, &symbol.params) {
+        (SymbolKind::Function, Some(params)) => format!(
+            "```vari\nfn {}({})\n```\narity: {}",
+            symbol.name.lexeme,
+            params.join(", "),
+            params.len()
+        ),
+        (SymbolKind::Struct, _) => format!("```vari\nstruct {}\n```", symbol.name.lexeme),
+        (SymbolKind::Parameter, _) => format!("```vari\n{}\n```\nparameter", symbol.name.lexeme),
+        _ => format!("```vari\nlet {}\n```", symbol.name.lexeme),
+    };
+
+    Json::object(vec![(
+        "contents",
+        Json::object(vec![
+            ("kind", Json::String("markdown".to_owned())),
+            ("value", Json::String(text)),
+        ]),
+    )])
+}
+
+fn completion(document: &Document, builtins: &[String]) -> Json {
+    // CompletionItemKind values from the specification
+    const FUNCTION: f64 = 3.0;
+    const VARIABLE: f64 = 6.0;
+    const KEYWORD: f64 = 14.0;
+
+    let mut items = vec![];
+    let mut seen = vec![];
+    let mut push = |label: &str, kind: f64, detail: Option<String>| {
+        if seen.iter().any(|name| name == label) {
+            return;
+        }
+        seen.push(label.to_owned());
+
+        let mut item = vec![
+            ("label", Json::String(label.to_owned())),
+            ("kind", Json::Number(kind)),
+        ];
+        if let Some(detail) = detail {
+            item.push(("detail", Json::String(detail)));
+        }
+        items.push(Json::object(item));
+    };
+
+    for symbol in &document.resolution.symbols {
+        let (kind, detail) = match &symbol.params {
+            Some(params) => (
+                FUNCTION,
+                Some(format!("fn {}({})", symbol.name.lexeme, params.join(", "))),
+            ),
+            None => (VARIABLE, None),
+        };
+        push(&symbol.name.lexeme, kind, detail);
+    }
+
+    for name in builtins {
+        push(name, FUNCTION, Some("built-in".to_owned()));
+    }
+
+    for (keyword, _) in KEYWORDS {
+        push(keyword, KEYWORD, None);
+    }
+
+    Json::Array(items)
+}
+
+fn document_symbols(document: &Document) -> Json {
+    symbols_in(document, None)
+}
+
+// `DocumentSymbol`s declared directly inside the function `parent`
+fn symbols_in(document: &Document, parent: Option<usize>) -> Json {
+    // SymbolKind values from the specification
+    const FUNCTION: f64 = 12.0;
+    const VARIABLE: f64 = 13.0;
+    const STRUCT: f64 = 23.0;
+
+    let symbols = &document.resolution.symbols;
+    let mut result = vec![];
+
+    for (index, symbol) in symbols.iter().enumerate() {
+        if symbol.parent != parent || symbol.kind == SymbolKind::Parameter {
+            continue;
+        }
+
+        let range = document.token_range(&symbol.name);
+        let mut fields = vec![
+            ("name", Json::String(symbol.name.lexeme.clone())),
+            (
+                "kind",
+                Json::Number(match symbol.kind {
+                    SymbolKind::Function => FUNCTION,
+                    SymbolKind::Struct => STRUCT,
+                    _ => VARIABLE,
+                }),
+            ),
+            ("range", range.clone()),
+            ("selectionRange", range),
+        ];
+
+        if let Some(params) = &symbol.params {
+            fields.push(("detail", Json::String(format!("({})", params.join(", ")))));
+            fields.push(("children", symbols_in(document, Some(index))));
+        }
+
+        result.push(Json::object(fields));
+    }
+
+    Json::Array(result)
+}
@@ -1,122 +1,444 @@
-use std::collections::HashMap;
-
+use crate::expr::{MatchArm, Pattern};
+use crate::fields::Fields;
+use crate::lexer::KEYWORDS;
+use crate::sandbox::{default_max_stack, stack_position};
 use crate::stmt::Stmt;
-use crate::vari::VariTypes;
+use crate::vari::{SyntaxError, VariTypes};
 use crate::{
     expr::Expr,
     token::{Token, TokenType},
 };
 
+type ParseResult<T> = Result<T, SyntaxError>;
+
+// How deeply expressions, statements and patterns may nest, the
+// parser recurses once for each level. Debug builds use a lot more
+// stack per level, there parsing also stops once it has used as much
+// as the interpreter would be allowed to.
+pub const MAX_NESTING: usize = 200;
+
+// How tall the tree may get. A chain like `1 + 2 + 3` is parsed in a
+// loop, but nests to the left as deep as it is long, and the passes
+// after the parser walk the tree recursively.
+pub const MAX_HEIGHT: usize = 500;
+
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
+    errors: Vec<SyntaxError>,
+    // levels of nesting around the token being parsed
+    depth: usize,
+    // levels of the tree above it, the nesting plus the links of
+    // the chains it's in
+    height: usize,
+    // where the stack was when parsing started, and how far
+    // past that it may go
+    stack_base: usize,
+    max_stack: usize,
+    // whether the last statement may be an expression without its ';'
+    bare_expression: bool,
 }
 
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Self {
-        Self { tokens, current: 0 }
+        Self {
+            tokens,
+            current: 0,
+            errors: vec![],
+            depth: 0,
+            height: 0,
+            stack_base: stack_position(),
+            max_stack: default_max_stack(),
+            bare_expression: false,
+        }
+    }
+
+    // lets the source end in an expression with no ';' after it,
+    // the way `Engine::eval` takes "1 + 2"
+    pub fn allow_bare_expression(&mut self) {
+        self.bare_expression = true;
+    }
+
+    // syntax errors encountered by `parse`
+    pub fn errors(&self) -> &[SyntaxError] {
+        &self.errors
+    }
+
+    // one level deeper, callers go back with `shallower` once they're
+    // done, `block` and `parse` put the levels back after an error
+    fn deeper(&mut self) -> ParseResult<()> {
+        let stack = self.stack_base.abs_diff(stack_position());
+        if self.depth >= MAX_NESTING || stack > self.max_stack {
+            return Err(self.error(&self.peek(), "Too deeply nested."));
+        }
+        self.depth += 1;
+        self.taller()
+    }
+
+    fn shallower(&mut self) {
+        self.depth -= 1;
+        self.height -= 1;
+    }
+
+    // one more link in a chain, the chain puts `height` back at its end
+    fn taller(&mut self) -> ParseResult<()> {
+        if self.height >= MAX_HEIGHT {
+            return Err(self.error(&self.peek(), "Expression is too long."));
+        }
+        self.height += 1;
+        Ok(())
     }
 
-    fn consume(&mut self, token_type: TokenType, err: &str) -> Token {
+    fn error(&self, token: &Token, msg: &str) -> SyntaxError {
+        let location = if token.token_type == TokenType::EOF {
+            "at end".to_owned()
+        } else {
+            format!("at '{}'", token.lexeme)
+        };
+
+        SyntaxError {
+            line: token.line,
+            column: token.column,
+            msg: format!("{}: {}", location, msg),
+        }
+    }
+
+    fn consume(&mut self, token_type: TokenType, err: &str) -> ParseResult<Token> {
         if self.check(token_type) {
             self.advance();
-            return self.tokens[self.current - 1].clone();
+            return Ok(self.tokens[self.current - 1].clone());
+        }
+
+        Err(self.error(&self.peek(), err))
+    }
+
+    // skip tokens until the start of the next statement so
+    // a single mistake doesn't cascade into more errors
+    fn synchronize(&mut self) {
+        self.advance();
+
+        while !self.done() {
+            if self.prev_token().token_type == TokenType::SEMICOLON {
+                return;
+            }
+
+            match self.peek().token_type {
+                TokenType::STRUCT
+                | TokenType::FUN
+                | TokenType::LET
+                | TokenType::FOR
+                | TokenType::IF
+                | TokenType::WHILE
+                | TokenType::PRINT
+                | TokenType::EPRINT
+                | TokenType::RETURN => return,
+                _ => self.advance(),
+            }
+        }
+    }
+
+    // `name: value` pairs up to the closing brace, the opening one is
+    // already consumed
+    fn struct_fields(&mut self) -> ParseResult<Fields<Expr>> {
+        let mut props = Fields::new();
+
+        while !self.check(TokenType::RBRACE) {
+            let name = self.consume(TokenType::IDENTIFIER, "Expected name for property")?;
+            self.consume(TokenType::COLON, "Expected colon after property name")?;
+            let expr = self.expression()?;
+
+            if props.contains_key(&name.lexeme) {
+                let msg = format!("Duplicate field '{}' in struct.", name.lexeme);
+                return Err(self.error(&name, &msg));
+            }
+            props.insert(name.lexeme, expr);
+
+            match self.peek().token_type {
+                TokenType::RBRACE => {
+                    self.consume(TokenType::RBRACE, "Expected '}' after struct")?;
+                    return Ok(props);
+                }
+                TokenType::COMMA => {
+                    self.consume(TokenType::COMMA, "Expected ',' after field expression")?;
+                }
+                _ => {
+                    return Err(self.error(&self.peek(), "Expected ',' or '}' after field"));
+                }
+            }
+        }
+
+        // only happens when struct is empty
+        self.consume(TokenType::RBRACE, "Expected '}' after struct")?;
+        Ok(props)
+    }
+
+    // match (value) { pattern if guard => body, ... }
+    fn match_expr(&mut self) -> ParseResult<Expr> {
+        let keyword = self.prev_token();
+        self.consume(TokenType::LPAREN, "Expected '(' after 'match'.")?;
+        let value = self.expression()?;
+        self.consume(TokenType::RPAREN, "Expected ')' after match value.")?;
+        self.consume(TokenType::LBRACE, "Expected '{' before match arms.")?;
+
+        let mut arms = vec![];
+        while !self.check(TokenType::RBRACE) {
+            let pattern = self.pattern()?;
+            let mut guard = None;
+            if self.match_list(vec![TokenType::IF]) {
+                guard = Some(self.expression()?);
+            }
+            self.consume(TokenType::ARROW, "Expected '=>' after pattern.")?;
+            let body = self.expression()?;
+            arms.push(MatchArm {
+                pattern,
+                guard,
+                body,
+            });
+
+            if !self.match_list(vec![TokenType::COMMA]) {
+                break;
+            }
+        }
+
+        let brace = self.consume(TokenType::RBRACE, "Expected '}' after match arms.")?;
+        if arms.is_empty() {
+            return Err(self.error(&brace, "A match needs at least one arm."));
+        }
+
+        Ok(Expr::Match {
+            keyword,
+            value: Box::new(value),
+            arms,
+        })
+    }
+
+    // alternatives separated by `|`
+    fn pattern(&mut self) -> ParseResult<Pattern> {
+        self.deeper()?;
+        let mut alternatives = vec![self.single_pattern()?];
+        while self.match_list(vec![TokenType::PIPE]) {
+            alternatives.push(self.single_pattern()?);
+        }
+        self.shallower();
+
+        if alternatives.len() == 1 {
+            return Ok(alternatives.pop().unwrap());
+        }
+        Ok(Pattern::Alternatives(alternatives))
+    }
+
+    fn single_pattern(&mut self) -> ParseResult<Pattern> {
+        if self.match_list(vec![
+            TokenType::NUMBER,
+            TokenType::STRING,
+            TokenType::TRUE,
+            TokenType::FALSE,
+            TokenType::NIL,
+        ]) {
+            let token = self.prev_token();
+            let value = match token.token_type {
+                TokenType::TRUE => VariTypes::Boolean(true),
+                TokenType::FALSE => VariTypes::Boolean(false),
+                TokenType::NIL => VariTypes::Nil,
+                _ => *token.literal.unwrap(),
+            };
+            return Ok(Pattern::Literal(value));
+        }
+
+        if self.match_list(vec![TokenType::MINUS]) {
+            let number = self.consume(TokenType::NUMBER, "Expected a number after '-'.")?;
+            return match *number.literal.unwrap() {
+                VariTypes::Num(n) => Ok(Pattern::Literal(VariTypes::Num(-n))),
+                _ => unreachable!(),
+            };
+        }
+
+        if self.match_list(vec![TokenType::IDENTIFIER]) {
+            let name = self.prev_token();
+            if self.match_list(vec![TokenType::LBRACE]) {
+                return self.struct_pattern(Some(name));
+            }
+            if name.lexeme == "_" {
+                return Ok(Pattern::Wildcard);
+            }
+            return Ok(Pattern::Binding(name));
+        }
+
+        if self.match_list(vec![TokenType::LBRACE]) {
+            return self.struct_pattern(None);
+        }
+
+        if self.match_list(vec![TokenType::LBRACKET]) {
+            return self.list_pattern();
+        }
+
+        Err(self.error(&self.peek(), "Expected a pattern."))
+    }
+
+    // `{x: 0, y}`, a field on its own binds its value to its name
+    fn struct_pattern(&mut self, name: Option<Token>) -> ParseResult<Pattern> {
+        let mut fields: Vec<(Token, Pattern)> = vec![];
+        while !self.check(TokenType::RBRACE) {
+            let field = self.consume(TokenType::IDENTIFIER, "Expected field name.")?;
+            if fields.iter().any(|(other, _)| other.lexeme == field.lexeme) {
+                let msg = format!("Duplicate field '{}' in pattern.", field.lexeme);
+                return Err(self.error(&field, &msg));
+            }
+
+            let pattern = if self.match_list(vec![TokenType::COLON]) {
+                self.pattern()?
+            } else {
+                Pattern::Binding(field.clone())
+            };
+            fields.push((field, pattern));
+
+            if !self.match_list(vec![TokenType::COMMA]) {
+                break;
+            }
+        }
+
+        self.consume(TokenType::RBRACE, "Expected '}' after struct pattern.")?;
+        Ok(Pattern::Struct { name, fields })
+    }
+
+    // `[first, ..rest]`, at most one `..` which may leave out the name
+    fn list_pattern(&mut self) -> ParseResult<Pattern> {
+        let mut head = vec![];
+        let mut rest: Option<(Option<Token>, Vec<Pattern>)> = None;
+        while !self.check(TokenType::RBRACKET) {
+            if self.match_list(vec![TokenType::DOTDOT]) {
+                let dots = self.prev_token();
+                if rest.is_some() {
+                    return Err(self.error(&dots, "Only one '..' is allowed in a list pattern."));
+                }
+
+                let mut name = None;
+                if self.match_list(vec![TokenType::IDENTIFIER]) {
+                    name = Some(self.prev_token()).filter(|name| name.lexeme != "_");
+                }
+                rest = Some((name, vec![]));
+            } else {
+                let item = self.pattern()?;
+                match &mut rest {
+                    Some((_, tail)) => tail.push(item),
+                    None => head.push(item),
+                }
+            }
+
+            if !self.match_list(vec![TokenType::COMMA]) {
+                break;
+            }
         }
 
-        panic!("Error: {}", err)
-        //VARI.error(0, err); // maybe make vari a member?
-        //unreachable!()
+        self.consume(TokenType::RBRACKET, "Expected ']' after list pattern.")?;
+        Ok(Pattern::List { head, rest })
     }
 
-    fn primary(&mut self) -> Expr {
+    fn primary(&mut self) -> ParseResult<Expr> {
         if self.match_list(vec![TokenType::FALSE]) {
-            return Expr::Literal {
+            return Ok(Expr::Literal {
                 value: Box::new(VariTypes::Boolean(false)),
-            };
+            });
         }
         if self.match_list(vec![TokenType::TRUE]) {
-            return Expr::Literal {
+            return Ok(Expr::Literal {
                 value: Box::new(VariTypes::Boolean(true)),
-            };
+            });
         }
 
         if self.match_list(vec![TokenType::NIL]) {
-            return Expr::Literal {
+            return Ok(Expr::Literal {
                 value: Box::new(VariTypes::Nil),
-            };
+            });
         }
 
         if self.match_list(vec![TokenType::NUMBER, TokenType::STRING]) {
-            return Expr::Literal {
+            return Ok(Expr::Literal {
                 value: self.prev_token().literal.unwrap(),
-            };
+            });
         }
 
         if self.match_list(vec![TokenType::IDENTIFIER]) {
-            return Expr::Variable {
-                value: self.prev_token(),
-            };
+            let name = self.prev_token();
+
+            // `Point { x: 1 }`, a brace after a name only starts fields
+            // when they're empty or a `name:` follows, so it's not
+            // mistaken for a block
+            let fields_follow = self.check_ahead(1, TokenType::RBRACE)
+                || (self.check_ahead(1, TokenType::IDENTIFIER)
+                    && self.check_ahead(2, TokenType::COLON));
+            if self.check(TokenType::LBRACE) && fields_follow {
+                self.advance();
+                return Ok(Expr::Construct {
+                    name,
+                    values: self.struct_fields()?,
+                });
+            }
+
+            return Ok(Expr::Variable { value: name });
         }
 
         if self.match_list(vec![TokenType::LPAREN]) {
-            let expr: Expr = self.expression();
-            self.consume(TokenType::RPAREN, "Expect ')' after expression.");
-            return Expr::Grouping {
+            let expr: Expr = self.expression()?;
+            self.consume(TokenType::RPAREN, "Expect ')' after expression.")?;
+            return Ok(Expr::Grouping {
                 expr: Box::new(expr),
-            };
+            });
+        }
+
+        if self.match_list(vec![TokenType::MATCH]) {
+            return self.match_expr();
         }
 
         if self.match_list(vec![TokenType::LBRACE]) {
-            let mut props = HashMap::new();
-
-            while !self.check(TokenType::RBRACE) {
-                let name = self.consume(TokenType::IDENTIFIER, "Expected name for property");
-                self.consume(TokenType::COLON, "Expected colon after property name");
-                let expr = self.expression();
-
-                props.insert(name.lexeme, expr);
-
-                match self.peek().token_type {
-                    TokenType::RBRACE => {
-                        self.consume(TokenType::RBRACE, "Expected '}' after struct");
-                        return Expr::Struct { values: props };
-                    }
-                    TokenType::COMMA => {
-                        self.consume(TokenType::COMMA, "Expected ',' after field expression");
-                    }
-                    _ => {
-                        panic!("Unexpected token."); // TODO: handle this properly
-                    }
+            return Ok(Expr::Struct {
+                values: self.struct_fields()?,
+            });
+        }
+
+        if self.match_list(vec![TokenType::LBRACKET]) {
+            let bracket = self.prev_token();
+            let mut items = vec![];
+
+            while !self.check(TokenType::RBRACKET) {
+                items.push(self.expression()?);
+
+                // a trailing comma is allowed
+                if !self.match_list(vec![TokenType::COMMA]) {
+                    break;
                 }
             }
 
-            // only happens when struct is empty
-            self.consume(TokenType::RBRACE, "Expected '}' after struct");
-            return Expr::Struct { values: props };
+            self.consume(TokenType::RBRACKET, "Expected ']' after list items.")?;
+            return Ok(Expr::List { bracket, items });
         }
 
-        panic!("Expected expression.");
+        Err(self.error(&self.peek(), "Expected expression."))
     }
 
-    fn unary(&mut self) -> Expr {
+    fn unary(&mut self) -> ParseResult<Expr> {
         if self.match_list(vec![TokenType::NOT, TokenType::MINUS]) {
             let operator: Token = self.prev_token();
-            let rhs = self.unary();
-            return Expr::Unary {
+            self.deeper()?;
+            let rhs = self.unary()?;
+            self.shallower();
+            return Ok(Expr::Unary {
                 op: operator,
                 rhs: Box::new(rhs),
-            };
+            });
         }
 
-        return self.call();
+        self.call()
     }
 
-    fn factor(&mut self) -> Expr {
-        let mut expr = self.unary();
+    fn factor(&mut self) -> ParseResult<Expr> {
+        let mut expr = self.unary()?;
 
+        let height = self.height;
         while self.match_list(vec![TokenType::SLASH, TokenType::STAR]) {
+            self.taller()?;
             let operator: Token = self.prev_token();
-            let rhs: Expr = self.unary();
+            let rhs: Expr = self.unary()?;
             expr = Expr::Binary {
                 lhs: Box::new(expr),
                 op: operator,
@@ -124,15 +446,18 @@ impl Parser {
             };
         }
 
-        expr
+        self.height = height;
+        Ok(expr)
     }
 
-    fn term(&mut self) -> Expr {
-        let mut expr: Expr = self.factor();
+    fn term(&mut self) -> ParseResult<Expr> {
+        let mut expr: Expr = self.factor()?;
 
+        let height = self.height;
         while self.match_list(vec![TokenType::MINUS, TokenType::PLUS, TokenType::MODULO]) {
+            self.taller()?;
             let operator: Token = self.prev_token();
-            let rhs: Expr = self.factor();
+            let rhs: Expr = self.factor()?;
             expr = Expr::Binary {
                 lhs: Box::new(expr),
                 op: operator,
@@ -140,20 +465,24 @@ impl Parser {
             };
         }
 
-        expr
+        self.height = height;
+        Ok(expr)
     }
 
-    fn comparison(&mut self) -> Expr {
-        let mut expr: Expr = self.term();
+    fn comparison(&mut self) -> ParseResult<Expr> {
+        let mut expr: Expr = self.term()?;
 
+        let height = self.height;
         while self.match_list(vec![
             TokenType::GT,
             TokenType::GE,
             TokenType::LT,
             TokenType::LE,
+            TokenType::IS,
         ]) {
+            self.taller()?;
             let operator: Token = self.prev_token();
-            let rhs: Expr = self.term();
+            let rhs: Expr = self.term()?;
             expr = Expr::Binary {
                 lhs: Box::new(expr),
                 op: operator,
@@ -161,19 +490,21 @@ impl Parser {
             };
         }
 
-        expr
+        self.height = height;
+        Ok(expr)
     }
 
-    fn expression(&mut self) -> Expr {
-        self.assignment()
+    fn expression(&mut self) -> ParseResult<Expr> {
+        self.deeper()?;
+        let expr = self.assignment()?;
+        self.shallower();
+        Ok(expr)
     }
 
     fn advance(&mut self) {
         if !self.done() {
             self.current += 1;
         }
-
-        self.prev_token();
     }
 
     fn peek(&self) -> Token {
@@ -197,6 +528,13 @@ impl Parser {
         self.peek().token_type == token_type
     }
 
+    // like `check`, for the token `offset` places ahead
+    fn check_ahead(&self, offset: usize, token_type: TokenType) -> bool {
+        self.tokens
+            .get(self.current + offset)
+            .is_some_and(|token| token.token_type == token_type)
+    }
+
     fn match_list(&mut self, token_types: Vec<TokenType>) -> bool {
         for token_type in token_types {
             if self.check(token_type) {
@@ -208,51 +546,77 @@ impl Parser {
         false
     }
 
-    fn block(&mut self) -> Vec<Stmt> {
+    fn block(&mut self) -> ParseResult<Vec<Stmt>> {
         let mut statements = vec![];
+        self.deeper()?;
+        let (depth, height) = (self.depth, self.height);
 
         while !self.check(TokenType::RBRACE) && !self.done() {
-            statements.push(self.declaration());
+            match self.declaration() {
+                Ok(stmt) => statements.push(stmt),
+                Err(err) => {
+                    self.errors.push(err);
+                    self.depth = depth;
+                    self.height = height;
+                    self.synchronize();
+                }
+            }
         }
 
-        self.consume(TokenType::RBRACE, "Expect '}' after block.");
+        self.consume(TokenType::RBRACE, "Expect '}' after block.")?;
+        self.shallower();
 
-        statements
+        Ok(statements)
     }
 
-    fn assignment(&mut self) -> Expr {
-        let expr = self.or();
+    fn assignment(&mut self) -> ParseResult<Expr> {
+        let expr = self.or()?;
 
         if self.match_list(vec![TokenType::EQUAL]) {
-            let _equals = self.prev_token();
-            let rhs = self.assignment();
+            let equals = self.prev_token();
+            self.deeper()?;
+            let rhs = self.assignment()?;
+            self.shallower();
 
             if let Expr::Variable { value } = expr {
-                return Expr::Assign {
+                return Ok(Expr::Assign {
                     name: value,
                     value_expr: Box::new(rhs),
-                };
+                });
             } else if let Expr::Get { expr, name } = expr {
-                return Expr::Set {
+                return Ok(Expr::Set {
                     expr,
                     name,
                     value: Box::new(rhs),
-                };
+                });
+            } else if let Expr::Index {
+                expr,
+                bracket,
+                index,
+            } = expr
+            {
+                return Ok(Expr::SetIndex {
+                    expr,
+                    bracket,
+                    index,
+                    value: Box::new(rhs),
+                });
             }
 
-            todo!()
-            // error : "Invalid assignment" on token equals
+            return Err(self.error(&equals, "Invalid assignment target."));
         }
 
-        return expr;
+        Ok(expr)
     }
 
-    fn and(&mut self) -> Expr {
-        let mut lhs = self.equality();
+    fn and(&mut self) -> ParseResult<Expr> {
+        let mut lhs = self.equality()?;
 
+        let height = self.height;
         while self.match_list(vec![TokenType::AND]) {
+            self.taller()?;
             let operator = self.prev_token();
-            let rhs = self.equality();
+            let rhs = self.equality()?;
             lhs = Expr::Logical {
                 lhs: Box::new(lhs),
                 operator,
@@ -260,30 +624,36 @@ impl Parser {
             }
         }
 
-        return lhs;
+        self.height = height;
+        Ok(lhs)
     }
 
-    fn or(&mut self) -> Expr {
-        let lhs: Expr = self.and();
+    fn or(&mut self) -> ParseResult<Expr> {
+        let mut lhs: Expr = self.and()?;
 
+        let height = self.height;
         while self.match_list(vec![TokenType::OR]) {
+            self.taller()?;
             let operator = self.prev_token();
-            let rhs = self.and();
-            return Expr::Logical {
+            let rhs = self.and()?;
+            lhs = Expr::Logical {
                 lhs: Box::new(lhs),
                 operator,
                 rhs: Box::new(rhs),
             };
         }
 
-        return lhs;
+        self.height = height;
+        Ok(lhs)
     }
 
-    fn equality(&mut self) -> Expr {
-        let mut expr: Expr = self.comparison();
+    fn equality(&mut self) -> ParseResult<Expr> {
+        let mut expr: Expr = self.comparison()?;
+        let height = self.height;
         while self.match_list(vec![TokenType::NE, TokenType::ISEQ]) {
+            self.taller()?;
             let operator: Token = self.prev_token();
-            let rhs: Expr = self.comparison();
+            let rhs: Expr = self.comparison()?;
             expr = Expr::Binary {
                 lhs: Box::new(expr),
                 op: operator,
@@ -291,54 +661,76 @@ impl Parser {
             };
         }
 
-        expr
+        self.height = height;
+        Ok(expr)
     }
 
-    fn finish_call(&mut self, callee: Expr) -> Expr {
+    fn finish_call(&mut self, callee: Expr) -> ParseResult<Expr> {
         let mut args = vec![];
         if !self.check(TokenType::RPAREN) {
-            args.push(self.expression());
+            args.push(self.expression()?);
             while self.match_list(vec![TokenType::COMMA]) {
-                args.push(self.expression());
+                args.push(self.expression()?);
             }
         }
 
-        let paren = self.consume(TokenType::RPAREN, "Expected ')' after arguments.");
+        let paren = self.consume(TokenType::RPAREN, "Expected ')' after arguments.")?;
 
         if args.len() > 255 {
-            // TODO: handle error
-            panic!("Can't have more than 255 arguments.");
+            return Err(self.error(&paren, "Can't have more than 255 arguments."));
         }
 
-        Expr::Call {
+        Ok(Expr::Call {
             callee: Box::new(callee),
             paren,
             args,
-        }
+        })
     }
 
-    fn call(&mut self) -> Expr {
-        let mut expr = self.primary();
+    fn call(&mut self) -> ParseResult<Expr> {
+        let mut expr = self.primary()?;
 
+        let height = self.height;
         loop {
             if self.match_list(vec![TokenType::LPAREN]) {
-                expr = self.finish_call(expr);
+                expr = self.finish_call(expr)?;
             } else if self.match_list(vec![TokenType::DOT]) {
-                let name = self.consume(TokenType::IDENTIFIER, "Expected property name after '.'.");
+                // keywords are fine as property names, `regex.match`
+                let is_keyword = KEYWORDS
+                    .iter()
+                    .any(|(_, keyword)| self.check(keyword.clone()));
+                let name = if is_keyword {
+                    self.advance();
+                    self.prev_token()
+                } else {
+                    self.consume(TokenType::IDENTIFIER, "Expected property name after '.'.")?
+                };
                 expr = Expr::Get {
                     expr: Box::new(expr),
                     name: name.lexeme,
                 };
+            } else if self.match_list(vec![TokenType::LBRACKET]) {
+                let bracket = self.prev_token();
+                let index = self.expression()?;
+                self.consume(TokenType::RBRACKET, "Expected ']' after index.")?;
+                expr = Expr::Index {
+                    expr: Box::new(expr),
+                    bracket,
+                    index: Box::new(index),
+                };
             } else {
                 break;
             }
+            self.taller()?;
         }
 
-        expr
+        self.height = height;
+        Ok(expr)
     }
 
-    fn for_stmt(&mut self) -> Stmt {
-        self.consume(TokenType::LPAREN, "Expect  '(' after 'for'.");
+    fn for_stmt(&mut self) -> ParseResult<Stmt> {
+        let keyword = self.prev_token();
+        self.consume(TokenType::LPAREN, "Expect  '(' after 'for'.")?;
 
         let initializer: Stmt;
 
@@ -347,39 +739,40 @@ impl Parser {
                 value: Box::new(VariTypes::Nil),
             });
         } else if self.match_list(vec![TokenType::LET]) {
-            initializer = self.var_decl();
+            initializer = self.var_decl()?;
         } else {
-            initializer = self.expr_stmt();
+            initializer = self.expr_stmt()?;
         }
 
         let mut condition: Option<Expr> = None;
 
         if !self.check(TokenType::SEMICOLON) {
-            condition = Some(self.expression());
+            condition = Some(self.expression()?);
         }
 
         self.consume(
             TokenType::SEMICOLON,
             "Expect  ';' after for loop condition.",
-        );
+        )?;
 
         let mut modifying_expr: Option<Expr> = None;
         if !self.check(TokenType::RPAREN) {
-            modifying_expr = Some(self.expression());
+            modifying_expr = Some(self.expression()?);
         }
 
-        self.consume(TokenType::RPAREN, "Expect  ')' after for loop condition.");
+        self.consume(TokenType::RPAREN, "Expect  ')' after for loop condition.")?;
 
-        let mut body = self.statement();
+        let mut body = self.statement()?;
 
         if let Some(mod_expr) = modifying_expr {
             body = Stmt::Block(vec![body, Stmt::Expression(mod_expr)]);
         }
 
         if let Some(cond) = condition {
-            body = Stmt::While(cond, Box::new(body));
+            body = Stmt::While(keyword, cond, Box::new(body));
         } else {
             body = Stmt::While(
+                keyword,
                 Expr::Literal {
                     value: Box::new(VariTypes::Boolean(true)),
                 },
@@ -388,107 +781,131 @@ impl Parser {
         }
 
         body = Stmt::Block(vec![initializer, body]);
-        body
+        Ok(body)
     }
 
-    fn if_stmt(&mut self) -> Stmt {
-        self.consume(TokenType::LPAREN, "Expect  '(' after 'if'.");
-        let condition = self.expression();
-        self.consume(TokenType::RPAREN, "Expect  ')' after if condition.");
+    fn if_stmt(&mut self) -> ParseResult<Stmt> {
+        let keyword = self.prev_token();
+        self.consume(TokenType::LPAREN, "Expect  '(' after 'if'.")?;
+        let condition = self.expression()?;
+        self.consume(TokenType::RPAREN, "Expect  ')' after if condition.")?;
 
-        let then_block = Box::new(self.statement());
+        let then_block = Box::new(self.statement()?);
         let mut else_block = None;
 
         if self.match_list(vec![TokenType::ELSE]) {
-            else_block = Some(Box::new(self.statement()));
+            else_block = Some(Box::new(self.statement()?));
         }
 
-        return Stmt::If(condition, then_block, else_block);
+        Ok(Stmt::If(keyword, condition, then_block, else_block))
     }
 
-    fn return_stmt(&mut self) -> Stmt {
+    fn return_stmt(&mut self) -> ParseResult<Stmt> {
         let keyword = self.prev_token();
         let mut value = Expr::Literal {
             value: Box::new(VariTypes::Nil),
         };
 
         if !self.check(TokenType::SEMICOLON) {
-            value = self.expression();
+            value = self.expression()?;
         }
 
-        self.consume(TokenType::SEMICOLON, "Expect ';' after return value");
+        self.consume(TokenType::SEMICOLON, "Expect ';' after return value")?;
 
-        Stmt::Return(keyword, value)
+        Ok(Stmt::Return(keyword, value))
     }
 
-    fn while_stmt(&mut self) -> Stmt {
-        self.consume(TokenType::LPAREN, "Expect '(' after 'while'");
-        let condition = self.expression();
-        self.consume(TokenType::RPAREN, "Expect ')' after 'while'");
+    fn while_stmt(&mut self) -> ParseResult<Stmt> {
+        let keyword = self.prev_token();
+        self.consume(TokenType::LPAREN, "Expect '(' after 'while'")?;
+        let condition = self.expression()?;
+        self.consume(TokenType::RPAREN, "Expect ')' after 'while'")?;
 
-        let body = self.statement();
+        let body = self.statement()?;
 
-        Stmt::While(condition, Box::new(body))
+        Ok(Stmt::While(keyword, condition, Box::new(body)))
     }
 
-    fn print_stmt(&mut self) -> Stmt {
-        let value = self.expression();
-        self.consume(TokenType::SEMICOLON, "Expected ';' after value.");
-        Stmt::Print(value)
+    fn print_stmt(&mut self) -> ParseResult<Stmt> {
+        let keyword = self.prev_token();
+        let value = self.expression()?;
+        self.consume(TokenType::SEMICOLON, "Expected ';' after value.")?;
+        Ok(Stmt::Print(keyword, value))
     }
 
-    fn expr_stmt(&mut self) -> Stmt {
-        let expr = self.expression();
-        self.consume(TokenType::SEMICOLON, "Expected ';' after value.");
-        Stmt::Expression(expr)
+    fn expr_stmt(&mut self) -> ParseResult<Stmt> {
+        let expr = self.expression()?;
+        // a match on its own ends with its braces, like a block
+        if matches!(expr, Expr::Match { .. }) || (self.bare_expression && self.done()) {
+            self.match_list(vec![TokenType::SEMICOLON]);
+        } else {
+            self.consume(TokenType::SEMICOLON, "Expected ';' after value.")?;
+        }
+        Ok(Stmt::Expression(expr))
     }
 
-    fn fun_decl(&mut self) -> Stmt {
-        let name = self.consume(TokenType::IDENTIFIER, "Expected function name after 'fun'");
-        self.consume(TokenType::LPAREN, "Expected '(' after function name.");
+    fn fun_decl(&mut self) -> ParseResult<Stmt> {
+        let name = self.consume(TokenType::IDENTIFIER, "Expected function name after 'fun'")?;
+        self.consume(TokenType::LPAREN, "Expected '(' after function name.")?;
 
         let mut params = vec![];
 
         if !self.check(TokenType::RPAREN) {
-            params.push(self.consume(TokenType::IDENTIFIER, "Expected parameter name"));
+            params.push(self.consume(TokenType::IDENTIFIER, "Expected parameter name")?);
             while self.match_list(vec![TokenType::COMMA]) {
-                params.push(self.consume(TokenType::IDENTIFIER, "Expected parameter name"));
+                params.push(self.consume(TokenType::IDENTIFIER, "Expected parameter name")?);
             }
         }
 
-        self.consume(TokenType::RPAREN, "Expected ')' after parameters.");
-        self.consume(TokenType::LBRACE, "Expected '{' after arguments list.");
-        let body = self.block();
+        self.consume(TokenType::RPAREN, "Expected ')' after parameters.")?;
+        self.consume(TokenType::LBRACE, "Expected '{' after arguments list.")?;
+        let body = self.block()?;
 
-        Stmt::Function(name, params, body)
+        Ok(Stmt::Function(name, params, body))
     }
 
-    fn var_decl(&mut self) -> Stmt {
-        let name = self.consume(TokenType::IDENTIFIER, "Expected variable name.");
+    fn var_decl(&mut self) -> ParseResult<Stmt> {
+        let name = self.consume(TokenType::IDENTIFIER, "Expected variable name.")?;
 
         let mut initializer_expr: Option<Expr> = None;
 
         if self.match_list(vec![TokenType::EQUAL]) {
-            initializer_expr = Some(self.expression());
+            initializer_expr = Some(self.expression()?);
         }
 
-        self.consume(TokenType::SEMICOLON, "Expected ';' after value.");
-        return Stmt::Var(name, initializer_expr);
+        self.consume(TokenType::SEMICOLON, "Expected ';' after value.")?;
+        Ok(Stmt::Var(name, initializer_expr))
     }
 
-    fn struct_decl(&mut self) -> Stmt {
-        let name = self.consume(TokenType::IDENTIFIER, "Expected struct name.");
-        let mut initializer_expr: Option<Expr> = None;
+    // struct Point { x, y = 0 }
+    fn struct_decl(&mut self) -> ParseResult<Stmt> {
+        let name = self.consume(TokenType::IDENTIFIER, "Expected struct name.")?;
+        self.consume(TokenType::LBRACE, "Expected '{' after struct name.")?;
 
-        if self.match_list(vec![TokenType::EQUAL]) {
-            initializer_expr = Some(self.expression()); // this should be Expr::Struct
+        let mut fields: Vec<(Token, Option<Expr>)> = vec![];
+        while !self.check(TokenType::RBRACE) {
+            let field = self.consume(TokenType::IDENTIFIER, "Expected field name.")?;
+            if fields.iter().any(|(other, _)| other.lexeme == field.lexeme) {
+                let msg = format!("Duplicate field '{}' in struct.", field.lexeme);
+                return Err(self.error(&field, &msg));
+            }
+
+            let mut default = None;
+            if self.match_list(vec![TokenType::EQUAL]) {
+                default = Some(self.expression()?);
+            }
+            fields.push((field, default));
+
+            if !self.match_list(vec![TokenType::COMMA]) {
+                break;
+            }
         }
 
-        self.consume(TokenType::SEMICOLON, "Expected ';' after value.");
-        return Stmt::Var(name, initializer_expr);
+        self.consume(TokenType::RBRACE, "Expected '}' after struct fields.")?;
+        Ok(Stmt::Struct(name, fields))
     }
 
-    fn declaration(&mut self) -> Stmt {
+    fn declaration(&mut self) -> ParseResult<Stmt> {
         if self.match_list(vec![TokenType::FUN]) {
             return self.fun_decl();
         }
@@ -501,42 +918,46 @@ impl Parser {
             return self.struct_decl();
         }
 
-        return self.statement();
+        self.statement()
     }
 
-    fn statement(&mut self) -> Stmt {
-        if self.match_list(vec![TokenType::PRINT]) {
-            return self.print_stmt();
-        }
-
-        if self.match_list(vec![TokenType::RETURN]) {
-            return self.return_stmt();
-        }
-
-        if self.match_list(vec![TokenType::LBRACE]) {
-            return Stmt::Block(self.block());
-        }
-
-        if self.match_list(vec![TokenType::WHILE]) {
-            return self.while_stmt();
-        }
-
-        if self.match_list(vec![TokenType::FOR]) {
-            return self.for_stmt();
-        }
-
-        if self.match_list(vec![TokenType::IF]) {
-            return self.if_stmt();
-        }
+    fn statement(&mut self) -> ParseResult<Stmt> {
+        self.deeper()?;
+        let stmt = if self.match_list(vec![TokenType::PRINT, TokenType::EPRINT]) {
+            self.print_stmt()?
+        } else if self.match_list(vec![TokenType::RETURN]) {
+            self.return_stmt()?
+        } else if self.match_list(vec![TokenType::LBRACE]) {
+            Stmt::Block(self.block()?)
+        } else if self.match_list(vec![TokenType::WHILE]) {
+            self.while_stmt()?
+        } else if self.match_list(vec![TokenType::FOR]) {
+            self.for_stmt()?
+        } else if self.match_list(vec![TokenType::IF]) {
+            self.if_stmt()?
+        } else {
+            self.expr_stmt()?
+        };
+        self.shallower();
 
-        self.expr_stmt()
+        Ok(stmt)
     }
 
+    // parses the whole token stream, recovering after each
+    // syntax error so they can all be reported at once
     pub fn parse(&mut self) -> Vec<Stmt> {
         let mut statements: Vec<Stmt> = vec![];
 
         while !self.done() {
-            statements.push(self.declaration());
+            match self.declaration() {
+                Ok(stmt) => statements.push(stmt),
+                Err(err) => {
+                    self.errors.push(err);
+                    self.depth = 0;
+                    self.height = 0;
+                    self.synchronize();
+                }
+            }
         }
 
         statements
@@ -1,16 +1,55 @@
 use std::{cell::RefCell, fmt, rc::Rc};
 
 use crate::{
-    environment::Environment, interpreter::Interpreter, stmt::Stmt, token::Token, vari::VariError,
+    environment::Environment,
+    expr::Expr,
+    fields::Fields,
+    interpreter::{Frame, Interpreter},
+    stmt::Stmt,
+    token::Token,
+    vari::VariError,
     vari::VariTypes,
 };
 
+// natives fail with `Interpreter::error` so the error points at the calling line
+pub type NativeFn = dyn Fn(&mut Interpreter, &[VariTypes]) -> Result<VariTypes, VariError>;
+
+// how many arguments a function accepts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arity {
+    Exact(usize),
+    // trailing arguments may be left out, the native sees fewer
+    Range(usize, usize),
+    // variadic
+    AtLeast(usize),
+}
+
+impl Arity {
+    pub fn accepts(&self, count: usize) -> bool {
+        match *self {
+            Arity::Exact(n) => count == n,
+            Arity::Range(min, max) => count >= min && count <= max,
+            Arity::AtLeast(min) => count >= min,
+        }
+    }
+}
+
+impl fmt::Display for Arity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Arity::Exact(n) => write!(f, "{}", n),
+            Arity::Range(min, max) => write!(f, "{} to {}", min, max),
+            Arity::AtLeast(min) => write!(f, "at least {}", min),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub enum Procedure {
     Native {
         name: String,
-        arity: usize,
-        body: Box<fn(&Vec<VariTypes>) -> VariTypes>,
+        arity: Arity,
+        body: Rc<NativeFn>,
     },
     User {
         arity: usize,
@@ -19,6 +58,80 @@ pub enum Procedure {
         body: Vec<Stmt>,
         closure: Rc<RefCell<Environment>>,
     },
+    // calling a struct type builds an instance from positional arguments
+    Struct(Rc<StructType>),
+}
+
+// A type declared with `struct Point { x, y = 0 }`. Defaults are
+// evaluated in the declaring scope each time an instance is built.
+pub struct StructType {
+    pub name: Token,
+    pub fields: Vec<(Token, Option<Expr>)>,
+    pub closure: Rc<RefCell<Environment>>,
+}
+
+impl fmt::Debug for StructType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<struct {}>", self.name.lexeme)
+    }
+}
+
+impl StructType {
+    pub fn name(&self) -> &str {
+        &self.name.lexeme
+    }
+
+    // positional arguments can leave out the fields after the last one
+    // without a default
+    fn arity(&self) -> Arity {
+        let required = self
+            .fields
+            .iter()
+            .rposition(|(_, default)| default.is_none())
+            .map_or(0, |last| last + 1);
+
+        if required == self.fields.len() {
+            Arity::Exact(required)
+        } else {
+            Arity::Range(required, self.fields.len())
+        }
+    }
+
+    // an instance with the `given` fields, the rest take their defaults
+    pub fn construct(
+        self: &Rc<Self>,
+        interpreter: &mut Interpreter,
+        given: Fields<VariTypes>,
+    ) -> Result<VariTypes, VariError> {
+        if let Some(unknown) = given
+            .keys()
+            .find(|name| !self.fields.iter().any(|(field, _)| field.lexeme == **name))
+        {
+            return Err(interpreter.error(format!("{} has no field '{}'.", self.name(), unknown)));
+        }
+
+        let mut fields = Fields::new();
+        for (field, default) in &self.fields {
+            let value = match (given.get(&field.lexeme), default) {
+                (Some(value), _) => value.clone(),
+                (None, Some(default)) => {
+                    *interpreter.evaluate_in(default.clone(), Rc::clone(&self.closure))?
+                }
+                (None, None) => {
+                    return Err(interpreter.error(format!(
+                        "{} needs a value for '{}'.",
+                        self.name(),
+                        field.lexeme
+                    )))
+                }
+            };
+            fields.insert(field.lexeme.clone(), value);
+        }
+
+        let instance = VariTypes::Instance(Rc::clone(self), fields);
+        interpreter.check_size(&instance)?;
+        Ok(instance)
+    }
 }
 
 impl fmt::Debug for Procedure {
@@ -33,26 +146,47 @@ impl fmt::Debug for Procedure {
                 .field("parameters", arity)
                 .field("name", &name.lexeme)
                 .finish(),
+            Self::Struct(struct_type) => struct_type.fmt(f),
         }
     }
 }
 
 impl Procedure {
-    pub fn arity(&self) -> usize {
-        match &*self {
+    pub fn name(&self) -> &str {
+        match self {
+            Procedure::Native { name, .. } => name,
+            Procedure::User { name, .. } => &name.lexeme,
+            Procedure::Struct(struct_type) => struct_type.name(),
+        }
+    }
+
+    pub fn arity(&self) -> Arity {
+        match self {
             Procedure::Native { arity, .. } => {
                 return *arity;
             }
             Procedure::User { arity, .. } => {
-                return *arity;
+                return Arity::Exact(*arity);
+            }
+            Procedure::Struct(struct_type) => {
+                return struct_type.arity();
             }
         }
     }
 
-    pub fn call(&self, interpreter: &mut Interpreter, args: Vec<VariTypes>) -> VariTypes {
-        match &*self {
-            Procedure::Native { body, .. } => (*body)(&args),
+    pub fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        args: Vec<VariTypes>,
+    ) -> Result<VariTypes, VariError> {
+        match self {
+            Procedure::Native { body, .. } => {
+                let value = body(interpreter, &args)?;
+                interpreter.check_size(&value)?;
+                Ok(value)
+            }
             Procedure::User {
+                name,
                 params,
                 body,
                 closure,
@@ -64,15 +198,33 @@ impl Procedure {
                     env.borrow_mut().define(param.lexeme.clone(), arg.clone());
                 }
 
-                match interpreter.execute_block((*body).clone(), env) {
+                interpreter.push_frame(Frame {
+                    name: name.lexeme.clone(),
+                    def_line: name.line,
+                    call_line: interpreter.line(),
+                })?;
+                let result = interpreter.execute_block((*body).clone(), env);
+                interpreter.pop_frame();
+
+                match result {
                     Err(VariError::Return(retval)) => {
-                        return retval;
+                        return Ok(retval);
                     }
+                    Err(err) => return Err(err),
                     // No return value,
                     // so return nil by default
-                    Ok(_) => return VariTypes::Nil,
+                    Ok(_) => return Ok(VariTypes::Nil),
                 }
             }
+            Procedure::Struct(struct_type) => {
+                let given = struct_type
+                    .fields
+                    .iter()
+                    .zip(args)
+                    .map(|((field, _), arg)| (field.lexeme.clone(), arg))
+                    .collect();
+                struct_type.construct(interpreter, given)
+            }
         }
     }
 }
@@ -0,0 +1,270 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use crate::interpreter::{ExecutionHook, Interpreter};
+use crate::vari::{Vari, VariError};
+
+// name used for top level code in folded stacks
+const SCRIPT_FRAME: &str = "<script>";
+
+// runs `vari --profile <file> [--folded <output>]`, returning the
+// process exit code, the report goes to stderr so the program's
+// own output is left untouched
+pub fn run(args: &[String]) -> i32 {
+    let (path, folded_path) = match args {
+        [path] => (path, None),
+        [path, flag, output] if flag == "--folded" => (path, Some(output)),
+        _ => {
+            println!("Usage: vari --profile <file> [--folded <output>]");
+            return 64;
+        }
+    };
+
+    let source = match fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(err) => {
+            println!("{}: {}", path, err);
+            return 1;
+        }
+    };
+
+    let profiler = Rc::new(RefCell::new(Profiler::new()));
+    let mut vari = Vari::new();
+    vari.interpreter
+        .set_hook(Some(Box::new(Rc::clone(&profiler))));
+
+    vari.run(&source);
+
+    let mut profiler = profiler.borrow_mut();
+    profiler.finish();
+
+    let source: Vec<&str> = source.lines().collect();
+    if let Err(err) = profiler.report(&source, &mut io::stderr()) {
+        println!("vari --profile: {}", err);
+        return 1;
+    }
+
+    if let Some(output) = folded_path {
+        let written =
+            fs::File::create(output).and_then(|mut file| profiler.write_folded(&mut file));
+        if let Err(err) = written {
+            println!("{}: {}", output, err);
+            return 1;
+        }
+    }
+
+    vari.status()
+}
+
+// a user function, by name and the line it was declared on
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct FunctionKey {
+    pub name: String,
+    pub line: usize,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FunctionStats {
+    pub calls: usize,
+    // from call to return, recursive calls are only counted once
+    pub inclusive: Duration,
+    // time spent running the function's own statements
+    pub exclusive: Duration,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LineStats {
+    // how many times a statement on the line started executing
+    pub hits: usize,
+    // time spent on the line itself, excluding calls it makes
+    pub time: Duration,
+}
+
+// Measures a program through `ExecutionHook`. Wall time between
+// two hook events is charged to the line and function that were
+// running, so every instant of the run is counted exactly once.
+pub struct Profiler {
+    pub functions: HashMap<FunctionKey, FunctionStats>,
+    pub lines: HashMap<usize, LineStats>,
+    // folded call stack -> exclusive time of its innermost frame
+    pub stacks: HashMap<String, Duration>,
+    pub total: Duration,
+    started: Instant,
+    // when time was last charged to something
+    checkpoint: Instant,
+    // calls that haven't returned yet, with when they started
+    calls: Vec<(FunctionKey, Instant)>,
+    // the line being executed, None before the first statement
+    line: Option<usize>,
+    // folded form of `calls`
+    stack: String,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        let now = Instant::now();
+
+        Self {
+            functions: HashMap::new(),
+            lines: HashMap::new(),
+            stacks: HashMap::new(),
+            total: Duration::ZERO,
+            started: now,
+            checkpoint: now,
+            calls: vec![],
+            line: None,
+            stack: SCRIPT_FRAME.to_owned(),
+        }
+    }
+
+    // charges the time since the last event to whatever was running
+    fn charge(&mut self) -> Instant {
+        let now = Instant::now();
+        let elapsed = now - self.checkpoint;
+        self.checkpoint = now;
+
+        if let Some(line) = self.line {
+            self.lines.entry(line).or_default().time += elapsed;
+        }
+        if let Some((key, _)) = self.calls.last() {
+            self.functions.entry(key.clone()).or_default().exclusive += elapsed;
+        }
+        *self.stacks.entry(self.stack.clone()).or_default() += elapsed;
+
+        now
+    }
+
+    fn fold_stack(&mut self) {
+        self.stack = SCRIPT_FRAME.to_owned();
+        for (key, _) in &self.calls {
+            self.stack.push_str(&format!(";{}:{}", key.name, key.line));
+        }
+    }
+
+    // stops the clock once the program has finished
+    pub fn finish(&mut self) {
+        let now = self.charge();
+        self.line = None;
+        self.total = now - self.started;
+    }
+
+    pub fn report(&self, source: &[&str], output: &mut dyn Write) -> io::Result<()> {
+        let mut functions: Vec<_> = self.functions.iter().collect();
+        functions.sort_by(|(a_key, a), (b_key, b)| {
+            b.exclusive.cmp(&a.exclusive).then_with(|| a_key.cmp(b_key))
+        });
+
+        let mut lines: Vec<_> = self.lines.iter().collect();
+        lines.sort_by(|(a_line, a), (b_line, b)| b.time.cmp(&a.time).then(a_line.cmp(b_line)));
+
+        writeln!(output, "Total time: {}", millis(self.total))?;
+
+        writeln!(output, "\nFunctions by exclusive time:")?;
+        writeln!(
+            output,
+            "{:>10} {:>12} {:>12}  function",
+            "calls", "inclusive", "exclusive"
+        )?;
+        for (key, stats) in functions {
+            writeln!(
+                output,
+                "{:>10} {:>12} {:>12}  {} (line {})",
+                stats.calls,
+                millis(stats.inclusive),
+                millis(stats.exclusive),
+                key.name,
+                key.line
+            )?;
+        }
+
+        writeln!(output, "\nLines by time:")?;
+        writeln!(output, "{:>10} {:>12}  line", "hits", "time")?;
+        for (line, stats) in lines {
+            let text = line
+                .checked_sub(1)
+                .and_then(|index| source.get(index))
+                .map_or("", |text| text.trim());
+            writeln!(
+                output,
+                "{:>10} {:>12}  {:>4} | {}",
+                stats.hits,
+                millis(stats.time),
+                line,
+                text
+            )?;
+        }
+
+        Ok(())
+    }
+
+    // one `frame;frame;frame microseconds` line per call stack, the
+    // input format of flamegraph.pl, inferno and speedscope
+    pub fn write_folded(&self, output: &mut dyn Write) -> io::Result<()> {
+        let mut stacks: Vec<_> = self.stacks.iter().collect();
+        stacks.sort();
+
+        for (stack, time) in stacks {
+            writeln!(output, "{} {}", stack, time.as_micros())?;
+        }
+
+        Ok(())
+    }
+}
+
+fn millis(duration: Duration) -> String {
+    format!("{:.3}ms", duration.as_secs_f64() * 1000.0)
+}
+
+// the profiler is shared with `run` so it can be read once the
+// interpreter is done with it
+impl ExecutionHook for Rc<RefCell<Profiler>> {
+    fn on_statement(
+        &mut self,
+        _interpreter: &mut Interpreter,
+        line: usize,
+    ) -> Result<(), VariError> {
+        let mut profiler = self.borrow_mut();
+        profiler.charge();
+        profiler.line = Some(line);
+        profiler.lines.entry(line).or_default().hits += 1;
+        Ok(())
+    }
+
+    fn on_call(&mut self, interpreter: &mut Interpreter) {
+        let frame = match interpreter.frames().last() {
+            Some(frame) => frame,
+            None => return,
+        };
+
+        let mut profiler = self.borrow_mut();
+        let now = profiler.charge();
+        let key = FunctionKey {
+            name: frame.name.clone(),
+            line: frame.def_line,
+        };
+
+        profiler.functions.entry(key.clone()).or_default().calls += 1;
+        profiler.calls.push((key, now));
+        profiler.fold_stack();
+    }
+
+    fn on_return(&mut self, interpreter: &mut Interpreter) {
+        let mut profiler = self.borrow_mut();
+        let now = profiler.charge();
+
+        if let Some((key, started)) = profiler.calls.pop() {
+            // a recursive call's time is already part of the outer call
+            if !profiler.calls.iter().any(|(outer, _)| *outer == key) {
+                profiler.functions.entry(key).or_default().inclusive += now - started;
+            }
+        }
+        profiler.fold_stack();
+
+        // the rest of the calling statement runs on the caller's line
+        profiler.line = interpreter.frames().last().map(|frame| frame.call_line);
+    }
+}
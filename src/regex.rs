@@ -0,0 +1,784 @@
+use std::fmt;
+
+// Patterns bigger than this once repetitions are unrolled are rejected,
+// `a{1000}{1000}` would otherwise take a million instructions.
+const MAX_INSTS: usize = 100_000;
+// the biggest count in `{n}`, `{n,}` and `{n,m}`
+const MAX_REPEAT: u32 = 1000;
+// Parsing and compiling recurse into groups and repetitions, deeper
+// patterns are rejected rather than overflowing the stack.
+const MAX_NESTING: usize = 256;
+
+// A compiled regular expression. Matching runs a Pike VM, a Thompson NFA
+// simulation that tracks capture groups, so it takes time linear in the
+// text for any pattern. Texts are slices of chars and positions count
+// chars. Matches are leftmost-first like Perl's: alternatives are tried
+// in order and quantifiers are greedy unless followed by `?`.
+#[derive(Debug, Clone)]
+pub struct Regex {
+    insts: Vec<Inst>,
+    // capture groups including the whole match, group 0
+    groups: usize,
+    names: Vec<(String, usize)>,
+}
+
+// the span of every group in a match, None for groups that didn't take part
+pub type Captures = Vec<Option<(usize, usize)>>;
+
+#[derive(Debug, Clone)]
+pub struct RegexError {
+    // the char in the pattern the error is about
+    pub position: usize,
+    pub msg: String,
+}
+
+impl fmt::Display for RegexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at character {}", self.msg, self.position + 1)
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Inst {
+    Char(char),
+    // any char but a newline
+    Any,
+    Class(Class),
+    Assert(Assertion),
+    // try the first branch before the second
+    Split(usize, usize),
+    Jump(usize),
+    // records the position in capture slot n
+    Save(usize),
+    Match,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Assertion {
+    Start,
+    End,
+    WordBoundary,
+    NotWordBoundary,
+}
+
+// a set of chars as sorted ranges of code points
+#[derive(Debug, Clone, PartialEq)]
+struct Class {
+    ranges: Vec<(u32, u32)>,
+    negated: bool,
+}
+
+impl Class {
+    fn new(ranges: &[(char, char)]) -> Self {
+        Self {
+            ranges: ranges.iter().map(|&(a, b)| (a as u32, b as u32)).collect(),
+            negated: false,
+        }
+    }
+
+    fn contains(&self, c: char) -> bool {
+        let c = c as u32;
+        self.ranges.iter().any(|&(lo, hi)| lo <= c && c <= hi) != self.negated
+    }
+
+    // the same set with `negated` folded into the ranges
+    fn normalized(mut self) -> Self {
+        self.ranges.sort_unstable();
+        if !self.negated {
+            return self;
+        }
+
+        let mut ranges = vec![];
+        let mut next = 0;
+        for &(lo, hi) in &self.ranges {
+            if lo > next {
+                ranges.push((next, lo - 1));
+            }
+            next = next.max(hi + 1);
+        }
+        if next <= char::MAX as u32 {
+            ranges.push((next, char::MAX as u32));
+        }
+        Self {
+            ranges,
+            negated: false,
+        }
+    }
+}
+
+fn digit() -> Class {
+    Class::new(&[('0', '9')])
+}
+
+fn word() -> Class {
+    Class::new(&[('0', '9'), ('A', 'Z'), ('_', '_'), ('a', 'z')])
+}
+
+fn space() -> Class {
+    Class::new(&[('\t', '\r'), (' ', ' ')])
+}
+
+fn is_word(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+#[derive(Debug)]
+enum Node {
+    Empty,
+    Char(char),
+    Any,
+    Class(Class),
+    Assert(Assertion),
+    // a capture group has its index
+    Group(Box<Node>, Option<usize>),
+    Concat(Vec<Node>),
+    Alt(Vec<Node>),
+    Repeat {
+        node: Box<Node>,
+        min: u32,
+        max: Option<u32>,
+        greedy: bool,
+    },
+}
+
+impl Regex {
+    pub fn new(pattern: &str) -> Result<Regex, RegexError> {
+        let mut parser = Parser {
+            pattern: pattern.chars().collect(),
+            current: 0,
+            groups: 1,
+            names: vec![],
+            depth: 0,
+            height: 0,
+        };
+        let node = parser.alternation()?;
+        if parser.current < parser.pattern.len() {
+            // the only thing `alternation` stops at early
+            return Err(parser.error_at(parser.current, "unmatched ')'"));
+        }
+
+        let mut compiler = Compiler { insts: vec![] };
+        compiler.push(Inst::Save(0))?;
+        compiler.node(&node)?;
+        compiler.push(Inst::Save(1))?;
+        compiler.push(Inst::Match)?;
+
+        Ok(Regex {
+            insts: compiler.insts,
+            groups: parser.groups,
+            names: parser.names,
+        })
+    }
+
+    // capture groups, counting the whole match as group 0
+    pub fn groups(&self) -> usize {
+        self.groups
+    }
+
+    // named groups and their indexes, in the order they were opened
+    pub fn names(&self) -> &[(String, usize)] {
+        &self.names
+    }
+
+    // the leftmost match starting at or after `start`
+    pub fn search(&self, text: &[char], start: usize) -> Option<Captures> {
+        self.run(text, start, false)
+    }
+
+    // a match starting right at `start`
+    pub fn match_at(&self, text: &[char], start: usize) -> Option<Captures> {
+        self.run(text, start, true)
+    }
+
+    fn run(&self, text: &[char], start: usize, anchored: bool) -> Option<Captures> {
+        let mut current = Threads::new(self.insts.len());
+        let mut next = Threads::new(self.insts.len());
+        let mut matched = None;
+
+        for pos in start..=text.len() {
+            // a new thread at every position, after the older ones
+            if matched.is_none() && (!anchored || pos == start) {
+                let slots = vec![None; self.groups * 2];
+                self.add_thread(&mut current, 0, pos, text, slots);
+            }
+            if current.list.is_empty() {
+                break;
+            }
+
+            for (pc, slots) in current.list.drain(..) {
+                let step = match &self.insts[pc] {
+                    Inst::Char(c) => text.get(pos) == Some(c),
+                    Inst::Any => matches!(text.get(pos), Some(&c) if c != '\n'),
+                    Inst::Class(class) => matches!(text.get(pos), Some(&c) if class.contains(c)),
+                    Inst::Match => {
+                        matched = Some(slots);
+                        // the threads after this one have lower priority
+                        break;
+                    }
+                    // handled by `add_thread`
+                    _ => false,
+                };
+                if step {
+                    self.add_thread(&mut next, pc + 1, pos + 1, text, slots);
+                }
+            }
+
+            current.clear();
+            std::mem::swap(&mut current, &mut next);
+        }
+
+        matched.map(|slots| {
+            slots
+                .chunks(2)
+                .map(|pair| match (pair[0], pair[1]) {
+                    (Some(start), Some(end)) => Some((start, end)),
+                    _ => None,
+                })
+                .collect()
+        })
+    }
+
+    // adds the thread at `pc` and every thread it reaches without
+    // consuming a char, in priority order
+    fn add_thread(
+        &self,
+        threads: &mut Threads,
+        pc: usize,
+        pos: usize,
+        text: &[char],
+        slots: Vec<Option<usize>>,
+    ) {
+        let mut stack = vec![(pc, slots)];
+        while let Some((pc, mut slots)) = stack.pop() {
+            if threads.seen[pc] {
+                continue;
+            }
+            threads.seen[pc] = true;
+
+            match &self.insts[pc] {
+                Inst::Jump(to) => stack.push((*to, slots)),
+                Inst::Split(first, second) => {
+                    stack.push((*second, slots.clone()));
+                    stack.push((*first, slots));
+                }
+                Inst::Save(slot) => {
+                    slots[*slot] = Some(pos);
+                    stack.push((pc + 1, slots));
+                }
+                Inst::Assert(assertion) => {
+                    if holds(*assertion, text, pos) {
+                        stack.push((pc + 1, slots));
+                    }
+                }
+                _ => threads.list.push((pc, slots)),
+            }
+        }
+    }
+}
+
+fn holds(assertion: Assertion, text: &[char], pos: usize) -> bool {
+    let word_before = pos > 0 && is_word(text[pos - 1]);
+    let word_after = pos < text.len() && is_word(text[pos]);
+    match assertion {
+        Assertion::Start => pos == 0,
+        Assertion::End => pos == text.len(),
+        Assertion::WordBoundary => word_before != word_after,
+        Assertion::NotWordBoundary => word_before == word_after,
+    }
+}
+
+// the threads at one position of the text
+struct Threads {
+    list: Vec<(usize, Vec<Option<usize>>)>,
+    seen: Vec<bool>,
+}
+
+impl Threads {
+    fn new(len: usize) -> Self {
+        Self {
+            list: vec![],
+            seen: vec![false; len],
+        }
+    }
+
+    fn clear(&mut self) {
+        self.list.clear();
+        self.seen.fill(false);
+    }
+}
+
+struct Parser {
+    pattern: Vec<char>,
+    current: usize,
+    // capture groups opened so far, counting group 0
+    groups: usize,
+    names: Vec<(String, usize)>,
+    // groups currently open
+    depth: usize,
+    // how deep the node parsed last is, repetitions of it nest
+    // it further without another group
+    height: usize,
+}
+
+impl Parser {
+    fn error_at(&self, position: usize, msg: &str) -> RegexError {
+        RegexError {
+            position,
+            msg: msg.to_owned(),
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.pattern.get(self.current).copied()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.current += 1;
+            return true;
+        }
+        false
+    }
+
+    // one more level around the node parsed last
+    fn wrap(&mut self, start: usize) -> Result<(), RegexError> {
+        self.height += 1;
+        if self.height > MAX_NESTING {
+            return Err(self.error_at(start, "pattern nested too deeply"));
+        }
+        Ok(())
+    }
+
+    // a|b|c, up to a ')' or the end of the pattern
+    fn alternation(&mut self) -> Result<Node, RegexError> {
+        let start = self.current;
+        let mut branches = vec![self.concat()?];
+        let mut height = self.height;
+        while self.eat('|') {
+            branches.push(self.concat()?);
+            height = height.max(self.height);
+        }
+
+        self.height = height;
+        if branches.len() == 1 {
+            return Ok(branches.pop().unwrap());
+        }
+        self.wrap(start)?;
+        Ok(Node::Alt(branches))
+    }
+
+    fn concat(&mut self) -> Result<Node, RegexError> {
+        let start = self.current;
+        let mut nodes = vec![];
+        let mut height = 0;
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            let atom = self.atom()?;
+            nodes.push(self.repetition(atom)?);
+            height = height.max(self.height);
+        }
+
+        self.height = height;
+        match nodes.len() {
+            0 => Ok(Node::Empty),
+            1 => Ok(nodes.pop().unwrap()),
+            _ => {
+                self.wrap(start)?;
+                Ok(Node::Concat(nodes))
+            }
+        }
+    }
+
+    // any quantifiers after `node`
+    fn repetition(&mut self, mut node: Node) -> Result<Node, RegexError> {
+        loop {
+            let start = self.current;
+            let (min, max) = match self.peek() {
+                Some('*') => (0, None),
+                Some('+') => (1, None),
+                Some('?') => (0, Some(1)),
+                Some('{') => match self.counts()? {
+                    Some(counts) => counts,
+                    // not a repetition, the `{` is a plain char
+                    None => return Ok(node),
+                },
+                _ => return Ok(node),
+            };
+            if start == self.current {
+                self.current += 1;
+            }
+
+            if matches!(node, Node::Assert(_) | Node::Empty) {
+                return Err(self.error_at(start, "nothing to repeat"));
+            }
+            self.wrap(start)?;
+            let greedy = !self.eat('?');
+            node = Node::Repeat {
+                node: Box::new(node),
+                min,
+                max,
+                greedy,
+            };
+        }
+    }
+
+    // `{n}`, `{n,}` or `{n,m}`, consumed only when it's one of those
+    fn counts(&mut self) -> Result<Option<(u32, Option<u32>)>, RegexError> {
+        let start = self.current;
+        let close = match self.pattern[start..].iter().position(|&c| c == '}') {
+            Some(offset) => start + offset,
+            None => return Ok(None),
+        };
+        let inside: String = self.pattern[start + 1..close].iter().collect();
+        let number = |s: &str| {
+            if s.is_empty() || !s.chars().all(|c| c.is_ascii_digit()) {
+                return None;
+            }
+            // too many digits saturates and fails the check below
+            Some(s.parse::<u32>().unwrap_or(u32::MAX))
+        };
+
+        let (min, max) = match inside.split_once(',') {
+            None => match number(&inside) {
+                Some(n) => (n, Some(n)),
+                None => return Ok(None),
+            },
+            Some((min, "")) => match number(min) {
+                Some(min) => (min, None),
+                None => return Ok(None),
+            },
+            Some((min, max)) => match (number(min), number(max)) {
+                (Some(min), Some(max)) => (min, Some(max)),
+                _ => return Ok(None),
+            },
+        };
+
+        if min > MAX_REPEAT || max.is_some_and(|max| max > MAX_REPEAT) {
+            let msg = format!("repetition count over {}", MAX_REPEAT);
+            return Err(self.error_at(start, &msg));
+        }
+        if max.is_some_and(|max| max < min) {
+            return Err(self.error_at(start, "repetition range is backwards"));
+        }
+
+        self.current = close + 1;
+        Ok(Some((min, max)))
+    }
+
+    fn atom(&mut self) -> Result<Node, RegexError> {
+        let start = self.current;
+        let c = self.peek().unwrap();
+        self.current += 1;
+        // a group sets its own
+        self.height = 1;
+
+        match c {
+            '.' => Ok(Node::Any),
+            '^' => Ok(Node::Assert(Assertion::Start)),
+            '$' => Ok(Node::Assert(Assertion::End)),
+            '(' => self.group(start),
+            '[' => self.class(start).map(Node::Class),
+            '\\' => self.escape(start, false),
+            '*' | '+' | '?' => Err(self.error_at(start, "nothing to repeat")),
+            c => Ok(Node::Char(c)),
+        }
+    }
+
+    // after the `(`: (...), (?:...), (?P<name>...) or (?<name>...)
+    fn group(&mut self, start: usize) -> Result<Node, RegexError> {
+        let mut index = None;
+        if self.eat('?') {
+            if self.eat('P') && self.peek() != Some('<') {
+                return Err(self.error_at(self.current, "expected '<' after '(?P'"));
+            }
+            if self.eat('<') {
+                let name = self.group_name()?;
+                index = Some(self.groups);
+                self.names.push((name, self.groups));
+                self.groups += 1;
+            } else if !self.eat(':') {
+                return Err(self.error_at(start, "unknown group flag"));
+            }
+        } else {
+            index = Some(self.groups);
+            self.groups += 1;
+        }
+
+        if self.depth == MAX_NESTING {
+            return Err(self.error_at(start, "pattern nested too deeply"));
+        }
+        self.depth += 1;
+        let node = self.alternation()?;
+        self.depth -= 1;
+        if !self.eat(')') {
+            return Err(self.error_at(start, "unclosed group"));
+        }
+        self.wrap(start)?;
+        Ok(Node::Group(Box::new(node), index))
+    }
+
+    // after the `<`, up to and including the `>`
+    fn group_name(&mut self) -> Result<String, RegexError> {
+        let start = self.current;
+        let mut name = String::new();
+        while let Some(c) = self.peek() {
+            if c == '>' {
+                break;
+            }
+            if !is_word(c) {
+                return Err(self.error_at(self.current, "bad group name"));
+            }
+            name.push(c);
+            self.current += 1;
+        }
+
+        if !self.eat('>') {
+            return Err(self.error_at(start, "unclosed group name"));
+        }
+        if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+            return Err(self.error_at(start, "bad group name"));
+        }
+        if self.names.iter().any(|(other, _)| *other == name) {
+            return Err(self.error_at(start, "duplicate group name"));
+        }
+        Ok(name)
+    }
+
+    // after the `[`, up to and including the `]`
+    fn class(&mut self, start: usize) -> Result<Class, RegexError> {
+        let mut class = Class {
+            ranges: vec![],
+            negated: self.eat('^'),
+        };
+
+        // a `]` right at the start is a plain char
+        let mut first = true;
+        loop {
+            let item_start = self.current;
+            let lo = match self.peek() {
+                None => return Err(self.error_at(start, "unclosed character class")),
+                Some(']') if !first => {
+                    self.current += 1;
+                    return Ok(class);
+                }
+                Some('\\') => {
+                    self.current += 1;
+                    match self.escape(item_start, true)? {
+                        Node::Char(c) => c,
+                        Node::Class(set) => {
+                            class.ranges.extend(set.normalized().ranges);
+                            first = false;
+                            continue;
+                        }
+                        _ => unreachable!("escapes in classes are chars or sets"),
+                    }
+                }
+                Some(c) => {
+                    self.current += 1;
+                    c
+                }
+            };
+            first = false;
+
+            // a `-` at the end is a plain char
+            let is_range = self.peek() == Some('-')
+                && !matches!(self.pattern.get(self.current + 1), Some(']') | None);
+            if !is_range {
+                class.ranges.push((lo as u32, lo as u32));
+                continue;
+            }
+
+            self.current += 1;
+            let hi = match self.peek() {
+                Some('\\') => {
+                    self.current += 1;
+                    match self.escape(self.current - 1, true)? {
+                        Node::Char(c) => c,
+                        _ => return Err(self.error_at(item_start, "bad range in character class")),
+                    }
+                }
+                Some(c) => {
+                    self.current += 1;
+                    c
+                }
+                None => return Err(self.error_at(start, "unclosed character class")),
+            };
+            if hi < lo {
+                return Err(self.error_at(item_start, "range is backwards"));
+            }
+            class.ranges.push((lo as u32, hi as u32));
+        }
+    }
+
+    // after the `\`, in a character class only chars and sets are allowed
+    fn escape(&mut self, start: usize, in_class: bool) -> Result<Node, RegexError> {
+        let c = match self.peek() {
+            Some(c) => c,
+            None => return Err(self.error_at(start, "trailing backslash")),
+        };
+        self.current += 1;
+
+        let negated = |mut class: Class| {
+            class.negated = true;
+            class
+        };
+        let node = match c {
+            'd' => Node::Class(digit()),
+            'w' => Node::Class(word()),
+            's' => Node::Class(space()),
+            'D' => Node::Class(negated(digit())),
+            'W' => Node::Class(negated(word())),
+            'S' => Node::Class(negated(space())),
+            'b' if !in_class => Node::Assert(Assertion::WordBoundary),
+            'B' if !in_class => Node::Assert(Assertion::NotWordBoundary),
+            'n' => Node::Char('\n'),
+            'r' => Node::Char('\r'),
+            't' => Node::Char('\t'),
+            c if c.is_alphanumeric() => {
+                let msg = format!("unknown escape '\\{}'", c);
+                return Err(self.error_at(start, &msg));
+            }
+            c => Node::Char(c),
+        };
+        Ok(node)
+    }
+}
+
+struct Compiler {
+    insts: Vec<Inst>,
+}
+
+impl Compiler {
+    fn push(&mut self, inst: Inst) -> Result<usize, RegexError> {
+        if self.insts.len() == MAX_INSTS {
+            return Err(RegexError {
+                position: 0,
+                msg: "pattern is too big".to_owned(),
+            });
+        }
+        self.insts.push(inst);
+        Ok(self.insts.len() - 1)
+    }
+
+    // points the split or jump at `at` to `to`, the second branch for a split
+    fn patch(&mut self, at: usize, to: usize) {
+        match &mut self.insts[at] {
+            Inst::Split(_, second) => *second = to,
+            Inst::Jump(target) => *target = to,
+            _ => unreachable!("only splits and jumps are patched"),
+        }
+    }
+
+    fn node(&mut self, node: &Node) -> Result<(), RegexError> {
+        match node {
+            Node::Empty => {}
+            Node::Char(c) => {
+                self.push(Inst::Char(*c))?;
+            }
+            Node::Any => {
+                self.push(Inst::Any)?;
+            }
+            Node::Class(class) => {
+                self.push(Inst::Class(class.clone()))?;
+            }
+            Node::Assert(assertion) => {
+                self.push(Inst::Assert(*assertion))?;
+            }
+            Node::Group(node, index) => match index {
+                Some(index) => {
+                    self.push(Inst::Save(index * 2))?;
+                    self.node(node)?;
+                    self.push(Inst::Save(index * 2 + 1))?;
+                }
+                None => self.node(node)?,
+            },
+            Node::Concat(nodes) => {
+                for node in nodes {
+                    self.node(node)?;
+                }
+            }
+            Node::Alt(branches) => {
+                // split(L1, next); L1: a; jump end; next: split(L2, ...)
+                let mut jumps = vec![];
+                for (i, branch) in branches.iter().enumerate() {
+                    if i == branches.len() - 1 {
+                        self.node(branch)?;
+                        break;
+                    }
+                    let split = self.push(Inst::Split(self.insts.len() + 1, 0))?;
+                    self.node(branch)?;
+                    jumps.push(self.push(Inst::Jump(0))?);
+                    let next = self.insts.len();
+                    self.patch(split, next);
+                }
+                let end = self.insts.len();
+                for jump in jumps {
+                    self.patch(jump, end);
+                }
+            }
+            Node::Repeat {
+                node,
+                min,
+                max,
+                greedy,
+            } => self.repeat(node, *min, *max, *greedy)?,
+        }
+        Ok(())
+    }
+
+    fn repeat(
+        &mut self,
+        node: &Node,
+        min: u32,
+        max: Option<u32>,
+        greedy: bool,
+    ) -> Result<(), RegexError> {
+        for _ in 0..min {
+            self.node(node)?;
+        }
+
+        match max {
+            // loop: split(body, end); body; jump loop
+            None => {
+                let split = self.split(greedy)?;
+                self.node(node)?;
+                self.push(Inst::Jump(split))?;
+                let end = self.insts.len();
+                self.set_exit(split, end, greedy);
+            }
+            // optional copies, each one only tried if the one before matched
+            Some(max) => {
+                let mut splits = vec![];
+                for _ in min..max {
+                    splits.push(self.split(greedy)?);
+                    self.node(node)?;
+                }
+                let end = self.insts.len();
+                for split in splits {
+                    self.set_exit(split, end, greedy);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // a split whose body is the next instruction, its exit is set later
+    fn split(&mut self, greedy: bool) -> Result<usize, RegexError> {
+        let body = self.insts.len() + 1;
+        if greedy {
+            self.push(Inst::Split(body, 0))
+        } else {
+            self.push(Inst::Split(0, body))
+        }
+    }
+
+    fn set_exit(&mut self, split: usize, exit: usize, greedy: bool) {
+        match &mut self.insts[split] {
+            Inst::Split(_, second) if greedy => *second = exit,
+            Inst::Split(first, _) => *first = exit,
+            _ => unreachable!("only splits have exits"),
+        }
+    }
+}
@@ -0,0 +1,332 @@
+use std::collections::HashMap;
+
+use crate::diagnostic::Diagnostic;
+use crate::expr::{Expr, ExprVisitor, Pattern};
+use crate::stmt::{Stmt, StmtVisitor};
+use crate::token::Token;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Variable,
+    Parameter,
+    Function,
+    Struct,
+}
+
+// a `let`, `fn` or parameter binding and every place it is used
+#[derive(Clone)]
+pub struct Symbol {
+    pub name: Token,
+    pub kind: SymbolKind,
+    // parameter names, only for functions
+    pub params: Option<Vec<String>>,
+    pub references: Vec<Token>,
+    // how many of `references` read the value rather than assign it
+    pub reads: usize,
+    // the binding from an enclosing scope hidden by this one
+    pub shadows: Option<usize>,
+    // index of the enclosing function's symbol, None at top level
+    pub parent: Option<usize>,
+}
+
+fn token_contains(token: &Token, line: usize, column: usize) -> bool {
+    token.line == line && column >= token.column && column <= token.column + token.lexeme.len()
+}
+
+pub struct Resolution {
+    pub symbols: Vec<Symbol>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Resolution {
+    // finds the symbol declared or referenced at the given position
+    pub fn symbol_at(&self, line: usize, column: usize) -> Option<usize> {
+        self.symbols.iter().position(|symbol| {
+            token_contains(&symbol.name, line, column)
+                || symbol
+                    .references
+                    .iter()
+                    .any(|token| token_contains(token, line, column))
+        })
+    }
+}
+
+// Walks the syntax tree, matching every variable use with
+// the binding it refers to. Mirrors the scoping rules used by
+// `Interpreter`: blocks open a new scope, function parameters
+// and body share one and top level names may be used before
+// they are declared (from inside a function body).
+pub struct Resolver {
+    // name -> index into `symbols`, innermost scope last
+    scopes: Vec<HashMap<String, usize>>,
+    symbols: Vec<Symbol>,
+    diagnostics: Vec<Diagnostic>,
+    // names defined by the interpreter itself, like `clock`
+    builtins: Vec<String>,
+    // references that didn't match a binding when they were seen,
+    // checked against the top level scope once everything is declared
+    unresolved: Vec<(Token, bool)>,
+    current_function: Option<usize>,
+}
+
+impl Resolver {
+    pub fn new(builtins: Vec<String>) -> Self {
+        Self {
+            scopes: vec![HashMap::new()],
+            symbols: vec![],
+            diagnostics: vec![],
+            builtins,
+            unresolved: vec![],
+            current_function: None,
+        }
+    }
+
+    pub fn resolve(mut self, statements: &[Stmt]) -> Resolution {
+        for stmt in statements {
+            self.visit_stmt(stmt.clone());
+        }
+
+        for (token, read) in std::mem::take(&mut self.unresolved) {
+            if let Some(&index) = self.scopes[0].get(&token.lexeme) {
+                self.add_reference(index, token, read);
+            } else if !self.builtins.contains(&token.lexeme) {
+                self.diagnostics.push(Diagnostic::error(
+                    token.line,
+                    token.column,
+                    token.lexeme.len(),
+                    format!("Undefined variable '{}'.", token.lexeme),
+                ));
+            }
+        }
+
+        Resolution {
+            symbols: self.symbols,
+            diagnostics: self.diagnostics,
+        }
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn end_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    fn declare(&mut self, name: Token, kind: SymbolKind, params: Option<Vec<String>>) -> usize {
+        let index = self.symbols.len();
+        let (innermost, enclosing) = self.scopes.split_last().unwrap();
+        let shadows = if innermost.contains_key(&name.lexeme) {
+            // redeclaring in the same scope replaces the old binding
+            None
+        } else {
+            enclosing
+                .iter()
+                .rev()
+                .find_map(|scope| scope.get(&name.lexeme).copied())
+        };
+
+        self.symbols.push(Symbol {
+            name: name.clone(),
+            kind,
+            params,
+            references: vec![],
+            reads: 0,
+            shadows,
+            parent: self.current_function,
+        });
+
+        self.scopes.last_mut().unwrap().insert(name.lexeme, index);
+        index
+    }
+
+    fn add_reference(&mut self, index: usize, name: Token, read: bool) {
+        let symbol = &mut self.symbols[index];
+        symbol.references.push(name);
+        if read {
+            symbol.reads += 1;
+        }
+    }
+
+    // `read` is false when the reference only assigns to the binding
+    fn reference(&mut self, name: Token, read: bool) {
+        let found = self
+            .scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(&name.lexeme).copied());
+
+        match found {
+            Some(index) => self.add_reference(index, name, read),
+            None => self.unresolved.push((name, read)),
+        }
+    }
+
+    // struct types named in a pattern
+    fn resolve_pattern(&mut self, pattern: &Pattern) {
+        match pattern {
+            Pattern::Literal(_) | Pattern::Wildcard | Pattern::Binding(_) => {}
+            Pattern::Alternatives(patterns) => {
+                for pattern in patterns {
+                    self.resolve_pattern(pattern);
+                }
+            }
+            Pattern::Struct { name, fields } => {
+                if let Some(name) = name {
+                    self.reference(name.clone(), true);
+                }
+                for (_, field) in fields {
+                    self.resolve_pattern(field);
+                }
+            }
+            Pattern::List { head, rest } => {
+                let tail = rest.iter().flat_map(|(_, tail)| tail);
+                for item in head.iter().chain(tail) {
+                    self.resolve_pattern(item);
+                }
+            }
+        }
+    }
+
+    fn resolve_stmts(&mut self, statements: Vec<Stmt>) {
+        for stmt in statements {
+            self.visit_stmt(stmt);
+        }
+    }
+}
+
+impl StmtVisitor<()> for Resolver {
+    fn visit_stmt(&mut self, stmt: Stmt) {
+        match stmt {
+            Stmt::Block(statements) => {
+                self.begin_scope();
+                self.resolve_stmts(statements);
+                self.end_scope();
+            }
+            Stmt::Expression(expr) | Stmt::Print(_, expr) => self.visit_expr(expr),
+            Stmt::Var(name, initializer) => {
+                // the initializer runs before the name is defined,
+                // so `let a = a;` reads the outer `a`
+                if let Some(expr) = initializer {
+                    self.visit_expr(expr);
+                }
+                self.declare(name, SymbolKind::Variable, None);
+            }
+            Stmt::If(_, condition, then_branch, else_branch) => {
+                self.visit_expr(condition);
+                self.visit_stmt(*then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.visit_stmt(*else_branch);
+                }
+            }
+            Stmt::While(_, condition, body) => {
+                self.visit_expr(condition);
+                self.visit_stmt(*body);
+            }
+            Stmt::Function(name, params, body) => {
+                let param_names = params.iter().map(|param| param.lexeme.clone()).collect();
+                let index = self.declare(name, SymbolKind::Function, Some(param_names));
+
+                let enclosing_function = self.current_function.replace(index);
+                self.begin_scope();
+                for param in params {
+                    self.declare(param, SymbolKind::Parameter, None);
+                }
+                self.resolve_stmts(body);
+                self.end_scope();
+                self.current_function = enclosing_function;
+            }
+            Stmt::Struct(name, fields) => {
+                for (_, default) in fields {
+                    if let Some(expr) = default {
+                        self.visit_expr(expr);
+                    }
+                }
+                self.declare(name, SymbolKind::Struct, None);
+            }
+            Stmt::Return(keyword, value) => {
+                if self.current_function.is_none() {
+                    self.diagnostics.push(Diagnostic::error(
+                        keyword.line,
+                        keyword.column,
+                        keyword.lexeme.len(),
+                        "Can't return from top-level code.".to_owned(),
+                    ));
+                }
+                self.visit_expr(value);
+            }
+        }
+    }
+}
+
+impl ExprVisitor<()> for Resolver {
+    fn visit_expr(&mut self, expr: Expr) {
+        match expr {
+            Expr::Binary { lhs, rhs, .. } | Expr::Logical { lhs, rhs, .. } => {
+                self.visit_expr(*lhs);
+                self.visit_expr(*rhs);
+            }
+            Expr::Call { callee, args, .. } => {
+                self.visit_expr(*callee);
+                for arg in args {
+                    self.visit_expr(arg);
+                }
+            }
+            Expr::Unary { rhs, .. } => self.visit_expr(*rhs),
+            Expr::Grouping { expr } => self.visit_expr(*expr),
+            Expr::Struct { values } => {
+                for (_, value) in values {
+                    self.visit_expr(value);
+                }
+            }
+            Expr::Construct { name, values } => {
+                self.reference(name, true);
+                for (_, value) in values {
+                    self.visit_expr(value);
+                }
+            }
+            Expr::List { items, .. } => {
+                for item in items {
+                    self.visit_expr(item);
+                }
+            }
+            Expr::Index { expr, index, .. } => {
+                self.visit_expr(*expr);
+                self.visit_expr(*index);
+            }
+            Expr::SetIndex {
+                expr, index, value, ..
+            } => {
+                self.visit_expr(*expr);
+                self.visit_expr(*index);
+                self.visit_expr(*value);
+            }
+            Expr::Get { expr, .. } => self.visit_expr(*expr),
+            Expr::Set { expr, value, .. } => {
+                self.visit_expr(*expr);
+                self.visit_expr(*value);
+            }
+            Expr::Literal { .. } => {}
+            Expr::Variable { value } => self.reference(value, true),
+            Expr::Assign { name, value_expr } => {
+                self.visit_expr(*value_expr);
+                self.reference(name, false);
+            }
+            Expr::Match { value, arms, .. } => {
+                self.visit_expr(*value);
+                for arm in arms {
+                    self.begin_scope();
+                    self.resolve_pattern(&arm.pattern);
+                    for name in arm.pattern.bindings() {
+                        self.declare(name, SymbolKind::Variable, None);
+                    }
+                    if let Some(guard) = arm.guard {
+                        self.visit_expr(guard);
+                    }
+                    self.visit_expr(arm.body);
+                    self.end_scope();
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use std::cell::Cell;
+use std::fmt;
+use std::mem;
+use std::time::Duration;
+
+use crate::vari::VariTypes;
+
+pub const DEFAULT_MAX_DEPTH: usize = 1000;
+
+// Lists can be nested as deep as a script likes. Comparing, printing
+// and converting them recurses, so they only go this many lists and
+// structs deep. Structs are copied by recursing into the structs in
+// them, so they can't be built any deeper than this.
+pub const MAX_VALUE_DEPTH: usize = 512;
+
+// Each level of recursion takes a few KB of Rust stack in release
+// builds and several times that in debug builds, so deep recursion
+// usually runs into this first. Half of the 2 MB a spawned thread
+// gets by default, the rest is left for the host. Hosts that run
+// scripts on a bigger stack can raise `Limits::max_stack`.
+pub const DEFAULT_MAX_STACK: usize = 1024 * 1024;
+
+thread_local! {
+    // the current thread's stack size, if it was recorded
+    // with `set_thread_stack_size`
+    static THREAD_STACK_SIZE: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+// for threads we spawn ourselves, so scripts on them get to use
+// half of the stack instead of `DEFAULT_MAX_STACK`
+pub(crate) fn set_thread_stack_size(size: usize) {
+    THREAD_STACK_SIZE.with(|stack| stack.set(Some(size)));
+}
+
+// how much stack the interpreter and parser may use on this thread
+pub(crate) fn default_max_stack() -> usize {
+    THREAD_STACK_SIZE.with(|stack| stack.get().map_or(DEFAULT_MAX_STACK, |size| size / 2))
+}
+
+// Bounds on what a script may use, checked as it runs. `None`
+// means unlimited, the usage is reset by `Interpreter::reset_usage`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    // statements executed, including every pass through a loop body
+    pub max_steps: Option<u64>,
+    // wall clock time
+    pub max_time: Option<Duration>,
+    // nested function calls
+    pub max_depth: Option<usize>,
+    // bytes of Rust stack the interpreter may use below the point
+    // where the usage was last reset, going over is a `Limit::Depth`
+    pub max_stack: Option<usize>,
+    // bytes a single string, list or struct may take up
+    pub max_memory: Option<usize>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_steps: None,
+            max_time: None,
+            max_depth: Some(DEFAULT_MAX_DEPTH),
+            max_stack: Some(default_max_stack()),
+            max_memory: None,
+        }
+    }
+}
+
+// the limit a script ran into
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    Steps,
+    Time,
+    Depth,
+    Memory,
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Limit::Steps => write!(f, "Step limit exceeded."),
+            Limit::Time => write!(f, "Time limit exceeded."),
+            Limit::Depth => write!(f, "Maximum recursion depth exceeded."),
+            Limit::Memory => write!(f, "Memory limit exceeded."),
+        }
+    }
+}
+
+// roughly how far down the stack the caller is, the distance
+// between two of these is how much stack was used in between
+pub(crate) fn stack_position() -> usize {
+    let marker = 0u8;
+    std::hint::black_box(&marker) as *const u8 as usize
+}
+
+// Natives that reach outside the interpreter are only defined
+// when the host grants the capability they need.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Capability {
+    // reading and writing files
+    FileSystem,
+    // environment variables, running commands and other process state
+    Process,
+}
+
+// what the command line tools grant scripts
+pub const ALL_CAPABILITIES: &[Capability] = &[Capability::FileSystem, Capability::Process];
+
+// roughly how many bytes `value` itself takes up, counted against
+// `Limits::max_memory`, values it contains are checked on their own
+pub fn size_of(value: &VariTypes) -> usize {
+    match value {
+        VariTypes::String(s) => s.len(),
+        VariTypes::Struct(fields) | VariTypes::Instance(_, fields) => fields
+            .keys()
+            .map(|name| name.len() + mem::size_of::<VariTypes>())
+            .sum(),
+        VariTypes::List(items) => items.borrow().len() * mem::size_of::<VariTypes>(),
+        _ => mem::size_of::<VariTypes>(),
+    }
+}
+
+// how many structs deep `value` goes, a list in between starts over
+// since lists aren't copied
+pub(crate) fn struct_depth(value: &VariTypes) -> usize {
+    match value.fields() {
+        Some(fields) => 1 + fields.values().map(struct_depth).max().unwrap_or(0),
+        None => 0,
+    }
+}
@@ -0,0 +1,205 @@
+use std::rc::Rc;
+
+use crate::fields::Fields;
+use crate::interpreter::Interpreter;
+use crate::procedure::{Arity, Procedure};
+use crate::sandbox::Capability;
+use crate::vari::{Items, VariError, VariTypes};
+
+mod builtins;
+mod format;
+mod fs;
+mod io;
+mod json;
+mod lists;
+mod math;
+mod os;
+mod random;
+mod regex;
+mod string;
+mod time;
+
+// defines the standard library modules in `interpreter`'s globals
+pub fn define(interpreter: &mut Interpreter) {
+    builtins::define(interpreter);
+    format::define(interpreter);
+    io::define(interpreter);
+    json::define(interpreter);
+    lists::define(interpreter);
+    math::define(interpreter);
+    random::define(interpreter);
+    regex::define(interpreter);
+    string::define(interpreter);
+    time::define(interpreter);
+
+    if interpreter.has_capability(Capability::FileSystem) {
+        fs::define(interpreter);
+    }
+    if interpreter.has_capability(Capability::Process) {
+        os::define(interpreter);
+    }
+}
+
+// A namespace of natives and constants, defined as a global struct
+// so scripts call `math.sqrt(2)`. Natives are named after their
+// module, "math.sqrt", in error messages.
+pub struct Module {
+    name: &'static str,
+    fields: Fields<VariTypes>,
+}
+
+impl Module {
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            fields: Fields::new(),
+        }
+    }
+
+    // `body` gets the native's full name for its error messages
+    pub fn native<F>(&mut self, name: &str, arity: Arity, body: F)
+    where
+        F: Fn(&mut Interpreter, &str, &[VariTypes]) -> Result<VariTypes, VariError> + 'static,
+    {
+        let full_name = format!("{}.{}", self.name, name);
+        let native = native(full_name.clone(), arity, move |interpreter, args| {
+            body(interpreter, &full_name, args)
+        });
+
+        self.fields.insert(name.to_owned(), native);
+    }
+
+    pub fn constant(&mut self, name: &str, value: VariTypes) {
+        self.fields.insert(name.to_owned(), value);
+    }
+
+    pub fn define(self, interpreter: &mut Interpreter) {
+        interpreter
+            .globals
+            .borrow_mut()
+            .define(self.name.to_owned(), VariTypes::Struct(self.fields));
+    }
+}
+
+// a native function value, for natives that aren't module members
+pub fn native<F>(name: String, arity: Arity, body: F) -> VariTypes
+where
+    F: Fn(&mut Interpreter, &[VariTypes]) -> Result<VariTypes, VariError> + 'static,
+{
+    VariTypes::Callable(Procedure::Native {
+        name,
+        arity,
+        body: Rc::new(body),
+    })
+}
+
+// the error for an argument of the wrong type, worded like the
+// ones `Engine::register` gives host functions
+pub fn type_error(
+    interpreter: &Interpreter,
+    name: &str,
+    expected: &str,
+    args: &[VariTypes],
+    index: usize,
+) -> VariError {
+    interpreter.error(format!(
+        "'{}' expects {} as argument {}, got {}.",
+        name,
+        expected,
+        index + 1,
+        args[index].type_name()
+    ))
+}
+
+pub fn num_arg(
+    interpreter: &Interpreter,
+    name: &str,
+    args: &[VariTypes],
+    index: usize,
+) -> Result<f64, VariError> {
+    match args[index] {
+        VariTypes::Num(n) => Ok(n),
+        _ => Err(type_error(interpreter, name, "a number", args, index)),
+    }
+}
+
+pub fn str_arg<'a>(
+    interpreter: &Interpreter,
+    name: &str,
+    args: &'a [VariTypes],
+    index: usize,
+) -> Result<&'a str, VariError> {
+    match &args[index] {
+        VariTypes::String(s) => Ok(s),
+        _ => Err(type_error(interpreter, name, "a string", args, index)),
+    }
+}
+
+// counts and positions, negative ones are left to the caller
+pub fn whole_arg(
+    interpreter: &Interpreter,
+    name: &str,
+    args: &[VariTypes],
+    index: usize,
+) -> Result<i64, VariError> {
+    match args[index] {
+        VariTypes::Num(n) if n.fract() == 0.0 && n.abs() <= i64::MAX as f64 => Ok(n as i64),
+        _ => Err(type_error(interpreter, name, "a whole number", args, index)),
+    }
+}
+
+pub fn list_arg(
+    interpreter: &Interpreter,
+    name: &str,
+    args: &[VariTypes],
+    index: usize,
+) -> Result<Rc<Items>, VariError> {
+    match &args[index] {
+        VariTypes::List(items) => Ok(Rc::clone(items)),
+        _ => Err(type_error(interpreter, name, "a list", args, index)),
+    }
+}
+
+pub fn fn_arg(
+    interpreter: &Interpreter,
+    name: &str,
+    args: &[VariTypes],
+    index: usize,
+) -> Result<Procedure, VariError> {
+    match &args[index] {
+        VariTypes::Callable(procedure) => Ok(procedure.clone()),
+        _ => Err(type_error(interpreter, name, "a function", args, index)),
+    }
+}
+
+// calls a function a native was given, checking its arity the way
+// a call in a script does
+pub fn call(
+    interpreter: &mut Interpreter,
+    procedure: &Procedure,
+    args: Vec<VariTypes>,
+) -> Result<VariTypes, VariError> {
+    check_arity(interpreter, procedure, args.len())?;
+    procedure.call(interpreter, args)
+}
+
+pub fn check_arity(
+    interpreter: &Interpreter,
+    procedure: &Procedure,
+    count: usize,
+) -> Result<(), VariError> {
+    if procedure.arity().accepts(count) {
+        return Ok(());
+    }
+
+    Err(interpreter.error(format!(
+        "'{}' expects {} arguments but got {}.",
+        procedure.name(),
+        procedure.arity(),
+        count
+    )))
+}
+
+pub fn new_list(items: Vec<VariTypes>) -> VariTypes {
+    VariTypes::List(Rc::new(Items::new(items)))
+}
@@ -0,0 +1,143 @@
+use crate::fields::Fields;
+use crate::interpreter::Interpreter;
+use crate::procedure::Arity;
+use crate::stdlib::{check_arity, fn_arg, new_list, type_error};
+use crate::vari::{VariError, VariTypes};
+
+// builtins that work on values of any type, defined as globals
+pub fn define(interpreter: &mut Interpreter) {
+    interpreter.define_native("len", Arity::Exact(1), len);
+    interpreter.define_native("type", Arity::Exact(1), type_of);
+    interpreter.define_native("num", Arity::Exact(1), num);
+    interpreter.define_native("str", Arity::Exact(1), str);
+    interpreter.define_native("repr", Arity::Exact(1), repr);
+    interpreter.define_native("bool", Arity::Exact(1), bool);
+    interpreter.define_native("arity", Arity::Exact(1), arity);
+    interpreter.define_native("fields", Arity::Exact(1), fields);
+    interpreter.define_native("try", Arity::AtLeast(1), try_call);
+}
+
+// len(x), characters in a string, items in a list, fields in a struct
+fn len(interpreter: &mut Interpreter, args: &[VariTypes]) -> Result<VariTypes, VariError> {
+    let len = match &args[0] {
+        VariTypes::String(s) => s.chars().count(),
+        VariTypes::List(items) => items.borrow().len(),
+        VariTypes::Struct(fields) | VariTypes::Instance(_, fields) => fields.len(),
+        _ => {
+            return Err(type_error(
+                interpreter,
+                "len",
+                "a string, list or struct",
+                args,
+                0,
+            ))
+        }
+    };
+
+    Ok(VariTypes::Num(len as f64))
+}
+
+// type(x), the name of the value's type, a declared struct's own name
+// for its instances
+fn type_of(_: &mut Interpreter, args: &[VariTypes]) -> Result<VariTypes, VariError> {
+    let name = match &args[0] {
+        VariTypes::Nil => "nil",
+        VariTypes::Num(_) => "num",
+        VariTypes::String(_) => "string",
+        VariTypes::Boolean(_) => "bool",
+        VariTypes::Struct(_) => "struct",
+        VariTypes::Instance(struct_type, _) => struct_type.name(),
+        VariTypes::List(_) => "list",
+        VariTypes::Callable(_) => "function",
+    };
+
+    Ok(VariTypes::String(name.to_owned()))
+}
+
+// num(x), parses strings, true and false become 1 and 0
+fn num(interpreter: &mut Interpreter, args: &[VariTypes]) -> Result<VariTypes, VariError> {
+    match &args[0] {
+        VariTypes::Num(n) => Ok(VariTypes::Num(*n)),
+        VariTypes::Boolean(b) => Ok(VariTypes::Num(if *b { 1.0 } else { 0.0 })),
+        VariTypes::String(s) => match s.trim().parse::<f64>() {
+            Ok(n) => Ok(VariTypes::Num(n)),
+            Err(_) => Err(interpreter.error(format!("Can't convert \"{}\" to a number.", s))),
+        },
+        _ => Err(type_error(
+            interpreter,
+            "num",
+            "a number, string or boolean",
+            args,
+            0,
+        )),
+    }
+}
+
+// str(x), the value as `print` shows it
+fn str(interpreter: &mut Interpreter, args: &[VariTypes]) -> Result<VariTypes, VariError> {
+    Ok(VariTypes::String(interpreter.stringify(args[0].clone())))
+}
+
+// repr(x), like str(x) but strings are quoted, so "1" and 1 differ
+fn repr(_: &mut Interpreter, args: &[VariTypes]) -> Result<VariTypes, VariError> {
+    Ok(VariTypes::String(args[0].repr()))
+}
+
+// bool(x), whether `if` would take x as true
+fn bool(interpreter: &mut Interpreter, args: &[VariTypes]) -> Result<VariTypes, VariError> {
+    Ok(VariTypes::Boolean(
+        interpreter.is_true(Box::new(args[0].clone())),
+    ))
+}
+
+// arity(f), how many arguments f takes, the fewest it accepts
+// for natives with optional or variadic parameters
+fn arity(interpreter: &mut Interpreter, args: &[VariTypes]) -> Result<VariTypes, VariError> {
+    match &args[0] {
+        VariTypes::Callable(procedure) => {
+            let count = match procedure.arity() {
+                Arity::Exact(n) | Arity::Range(n, _) | Arity::AtLeast(n) => n,
+            };
+            Ok(VariTypes::Num(count as f64))
+        }
+        _ => Err(type_error(interpreter, "arity", "a function", args, 0)),
+    }
+}
+
+// fields(s), the struct's field names in the order they were set
+fn fields(interpreter: &mut Interpreter, args: &[VariTypes]) -> Result<VariTypes, VariError> {
+    match args[0].fields() {
+        Some(fields) => {
+            let names = fields
+                .keys()
+                .map(|name| VariTypes::String(name.clone()))
+                .collect();
+            Ok(new_list(names))
+        }
+        None => Err(type_error(interpreter, "fields", "a struct", args, 0)),
+    }
+}
+
+// try(f, args...), calls f and catches the runtime error it stops
+// with, giving {ok: true, value: ...} or {ok: false, error: "..."},
+// going over a limit can't be caught
+fn try_call(interpreter: &mut Interpreter, args: &[VariTypes]) -> Result<VariTypes, VariError> {
+    let procedure = fn_arg(interpreter, "try", args, 0)?;
+    // a call that can't be made isn't the function's error
+    check_arity(interpreter, &procedure, args.len() - 1)?;
+
+    let mut result = Fields::new();
+    match procedure.call(interpreter, args[1..].to_vec()) {
+        Ok(value) => {
+            result.insert("ok".to_owned(), VariTypes::Boolean(true));
+            result.insert("value".to_owned(), value);
+        }
+        Err(VariError::RuntimeError(_, msg)) => {
+            result.insert("ok".to_owned(), VariTypes::Boolean(false));
+            result.insert("error".to_owned(), VariTypes::String(msg));
+        }
+        Err(err) => return Err(err),
+    }
+
+    Ok(VariTypes::Struct(result))
+}
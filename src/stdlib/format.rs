@@ -0,0 +1,306 @@
+use crate::interpreter::Interpreter;
+use crate::procedure::Arity;
+use crate::stdlib::{call, str_arg};
+use crate::vari::{format_number, VariError, VariTypes};
+
+pub fn define(interpreter: &mut Interpreter) {
+    interpreter.define_native("format", Arity::AtLeast(1), format);
+}
+
+// format(fmt, args...), fills in the placeholders of `fmt`:
+//
+//   {}         the next argument        {0} {1}  an argument by position
+//   {name}     a field of the last argument, which has to be a struct
+//   {{ }}      literal braces
+//
+// After a `:` comes a spec like Rust's, `[[fill]align][+][#][0][width]
+// [.precision][type]`. Align is `<`, `^` or `>`, numbers go right and
+// everything else left by default. The type is `x`, `X`, `o` or `b` for
+// whole numbers in hex, octal or binary (`#` adds 0x, 0o or 0b), `e` or
+// `E` for scientific notation, or nothing for the value as `print`
+// shows it. Structs with a `to_string` function are shown with what it
+// returns.
+fn format(interpreter: &mut Interpreter, args: &[VariTypes]) -> Result<VariTypes, VariError> {
+    let fmt = str_arg(interpreter, "format", args, 0)?.to_owned();
+    let values = &args[1..];
+
+    let mut out = String::new();
+    let mut next = 0;
+    let mut chars = fmt.chars().enumerate().peekable();
+    while let Some((at, c)) = chars.next() {
+        match c {
+            '{' if chars.next_if(|&(_, c)| c == '{').is_some() => out.push('{'),
+            '}' if chars.next_if(|&(_, c)| c == '}').is_some() => out.push('}'),
+            '}' => {
+                return Err(interpreter.error(format!(
+                    "'format' found a '}}' without a '{{' at character {}.",
+                    at + 1
+                )))
+            }
+            '{' => {
+                let mut placeholder = String::new();
+                let mut closed = false;
+                for (_, c) in chars.by_ref() {
+                    if c == '}' {
+                        closed = true;
+                        break;
+                    }
+                    placeholder.push(c);
+                }
+                if !closed {
+                    return Err(interpreter.error(format!(
+                        "'format' found a '{{' without a '}}' at character {}.",
+                        at + 1
+                    )));
+                }
+
+                let (arg, spec) = placeholder.split_once(':').unwrap_or((&placeholder, ""));
+                let value = argument(interpreter, values, arg, &mut next)?;
+                let spec = Spec::parse(spec).ok_or_else(|| {
+                    interpreter.error(format!("'format' can't read the spec \"{}\".", spec))
+                })?;
+                interpreter.check_memory(spec.width.max(spec.precision.unwrap_or(0)))?;
+                out.push_str(&spec.render(interpreter, value)?);
+                interpreter.check_memory(out.len())?;
+            }
+            c => out.push(c),
+        }
+    }
+
+    Ok(VariTypes::String(out))
+}
+
+// the value a placeholder refers to, `next` counts the `{}`s so far
+fn argument(
+    interpreter: &Interpreter,
+    values: &[VariTypes],
+    arg: &str,
+    next: &mut usize,
+) -> Result<VariTypes, VariError> {
+    let index = if arg.is_empty() {
+        *next += 1;
+        *next - 1
+    } else if let Ok(index) = arg.parse::<usize>() {
+        index
+    } else {
+        let field = match values.last().and_then(VariTypes::fields) {
+            Some(fields) => fields.get(arg),
+            None => {
+                return Err(interpreter.error(format!(
+                    "'format' needs a struct as the last argument for {{{}}}.",
+                    arg
+                )))
+            }
+        };
+        return field.cloned().ok_or_else(|| {
+            interpreter.error(format!("'format' got no field '{}' for {{{}}}.", arg, arg))
+        });
+    };
+
+    values.get(index).cloned().ok_or_else(|| {
+        interpreter.error(format!(
+            "'format' has no argument {}, it got {}.",
+            index,
+            values.len()
+        ))
+    })
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+struct Spec {
+    fill: char,
+    align: Option<Align>,
+    sign: bool,
+    alternate: bool,
+    zero: bool,
+    width: usize,
+    precision: Option<usize>,
+    kind: Option<char>,
+}
+
+impl Spec {
+    fn parse(spec: &str) -> Option<Spec> {
+        let chars: Vec<char> = spec.chars().collect();
+        let align_of = |c: Option<&char>| match c {
+            Some('<') => Some(Align::Left),
+            Some('^') => Some(Align::Center),
+            Some('>') => Some(Align::Right),
+            _ => None,
+        };
+
+        let mut parsed = Spec {
+            fill: ' ',
+            align: None,
+            sign: false,
+            alternate: false,
+            zero: false,
+            width: 0,
+            precision: None,
+            kind: None,
+        };
+        let mut i = 0;
+        if let Some(align) = align_of(chars.get(1)) {
+            parsed.fill = chars[0];
+            parsed.align = Some(align);
+            i = 2;
+        } else if let Some(align) = align_of(chars.first()) {
+            parsed.align = Some(align);
+            i = 1;
+        }
+
+        let mut flag = |c: char| {
+            let found = chars.get(i) == Some(&c);
+            if found {
+                i += 1;
+            }
+            found
+        };
+        parsed.sign = flag('+');
+        parsed.alternate = flag('#');
+        parsed.zero = flag('0');
+
+        let number = |i: &mut usize| {
+            let start = *i;
+            while chars.get(*i).is_some_and(char::is_ascii_digit) {
+                *i += 1;
+            }
+            let digits: String = chars[start..*i].iter().collect();
+            digits.parse::<usize>().ok()
+        };
+        parsed.width = number(&mut i).unwrap_or(0);
+        if chars.get(i) == Some(&'.') {
+            i += 1;
+            parsed.precision = Some(number(&mut i)?);
+        }
+        if let Some(&kind) = chars.get(i) {
+            if !"xXobeE".contains(kind) {
+                return None;
+            }
+            parsed.kind = Some(kind);
+            i += 1;
+        }
+
+        if i < chars.len() {
+            return None;
+        }
+        Some(parsed)
+    }
+
+    fn render(&self, interpreter: &mut Interpreter, value: VariTypes) -> Result<String, VariError> {
+        let n = match (&value, self.kind) {
+            (VariTypes::Num(n), _) => *n,
+            (_, None) => return Ok(self.pad(self.display(interpreter, value)?, "", false)),
+            (_, Some(kind)) => {
+                return Err(interpreter.error(format!(
+                    "'format' can only use '{}' with numbers, got {}.",
+                    kind,
+                    value.type_name()
+                )))
+            }
+        };
+
+        let magnitude = n.abs();
+        let (prefix, body) = match self.kind {
+            // spelled the way `print` does, whatever the precision
+            None | Some('e' | 'E') if !n.is_finite() => ("", format_number(magnitude)),
+            None => match self.precision {
+                Some(precision) => ("", format!("{:.*}", precision, magnitude)),
+                None => ("", interpreter.stringify(VariTypes::Num(magnitude))),
+            },
+            Some(kind @ ('e' | 'E')) => {
+                let body = match self.precision {
+                    Some(precision) => format!("{:.*e}", precision, magnitude),
+                    None => format!("{:e}", magnitude),
+                };
+                let body = if kind == 'E' {
+                    body.to_uppercase()
+                } else {
+                    body
+                };
+                ("", body)
+            }
+            Some(kind) => {
+                if n.fract() != 0.0 || magnitude >= u64::MAX as f64 {
+                    return Err(interpreter.error(format!(
+                        "'format' can only use '{}' with whole numbers, got {}.",
+                        kind,
+                        format_number(n)
+                    )));
+                }
+                let whole = magnitude as u64;
+                match kind {
+                    'x' => ("0x", format!("{:x}", whole)),
+                    'X' => ("0x", format!("{:X}", whole)),
+                    'o' => ("0o", format!("{:o}", whole)),
+                    _ => ("0b", format!("{:b}", whole)),
+                }
+            }
+        };
+
+        let sign = if n < 0.0 {
+            "-"
+        } else if self.sign {
+            "+"
+        } else {
+            ""
+        };
+        let prefix = if self.alternate { prefix } else { "" };
+        Ok(self.pad(body, &format!("{}{}", sign, prefix), true))
+    }
+
+    // the value as `print` shows it, or a struct's own `to_string()`,
+    // cut to `precision` characters
+    fn display(
+        &self,
+        interpreter: &mut Interpreter,
+        value: VariTypes,
+    ) -> Result<String, VariError> {
+        let to_string = match value.fields().and_then(|fields| fields.get("to_string")) {
+            Some(VariTypes::Callable(procedure)) => Some(procedure.clone()),
+            _ => None,
+        };
+
+        let shown = match to_string {
+            Some(procedure) => match call(interpreter, &procedure, vec![])? {
+                VariTypes::String(s) => s,
+                other => {
+                    return Err(interpreter.error(format!(
+                        "'format' expects 'to_string' to return a string, got {}.",
+                        other.type_name()
+                    )))
+                }
+            },
+            None => interpreter.stringify(value),
+        };
+
+        Ok(match self.precision {
+            Some(precision) => shown.chars().take(precision).collect(),
+            None => shown,
+        })
+    }
+
+    // pads `body` to `width` characters, zero padding goes between the
+    // sign or prefix and the digits
+    fn pad(&self, body: String, sign: &str, number: bool) -> String {
+        let len = sign.chars().count() + body.chars().count();
+        let missing = self.width.saturating_sub(len);
+        if number && self.zero && self.align.is_none() {
+            return format!("{}{}{}", sign, "0".repeat(missing), body);
+        }
+
+        let default = if number { Align::Right } else { Align::Left };
+        let (before, after) = match self.align.unwrap_or(default) {
+            Align::Left => (0, missing),
+            Align::Center => (missing / 2, missing - missing / 2),
+            Align::Right => (missing, 0),
+        };
+        let fill = |count: usize| self.fill.to_string().repeat(count);
+        format!("{}{}{}{}", fill(before), sign, body, fill(after))
+    }
+}
@@ -0,0 +1,253 @@
+use std::cell::RefCell;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::rc::Rc;
+
+use crate::fields::Fields;
+use crate::interpreter::Interpreter;
+use crate::procedure::Arity;
+use crate::stdlib::{native, new_list, str_arg, Module};
+use crate::vari::{VariError, VariTypes};
+
+// Only defined when the interpreter has `Capability::FileSystem`.
+// Failures are runtime errors naming the function and the path,
+// scripts can handle them with `try`.
+pub fn define(interpreter: &mut Interpreter) {
+    let mut fs = Module::new("fs");
+
+    // read_file(path), the whole file as a string
+    fs.native("read_file", Arity::Exact(1), |interpreter, name, args| {
+        let path = str_arg(interpreter, name, args, 0)?;
+        let contents =
+            fs::read_to_string(path).map_err(|err| io_error(interpreter, name, path, err))?;
+        Ok(VariTypes::String(contents))
+    });
+
+    // write_file(path, s), replacing what was there
+    fs.native("write_file", Arity::Exact(2), |interpreter, name, args| {
+        let path = str_arg(interpreter, name, args, 0)?;
+        let contents = str_arg(interpreter, name, args, 1)?;
+        fs::write(path, contents).map_err(|err| io_error(interpreter, name, path, err))?;
+        Ok(VariTypes::Nil)
+    });
+
+    // append_file(path, s), creating the file if it doesn't exist
+    fs.native("append_file", Arity::Exact(2), |interpreter, name, args| {
+        let path = str_arg(interpreter, name, args, 0)?;
+        let contents = str_arg(interpreter, name, args, 1)?;
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| file.write_all(contents.as_bytes()))
+            .map_err(|err| io_error(interpreter, name, path, err))?;
+        Ok(VariTypes::Nil)
+    });
+
+    // read_lines(path), a list of lines without their line endings
+    fs.native("read_lines", Arity::Exact(1), |interpreter, name, args| {
+        let path = str_arg(interpreter, name, args, 0)?;
+        let contents =
+            fs::read_to_string(path).map_err(|err| io_error(interpreter, name, path, err))?;
+        let lines = contents
+            .lines()
+            .map(|line| VariTypes::String(line.to_owned()))
+            .collect();
+        Ok(new_list(lines))
+    });
+
+    fs.native("exists", Arity::Exact(1), |interpreter, name, args| {
+        let path = str_arg(interpreter, name, args, 0)?;
+        Ok(VariTypes::Boolean(Path::new(path).exists()))
+    });
+
+    // list_dir(path), the names of the entries in a directory, sorted
+    fs.native("list_dir", Arity::Exact(1), |interpreter, name, args| {
+        let path = str_arg(interpreter, name, args, 0)?;
+        let mut names = fs::read_dir(path)
+            .and_then(|entries| {
+                entries
+                    .map(|entry| {
+                        entry.map(|entry| entry.file_name().to_string_lossy().into_owned())
+                    })
+                    .collect::<io::Result<Vec<String>>>()
+            })
+            .map_err(|err| io_error(interpreter, name, path, err))?;
+        names.sort();
+        Ok(new_list(names.into_iter().map(VariTypes::String).collect()))
+    });
+
+    // mkdir(path), along with any missing parent directories
+    fs.native("mkdir", Arity::Exact(1), |interpreter, name, args| {
+        let path = str_arg(interpreter, name, args, 0)?;
+        fs::create_dir_all(path).map_err(|err| io_error(interpreter, name, path, err))?;
+        Ok(VariTypes::Nil)
+    });
+
+    // remove(path), a file or an empty directory
+    fs.native("remove", Arity::Exact(1), |interpreter, name, args| {
+        let path = str_arg(interpreter, name, args, 0)?;
+        let removed = if Path::new(path).is_dir() {
+            fs::remove_dir(path)
+        } else {
+            fs::remove_file(path)
+        };
+        removed.map_err(|err| io_error(interpreter, name, path, err))?;
+        Ok(VariTypes::Nil)
+    });
+
+    // rename(from, to)
+    fs.native("rename", Arity::Exact(2), |interpreter, name, args| {
+        let from = str_arg(interpreter, name, args, 0)?;
+        let to = str_arg(interpreter, name, args, 1)?;
+        fs::rename(from, to).map_err(|err| io_error(interpreter, name, from, err))?;
+        Ok(VariTypes::Nil)
+    });
+
+    // open(path, mode?), mode is "r" (the default), "w" or "a"
+    fs.native("open", Arity::Range(1, 2), |interpreter, name, args| {
+        let path = str_arg(interpreter, name, args, 0)?;
+        let mode = match args.get(1) {
+            Some(_) => str_arg(interpreter, name, args, 1)?,
+            None => "r",
+        };
+
+        let handle = match mode {
+            "r" => File::open(path).map(|file| Handle::Reader(BufReader::new(file))),
+            "w" => File::create(path).map(|file| Handle::Writer(BufWriter::new(file))),
+            "a" => OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .map(|file| Handle::Writer(BufWriter::new(file))),
+            _ => {
+                return Err(interpreter.error(format!(
+                    "'{}' expects mode \"r\", \"w\" or \"a\", got \"{}\".",
+                    name, mode
+                )))
+            }
+        }
+        .map_err(|err| io_error(interpreter, name, path, err))?;
+
+        Ok(file_object(path, handle))
+    });
+
+    fs.define(interpreter);
+}
+
+fn io_error(interpreter: &Interpreter, name: &str, path: &str, err: io::Error) -> VariError {
+    interpreter.error(format!("'{}' failed on '{}': {}.", name, path, err))
+}
+
+enum Handle {
+    Reader(BufReader<File>),
+    Writer(BufWriter<File>),
+}
+
+type Method =
+    fn(&Interpreter, &str, &str, &mut Handle, &[VariTypes]) -> Result<VariTypes, VariError>;
+
+// A struct of natives sharing the open file, so scripts call
+// `file.read_line()`. The file is closed by `close` or once the
+// last copy of the struct is gone.
+fn file_object(path: &str, handle: Handle) -> VariTypes {
+    // None once the file is closed
+    let handle = Rc::new(RefCell::new(Some(handle)));
+    let mut fields = Fields::new();
+    fields.insert("path".to_owned(), VariTypes::String(path.to_owned()));
+
+    let methods: &[(&str, Arity, Method)] = &[
+        ("read_line", Arity::Exact(0), read_line),
+        ("write", Arity::Exact(1), write),
+    ];
+    for &(name, arity, method) in methods {
+        let handle = Rc::clone(&handle);
+        let path = path.to_owned();
+        let full_name = format!("file.{}", name);
+        let body = move |interpreter: &mut Interpreter, args: &[VariTypes]| {
+            let mut handle = handle.borrow_mut();
+            match handle.as_mut() {
+                Some(handle) => method(interpreter, &full_name, &path, handle, args),
+                None => Err(interpreter.error(format!("'{}' called on a closed file.", full_name))),
+            }
+        };
+        fields.insert(
+            name.to_owned(),
+            native(format!("file.{}", name), arity, body),
+        );
+    }
+
+    // close(), writes out anything buffered, closing again does nothing
+    let path = path.to_owned();
+    let close = move |interpreter: &mut Interpreter, _: &[VariTypes]| {
+        if let Some(Handle::Writer(mut writer)) = handle.borrow_mut().take() {
+            writer
+                .flush()
+                .map_err(|err| io_error(interpreter, "file.close", &path, err))?;
+        }
+        Ok(VariTypes::Nil)
+    };
+    fields.insert(
+        "close".to_owned(),
+        native("file.close".to_owned(), Arity::Exact(0), close),
+    );
+
+    VariTypes::Struct(fields)
+}
+
+// read_line(), the next line without its line ending, nil at the end
+fn read_line(
+    interpreter: &Interpreter,
+    name: &str,
+    path: &str,
+    handle: &mut Handle,
+    _: &[VariTypes],
+) -> Result<VariTypes, VariError> {
+    let reader = match handle {
+        Handle::Reader(reader) => reader,
+        Handle::Writer(_) => {
+            return Err(interpreter.error(format!("'{}' needs a file opened with \"r\".", name)))
+        }
+    };
+
+    let mut line = String::new();
+    match reader.read_line(&mut line) {
+        Ok(0) => Ok(VariTypes::Nil),
+        Ok(_) => {
+            if line.ends_with('\n') {
+                line.pop();
+                if line.ends_with('\r') {
+                    line.pop();
+                }
+            }
+            Ok(VariTypes::String(line))
+        }
+        Err(err) => Err(io_error(interpreter, name, path, err)),
+    }
+}
+
+// write(s)
+fn write(
+    interpreter: &Interpreter,
+    name: &str,
+    path: &str,
+    handle: &mut Handle,
+    args: &[VariTypes],
+) -> Result<VariTypes, VariError> {
+    let writer = match handle {
+        Handle::Writer(writer) => writer,
+        Handle::Reader(_) => {
+            return Err(interpreter.error(format!(
+                "'{}' needs a file opened with \"w\" or \"a\".",
+                name
+            )))
+        }
+    };
+
+    let s = str_arg(interpreter, name, args, 0)?;
+    writer
+        .write_all(s.as_bytes())
+        .map_err(|err| io_error(interpreter, name, path, err))?;
+    Ok(VariTypes::Nil)
+}
@@ -0,0 +1,64 @@
+use crate::interpreter::Interpreter;
+use crate::procedure::Arity;
+use crate::stdlib::{str_arg, whole_arg};
+use crate::vari::{VariError, VariTypes};
+
+// Reading the script's input and ending it, with the interpreter's
+// input and output streams, stdin and stdout for `vari` itself.
+pub fn define(interpreter: &mut Interpreter) {
+    interpreter.set_args(&[]);
+    interpreter.define_native("input", Arity::Range(0, 1), input);
+    interpreter.define_native("read_all_stdin", Arity::Exact(0), read_all_stdin);
+    interpreter.define_native("exit", Arity::Range(0, 1), exit);
+}
+
+// input(prompt?), the next line without its line ending, nil at the
+// end of the input
+fn input(interpreter: &mut Interpreter, args: &[VariTypes]) -> Result<VariTypes, VariError> {
+    if !args.is_empty() {
+        let prompt = str_arg(interpreter, "input", args, 0)?.to_owned();
+        let output = interpreter.output();
+        let written = write!(output, "{}", prompt).and_then(|_| output.flush());
+        written.map_err(|err| interpreter.error(format!("Couldn't print: {}.", err)))?;
+    }
+
+    let mut line = String::new();
+    match interpreter.input().read_line(&mut line) {
+        Ok(0) => Ok(VariTypes::Nil),
+        Ok(_) => {
+            if line.ends_with('\n') {
+                line.pop();
+                if line.ends_with('\r') {
+                    line.pop();
+                }
+            }
+            Ok(VariTypes::String(line))
+        }
+        Err(err) => Err(interpreter.error(format!("Couldn't read input: {}.", err))),
+    }
+}
+
+// read_all_stdin(), everything left in the input
+fn read_all_stdin(interpreter: &mut Interpreter, _: &[VariTypes]) -> Result<VariTypes, VariError> {
+    let mut contents = String::new();
+    match interpreter.input().read_to_string(&mut contents) {
+        Ok(_) => Ok(VariTypes::String(contents)),
+        Err(err) => Err(interpreter.error(format!("Couldn't read input: {}.", err))),
+    }
+}
+
+// exit(code?), ends the program with `code`, 0 by default
+fn exit(interpreter: &mut Interpreter, args: &[VariTypes]) -> Result<VariTypes, VariError> {
+    let code = match args.first() {
+        Some(_) => whole_arg(interpreter, "exit", args, 0)?,
+        None => 0,
+    };
+
+    match i32::try_from(code) {
+        Ok(code) => Err(VariError::Exit(interpreter.line(), code)),
+        Err(_) => Err(interpreter.error(format!(
+            "'exit' got {}, which is too big for an exit code.",
+            code
+        ))),
+    }
+}
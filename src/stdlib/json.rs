@@ -0,0 +1,126 @@
+use std::rc::Rc;
+
+use crate::interpreter::Interpreter;
+use crate::json::Json;
+use crate::procedure::Arity;
+use crate::sandbox::{Limit, MAX_VALUE_DEPTH};
+use crate::stdlib::{new_list, str_arg, type_error, whole_arg, Module};
+use crate::vari::{format_number, VariError, VariTypes};
+
+// Objects become structs and arrays lists, so everything `parse`
+// returns can be given back to `stringify`.
+pub fn define(interpreter: &mut Interpreter) {
+    let mut json = Module::new("json");
+
+    // parse(s), errors give the line and column of the problem
+    json.native("parse", Arity::Exact(1), |interpreter, name, args| {
+        let source = str_arg(interpreter, name, args, 0)?;
+        let json = Json::parse(source)
+            .map_err(|err| interpreter.error(format!("'{}' failed: {}.", name, err)))?;
+        interpreter.check_memory(source.len())?;
+        Ok(from_json(json))
+    });
+
+    // stringify(value, indent?), on one line unless `indent` is given
+    json.native(
+        "stringify",
+        Arity::Range(1, 2),
+        |interpreter, name, args| {
+            let indent = match args.get(1) {
+                Some(_) => whole_arg(interpreter, name, args, 1)?,
+                None => 0,
+            };
+            if indent < 0 {
+                return Err(type_error(interpreter, name, "a positive indent", args, 1));
+            }
+
+            let json = to_json(interpreter, name, &args[0], &mut vec![], 0)?;
+            let out = json.stringify(indent as usize);
+            interpreter.check_memory(out.len())?;
+            Ok(VariTypes::String(out))
+        },
+    );
+
+    json.define(interpreter);
+}
+
+fn from_json(json: Json) -> VariTypes {
+    match json {
+        Json::Null => VariTypes::Nil,
+        Json::Bool(b) => VariTypes::Boolean(b),
+        Json::Number(n) => VariTypes::Num(n),
+        Json::String(s) => VariTypes::String(s),
+        Json::Array(items) => new_list(items.into_iter().map(from_json).collect()),
+        // a repeated key keeps its last value
+        Json::Object(members) => VariTypes::Struct(
+            members
+                .into_iter()
+                .map(|(name, value)| (name, from_json(value)))
+                .collect(),
+        ),
+    }
+}
+
+// `open` holds the lists being written, a list inside itself would
+// otherwise never end
+fn to_json(
+    interpreter: &Interpreter,
+    name: &str,
+    value: &VariTypes,
+    open: &mut Vec<*const ()>,
+    depth: usize,
+) -> Result<Json, VariError> {
+    let json = match value {
+        VariTypes::List(_) | VariTypes::Struct(_) | VariTypes::Instance(..)
+            if depth >= MAX_VALUE_DEPTH =>
+        {
+            return Err(VariError::LimitExceeded(interpreter.line(), Limit::Depth))
+        }
+        VariTypes::Nil => Json::Null,
+        VariTypes::Boolean(b) => Json::Bool(*b),
+        VariTypes::Num(n) if !n.is_finite() => {
+            return Err(interpreter.error(format!(
+                "'{}' can't write {}, JSON only has finite numbers.",
+                name,
+                format_number(*n)
+            )))
+        }
+        VariTypes::Num(n) => Json::Number(*n),
+        VariTypes::String(s) => Json::String(s.clone()),
+        VariTypes::List(items) => {
+            let ptr = Rc::as_ptr(items) as *const ();
+            if open.contains(&ptr) {
+                return Err(interpreter.error(format!(
+                    "'{}' can't write a list that contains itself.",
+                    name
+                )));
+            }
+
+            // plain loops, iterator adapters would add to every
+            // level's share of the stack
+            open.push(ptr);
+            let mut array = vec![];
+            for item in items.borrow().iter() {
+                array.push(to_json(interpreter, name, item, open, depth + 1)?);
+            }
+            open.pop();
+            Json::Array(array)
+        }
+        VariTypes::Struct(fields) | VariTypes::Instance(_, fields) => {
+            let mut members = vec![];
+            for (field, value) in fields.iter() {
+                let value = to_json(interpreter, name, value, open, depth + 1)?;
+                members.push((field.clone(), value));
+            }
+            Json::Object(members)
+        }
+        VariTypes::Callable(_) => {
+            return Err(interpreter.error(format!(
+                "'{}' can't write a function, JSON has no functions.",
+                name
+            )))
+        }
+    };
+
+    Ok(json)
+}
@@ -0,0 +1,253 @@
+use std::cmp::Ordering;
+
+use crate::interpreter::Interpreter;
+use crate::procedure::{Arity, Procedure};
+use crate::stdlib::{call, fn_arg, list_arg, new_list};
+use crate::vari::{VariError, VariTypes};
+
+// Builtins over lists that take a function, defined as globals. They
+// work on a copy of the list, so the function is free to change it.
+pub fn define(interpreter: &mut Interpreter) {
+    interpreter.define_native("map", Arity::Exact(2), map);
+    interpreter.define_native("filter", Arity::Exact(2), filter);
+    interpreter.define_native("reduce", Arity::Range(2, 3), reduce);
+    interpreter.define_native("each", Arity::Exact(2), each);
+    interpreter.define_native("any", Arity::Range(1, 2), any);
+    interpreter.define_native("all", Arity::Range(1, 2), all);
+    interpreter.define_native("find", Arity::Exact(2), find);
+    interpreter.define_native("zip", Arity::AtLeast(1), zip);
+    interpreter.define_native("enumerate", Arity::Exact(1), enumerate);
+    interpreter.define_native("sort", Arity::Range(1, 2), sort);
+}
+
+// the items of the list argument at `index`
+fn items(
+    interpreter: &Interpreter,
+    name: &str,
+    args: &[VariTypes],
+    index: usize,
+) -> Result<Vec<VariTypes>, VariError> {
+    Ok(list_arg(interpreter, name, args, index)?.borrow().clone())
+}
+
+// calls `f` with `item` and tells whether the result is truthy
+fn test(interpreter: &mut Interpreter, f: &Procedure, item: &VariTypes) -> Result<bool, VariError> {
+    let result = call(interpreter, f, vec![item.clone()])?;
+    Ok(interpreter.is_true(Box::new(result)))
+}
+
+// map(list, f), a new list of f(item) for every item
+fn map(interpreter: &mut Interpreter, args: &[VariTypes]) -> Result<VariTypes, VariError> {
+    let items = items(interpreter, "map", args, 0)?;
+    let f = fn_arg(interpreter, "map", args, 1)?;
+
+    let mapped = items
+        .into_iter()
+        .map(|item| call(interpreter, &f, vec![item]))
+        .collect::<Result<_, _>>()?;
+    Ok(new_list(mapped))
+}
+
+// filter(list, f), a new list of the items f is truthy for
+fn filter(interpreter: &mut Interpreter, args: &[VariTypes]) -> Result<VariTypes, VariError> {
+    let items = items(interpreter, "filter", args, 0)?;
+    let f = fn_arg(interpreter, "filter", args, 1)?;
+
+    let mut kept = vec![];
+    for item in items {
+        if test(interpreter, &f, &item)? {
+            kept.push(item);
+        }
+    }
+    Ok(new_list(kept))
+}
+
+// reduce(list, f, initial?), folds the list with f(acc, item),
+// starting from the first item when there's no initial value
+fn reduce(interpreter: &mut Interpreter, args: &[VariTypes]) -> Result<VariTypes, VariError> {
+    let items = items(interpreter, "reduce", args, 0)?;
+    let f = fn_arg(interpreter, "reduce", args, 1)?;
+
+    let mut items = items.into_iter();
+    let mut acc = match args.get(2) {
+        Some(initial) => initial.clone(),
+        None => items.next().ok_or_else(|| {
+            interpreter.error("'reduce' needs an initial value for an empty list.".to_owned())
+        })?,
+    };
+    for item in items {
+        acc = call(interpreter, &f, vec![acc, item])?;
+    }
+    Ok(acc)
+}
+
+// each(list, f), calls f with every item
+fn each(interpreter: &mut Interpreter, args: &[VariTypes]) -> Result<VariTypes, VariError> {
+    let items = items(interpreter, "each", args, 0)?;
+    let f = fn_arg(interpreter, "each", args, 1)?;
+
+    for item in items {
+        call(interpreter, &f, vec![item])?;
+    }
+    Ok(VariTypes::Nil)
+}
+
+// any(list, f?) and all(list, f?) test the items themselves when
+// there's no f, both stop at the first item that decides the answer
+fn any(interpreter: &mut Interpreter, args: &[VariTypes]) -> Result<VariTypes, VariError> {
+    quantify(interpreter, "any", args, true)
+}
+
+fn all(interpreter: &mut Interpreter, args: &[VariTypes]) -> Result<VariTypes, VariError> {
+    quantify(interpreter, "all", args, false)
+}
+
+fn quantify(
+    interpreter: &mut Interpreter,
+    name: &str,
+    args: &[VariTypes],
+    decides: bool,
+) -> Result<VariTypes, VariError> {
+    let items = items(interpreter, name, args, 0)?;
+    let f = match args.get(1) {
+        Some(_) => Some(fn_arg(interpreter, name, args, 1)?),
+        None => None,
+    };
+
+    for item in items {
+        let truthy = match &f {
+            Some(f) => test(interpreter, f, &item)?,
+            None => interpreter.is_true(Box::new(item)),
+        };
+        if truthy == decides {
+            return Ok(VariTypes::Boolean(decides));
+        }
+    }
+    Ok(VariTypes::Boolean(!decides))
+}
+
+// find(list, f), the first item f is truthy for, nil if there's none
+fn find(interpreter: &mut Interpreter, args: &[VariTypes]) -> Result<VariTypes, VariError> {
+    let items = items(interpreter, "find", args, 0)?;
+    let f = fn_arg(interpreter, "find", args, 1)?;
+
+    for item in items {
+        if test(interpreter, &f, &item)? {
+            return Ok(item);
+        }
+    }
+    Ok(VariTypes::Nil)
+}
+
+// zip(a, b, ...), lists of the items at the same position, as long as
+// the shortest list
+fn zip(interpreter: &mut Interpreter, args: &[VariTypes]) -> Result<VariTypes, VariError> {
+    let lists = (0..args.len())
+        .map(|i| items(interpreter, "zip", args, i))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let len = lists.iter().map(Vec::len).min().unwrap_or(0);
+    let zipped = (0..len)
+        .map(|i| new_list(lists.iter().map(|list| list[i].clone()).collect()))
+        .collect();
+    Ok(new_list(zipped))
+}
+
+// enumerate(list), [index, item] pairs
+fn enumerate(interpreter: &mut Interpreter, args: &[VariTypes]) -> Result<VariTypes, VariError> {
+    let items = items(interpreter, "enumerate", args, 0)?;
+
+    let pairs = items
+        .into_iter()
+        .enumerate()
+        .map(|(i, item)| new_list(vec![VariTypes::Num(i as f64), item]))
+        .collect();
+    Ok(new_list(pairs))
+}
+
+// sort(list, f?), a new sorted list, items that compare equal keep
+// their order. With no f the items must be all numbers or all
+// strings, f(item) gives a key to sort by instead and f(a, b) is a
+// comparator, a negative number when a goes before b.
+fn sort(interpreter: &mut Interpreter, args: &[VariTypes]) -> Result<VariTypes, VariError> {
+    let items = items(interpreter, "sort", args, 0)?;
+    let f = match args.get(1) {
+        Some(_) => Some(fn_arg(interpreter, "sort", args, 1)?),
+        None => None,
+    };
+
+    let sorted = match f {
+        Some(f) if f.arity().accepts(2) => {
+            let mut less = |a: &VariTypes, b: &VariTypes| {
+                let order = call(interpreter, &f, vec![a.clone(), b.clone()])?;
+                match order {
+                    VariTypes::Num(n) => Ok(n < 0.0),
+                    other => Err(interpreter.error(format!(
+                        "'sort' expects the comparator to return a number, got {}.",
+                        other.type_name()
+                    ))),
+                }
+            };
+            merge_sort(items, &mut less)?
+        }
+        Some(f) => {
+            let keyed = items
+                .into_iter()
+                .map(|item| Ok((call(interpreter, &f, vec![item.clone()])?, item)))
+                .collect::<Result<Vec<_>, VariError>>()?;
+            let mut less = |(a, _): &(VariTypes, VariTypes), (b, _): &(VariTypes, VariTypes)| {
+                Ok(compare(interpreter, a, b)? == Ordering::Less)
+            };
+            let keyed = merge_sort(keyed, &mut less)?;
+            keyed.into_iter().map(|(_, item)| item).collect()
+        }
+        None => {
+            let mut less =
+                |a: &VariTypes, b: &VariTypes| Ok(compare(interpreter, a, b)? == Ordering::Less);
+            merge_sort(items, &mut less)?
+        }
+    };
+    Ok(new_list(sorted))
+}
+
+fn compare(interpreter: &Interpreter, a: &VariTypes, b: &VariTypes) -> Result<Ordering, VariError> {
+    match (a, b) {
+        (VariTypes::Num(a), VariTypes::Num(b)) => Ok(a.total_cmp(b)),
+        (VariTypes::String(a), VariTypes::String(b)) => Ok(a.cmp(b)),
+        _ => Err(interpreter.error(format!(
+            "'sort' can only order numbers or strings, got {} and {}.",
+            a.type_name(),
+            b.type_name()
+        ))),
+    }
+}
+
+// A stable merge sort where `less` can fail. Script comparators needn't
+// be consistent, which the standard library's sorts may panic on.
+fn merge_sort<T>(
+    mut items: Vec<T>,
+    less: &mut dyn FnMut(&T, &T) -> Result<bool, VariError>,
+) -> Result<Vec<T>, VariError> {
+    if items.len() <= 1 {
+        return Ok(items);
+    }
+
+    let right = items.split_off(items.len() / 2);
+    let left = merge_sort(items, less)?;
+    let right = merge_sort(right, less)?;
+
+    let mut merged = Vec::with_capacity(left.len() + right.len());
+    let mut left = left.into_iter().peekable();
+    let mut right = right.into_iter().peekable();
+    while let (Some(l), Some(r)) = (left.peek(), right.peek()) {
+        // only a strictly smaller right item goes first, keeping it stable
+        if less(r, l)? {
+            merged.extend(right.next());
+        } else {
+            merged.extend(left.next());
+        }
+    }
+    merged.extend(left);
+    merged.extend(right);
+    Ok(merged)
+}
@@ -0,0 +1,117 @@
+use std::f64::consts;
+
+use crate::interpreter::Interpreter;
+use crate::procedure::Arity;
+use crate::stdlib::{num_arg, Module};
+use crate::vari::{format_number, VariTypes};
+
+type Unary = fn(f64) -> f64;
+type Binary = fn(f64, f64) -> f64;
+type Predicate = fn(f64) -> bool;
+
+pub fn define(interpreter: &mut Interpreter) {
+    let mut math = Module::new("math");
+
+    math.constant("PI", VariTypes::Num(consts::PI));
+    math.constant("E", VariTypes::Num(consts::E));
+    math.constant("INF", VariTypes::Num(f64::INFINITY));
+    math.constant("NAN", VariTypes::Num(f64::NAN));
+
+    let unary: &[(&str, Unary)] = &[
+        ("sqrt", f64::sqrt),
+        ("abs", f64::abs),
+        ("floor", f64::floor),
+        ("ceil", f64::ceil),
+        // halfway cases round away from zero
+        ("round", f64::round),
+        ("trunc", f64::trunc),
+        ("sin", f64::sin),
+        ("cos", f64::cos),
+        ("tan", f64::tan),
+        ("asin", f64::asin),
+        ("acos", f64::acos),
+        ("atan", f64::atan),
+        ("sinh", f64::sinh),
+        ("cosh", f64::cosh),
+        ("tanh", f64::tanh),
+        ("asinh", f64::asinh),
+        ("acosh", f64::acosh),
+        ("atanh", f64::atanh),
+        ("log2", f64::log2),
+        ("log10", f64::log10),
+        ("exp", f64::exp),
+    ];
+    for &(name, function) in unary {
+        math.native(name, Arity::Exact(1), move |interpreter, name, args| {
+            let x = num_arg(interpreter, name, args, 0)?;
+            Ok(VariTypes::Num(function(x)))
+        });
+    }
+
+    let binary: &[(&str, Binary)] = &[
+        ("pow", f64::powf),
+        ("atan2", f64::atan2),
+        ("hypot", f64::hypot),
+    ];
+    for &(name, function) in binary {
+        math.native(name, Arity::Exact(2), move |interpreter, name, args| {
+            let a = num_arg(interpreter, name, args, 0)?;
+            let b = num_arg(interpreter, name, args, 1)?;
+            Ok(VariTypes::Num(function(a, b)))
+        });
+    }
+
+    // log(x, base?), the natural logarithm without a base
+    math.native("log", Arity::Range(1, 2), |interpreter, name, args| {
+        let x = num_arg(interpreter, name, args, 0)?;
+        match args.get(1) {
+            Some(_) => Ok(VariTypes::Num(x.log(num_arg(interpreter, name, args, 1)?))),
+            None => Ok(VariTypes::Num(x.ln())),
+        }
+    });
+
+    // min(a, b, ...), max(a, b, ...), NaN if any argument is NaN
+    let folds: &[(&str, Binary)] = &[("min", f64::min), ("max", f64::max)];
+    for &(name, function) in folds {
+        math.native(name, Arity::AtLeast(1), move |interpreter, name, args| {
+            let mut result = num_arg(interpreter, name, args, 0)?;
+            for index in 1..args.len() {
+                let n = num_arg(interpreter, name, args, index)?;
+                result = if result.is_nan() || n.is_nan() {
+                    f64::NAN
+                } else {
+                    function(result, n)
+                };
+            }
+            Ok(VariTypes::Num(result))
+        });
+    }
+
+    // clamp(x, min, max)
+    math.native("clamp", Arity::Exact(3), |interpreter, name, args| {
+        let x = num_arg(interpreter, name, args, 0)?;
+        let min = num_arg(interpreter, name, args, 1)?;
+        let max = num_arg(interpreter, name, args, 2)?;
+        // `f64::clamp` panics on these
+        if min.is_nan() || max.is_nan() || min > max {
+            return Err(interpreter.error(format!(
+                "'{}' expects min to be no greater than max, got {} and {}.",
+                name,
+                format_number(min),
+                format_number(max)
+            )));
+        }
+        Ok(VariTypes::Num(x.clamp(min, max)))
+    });
+
+    let predicates: &[(&str, Predicate)] =
+        &[("is_nan", f64::is_nan), ("is_finite", f64::is_finite)];
+    for &(name, predicate) in predicates {
+        math.native(name, Arity::Exact(1), move |interpreter, name, args| {
+            let x = num_arg(interpreter, name, args, 0)?;
+            Ok(VariTypes::Boolean(predicate(x)))
+        });
+    }
+
+    math.define(interpreter);
+}
@@ -0,0 +1,117 @@
+use std::env;
+use std::process::{self, Command};
+
+use crate::fields::Fields;
+use crate::interpreter::Interpreter;
+use crate::procedure::Arity;
+use crate::stdlib::{list_arg, str_arg, Module};
+use crate::vari::VariTypes;
+
+// Only defined when the interpreter has `Capability::Process`.
+pub fn define(interpreter: &mut Interpreter) {
+    let mut os = Module::new("os");
+
+    // env_get(name), nil when it isn't set
+    os.native("env_get", Arity::Exact(1), |interpreter, name, args| {
+        let var = str_arg(interpreter, name, args, 0)?;
+        match env::var_os(var) {
+            Some(value) => Ok(VariTypes::String(value.to_string_lossy().into_owned())),
+            None => Ok(VariTypes::Nil),
+        }
+    });
+
+    // env_set(name, value), for this process and the commands it runs
+    os.native("env_set", Arity::Exact(2), |interpreter, name, args| {
+        let var = str_arg(interpreter, name, args, 0)?;
+        let value = str_arg(interpreter, name, args, 1)?;
+        if var.is_empty() || var.contains(['=', '\0']) || value.contains('\0') {
+            return Err(interpreter.error(format!(
+                "'{}' can't set a variable named \"{}\".",
+                name, var
+            )));
+        }
+        env::set_var(var, value);
+        Ok(VariTypes::Nil)
+    });
+
+    // env_vars(), a struct of every variable
+    os.native("env_vars", Arity::Exact(0), |_, _, _| {
+        let vars = env::vars_os()
+            .map(|(name, value)| {
+                (
+                    name.to_string_lossy().into_owned(),
+                    VariTypes::String(value.to_string_lossy().into_owned()),
+                )
+            })
+            .collect();
+        Ok(VariTypes::Struct(vars))
+    });
+
+    // cwd(), the working directory
+    os.native("cwd", Arity::Exact(0), |interpreter, name, _| {
+        let dir = env::current_dir()
+            .map_err(|err| interpreter.error(format!("'{}' failed: {}.", name, err)))?;
+        Ok(VariTypes::String(dir.to_string_lossy().into_owned()))
+    });
+
+    // chdir(path), changes the working directory of the whole process
+    os.native("chdir", Arity::Exact(1), |interpreter, name, args| {
+        let path = str_arg(interpreter, name, args, 0)?;
+        env::set_current_dir(path).map_err(|err| {
+            interpreter.error(format!("'{}' failed on '{}': {}.", name, path, err))
+        })?;
+        Ok(VariTypes::Nil)
+    });
+
+    // run(cmd, args?), waits for the command and returns {stdout,
+    // stderr, status}, status is nil if it was killed by a signal
+    os.native("run", Arity::Range(1, 2), |interpreter, name, args| {
+        let cmd = str_arg(interpreter, name, args, 0)?;
+        let mut command = Command::new(cmd);
+        if args.len() > 1 {
+            for arg in list_arg(interpreter, name, args, 1)?.borrow().iter() {
+                match arg {
+                    VariTypes::String(arg) => command.arg(arg),
+                    other => {
+                        return Err(interpreter.error(format!(
+                            "'{}' expects the command's arguments to be strings, got {}.",
+                            name,
+                            other.type_name()
+                        )))
+                    }
+                };
+            }
+        }
+
+        let output = command.output().map_err(|err| {
+            interpreter.error(format!("'{}' couldn't run '{}': {}.", name, cmd, err))
+        })?;
+
+        let status = match output.status.code() {
+            Some(code) => VariTypes::Num(code as f64),
+            None => VariTypes::Nil,
+        };
+        let mut result = Fields::new();
+        result.insert(
+            "stdout".to_owned(),
+            VariTypes::String(String::from_utf8_lossy(&output.stdout).into_owned()),
+        );
+        result.insert(
+            "stderr".to_owned(),
+            VariTypes::String(String::from_utf8_lossy(&output.stderr).into_owned()),
+        );
+        result.insert("status".to_owned(), status);
+        Ok(VariTypes::Struct(result))
+    });
+
+    // platform(), "linux", "macos", "windows" and so on
+    os.native("platform", Arity::Exact(0), |_, _, _| {
+        Ok(VariTypes::String(env::consts::OS.to_owned()))
+    });
+
+    os.native("pid", Arity::Exact(0), |_, _, _| {
+        Ok(VariTypes::Num(process::id() as f64))
+    });
+
+    os.define(interpreter);
+}
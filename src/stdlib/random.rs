@@ -0,0 +1,162 @@
+use std::cell::RefCell;
+use std::f64::consts::TAU;
+use std::process;
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::interpreter::Interpreter;
+use crate::procedure::Arity;
+use crate::stdlib::{list_arg, num_arg, whole_arg, Module};
+use crate::vari::VariTypes;
+
+// The natives share one generator, seeded from the clock unless the
+// script calls `seed(n)`. After the same seed, `random`, `randint`,
+// `choice` and `shuffle` give the same results on every platform.
+// `gauss` goes through `ln` and `cos`, which may differ in the last
+// bit between platforms.
+pub fn define(interpreter: &mut Interpreter) {
+    let mut random = Module::new("random");
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_nanos() as u64);
+    let rng = Rc::new(RefCell::new(Xoshiro256::new(nanos ^ process::id() as u64)));
+
+    // seed(n), n is any whole number
+    let shared = Rc::clone(&rng);
+    random.native("seed", Arity::Exact(1), move |interpreter, name, args| {
+        let seed = whole_arg(interpreter, name, args, 0)?;
+        *shared.borrow_mut() = Xoshiro256::new(seed as u64);
+        Ok(VariTypes::Nil)
+    });
+
+    // random(), a number from 0 up to but not including 1
+    let shared = Rc::clone(&rng);
+    random.native("random", Arity::Exact(0), move |_, _, _| {
+        Ok(VariTypes::Num(shared.borrow_mut().next_f64()))
+    });
+
+    // randint(lo, hi), a whole number from lo to hi, both included
+    let shared = Rc::clone(&rng);
+    random.native(
+        "randint",
+        Arity::Exact(2),
+        move |interpreter, name, args| {
+            let lo = whole_arg(interpreter, name, args, 0)?;
+            let hi = whole_arg(interpreter, name, args, 1)?;
+            if lo > hi {
+                return Err(interpreter.error(format!(
+                    "'{}' got an empty range, {} is more than {}.",
+                    name, lo, hi
+                )));
+            }
+
+            // the span fits in a u64 when it's less than 2^64
+            let span = (hi as i128 - lo as i128 + 1) as u128;
+            let offset = match u64::try_from(span) {
+                Ok(span) => shared.borrow_mut().below(span),
+                Err(_) => shared.borrow_mut().next_u64(),
+            };
+            Ok(VariTypes::Num((lo as i128 + offset as i128) as f64))
+        },
+    );
+
+    // choice(list), one of the list's items
+    let shared = Rc::clone(&rng);
+    random.native("choice", Arity::Exact(1), move |interpreter, name, args| {
+        let items = list_arg(interpreter, name, args, 0)?;
+        let items = items.borrow();
+        if items.is_empty() {
+            return Err(interpreter.error(format!("'{}' got an empty list.", name)));
+        }
+        let index = shared.borrow_mut().below(items.len() as u64);
+        Ok(items[index as usize].clone())
+    });
+
+    // shuffle(list), reorders the list in place
+    let shared = Rc::clone(&rng);
+    random.native(
+        "shuffle",
+        Arity::Exact(1),
+        move |interpreter, name, args| {
+            let items = list_arg(interpreter, name, args, 0)?;
+            let mut items = items.borrow_mut();
+            let mut rng = shared.borrow_mut();
+            // Fisher-Yates, from the back
+            for i in (1..items.len()).rev() {
+                let j = rng.below(i as u64 + 1) as usize;
+                items.swap(i, j);
+            }
+            Ok(VariTypes::Nil)
+        },
+    );
+
+    // gauss(mu, sigma), normally distributed with mean mu and standard
+    // deviation sigma
+    random.native("gauss", Arity::Exact(2), move |interpreter, name, args| {
+        let mu = num_arg(interpreter, name, args, 0)?;
+        let sigma = num_arg(interpreter, name, args, 1)?;
+        let mut rng = rng.borrow_mut();
+        // Box-Muller, 1 - u keeps the logarithm away from 0
+        let u = 1.0 - rng.next_f64();
+        let v = rng.next_f64();
+        let z = (-2.0 * u.ln()).sqrt() * (TAU * v).cos();
+        Ok(VariTypes::Num(mu + sigma * z))
+    });
+
+    random.define(interpreter);
+}
+
+// xoshiro256** 1.0 by David Blackman and Sebastiano Vigna, see
+// https://prng.di.unimi.it, seeded with SplitMix64 as they suggest.
+struct Xoshiro256 {
+    state: [u64; 4],
+}
+
+impl Xoshiro256 {
+    fn new(seed: u64) -> Self {
+        let mut x = seed;
+        let mut split_mix = || {
+            x = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+            let mut z = x;
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+            z ^ (z >> 31)
+        };
+
+        Self {
+            state: [split_mix(), split_mix(), split_mix(), split_mix()],
+        }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let s = &mut self.state;
+        let result = s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = s[1] << 17;
+
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(45);
+
+        result
+    }
+
+    // the top 53 bits, evenly spread over [0, 1)
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 * (1.0 / (1u64 << 53) as f64)
+    }
+
+    // a number below `bound` without modulo bias, throwing away the
+    // draws from the incomplete last block of `bound` values
+    fn below(&mut self, bound: u64) -> u64 {
+        let threshold = bound.wrapping_neg() % bound;
+        loop {
+            let x = self.next_u64();
+            if x >= threshold {
+                return x % bound;
+            }
+        }
+    }
+}
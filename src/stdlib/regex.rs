@@ -0,0 +1,267 @@
+use crate::fields::Fields;
+use crate::interpreter::Interpreter;
+use crate::procedure::{Arity, Procedure};
+use crate::regex::{Captures, Regex};
+use crate::stdlib::{call, new_list, str_arg, type_error, whole_arg, Module};
+use crate::vari::{VariError, VariTypes};
+
+// Patterns come first, `regex.search("\d+", s)`. A match is a struct
+// {text, start, end, groups, named}: `groups` lists what groups 1 and
+// up matched, nil for the ones that didn't take part, and `named` has
+// the groups with names. Positions count characters.
+pub fn define(interpreter: &mut Interpreter) {
+    let mut regex = Module::new("regex");
+
+    // match(pattern, s), a match at the very start of s or nil
+    regex.native("match", Arity::Exact(2), |interpreter, name, args| {
+        let regex = compile(interpreter, name, args)?;
+        let text: Vec<char> = str_arg(interpreter, name, args, 1)?.chars().collect();
+        Ok(match regex.match_at(&text, 0) {
+            Some(captures) => match_struct(&regex, &text, &captures),
+            None => VariTypes::Nil,
+        })
+    });
+
+    // search(pattern, s), the first match anywhere in s or nil
+    regex.native("search", Arity::Exact(2), |interpreter, name, args| {
+        let regex = compile(interpreter, name, args)?;
+        let text: Vec<char> = str_arg(interpreter, name, args, 1)?.chars().collect();
+        Ok(match regex.search(&text, 0) {
+            Some(captures) => match_struct(&regex, &text, &captures),
+            None => VariTypes::Nil,
+        })
+    });
+
+    // find_all(pattern, s), every match that doesn't overlap the last
+    regex.native("find_all", Arity::Exact(2), |interpreter, name, args| {
+        let regex = compile(interpreter, name, args)?;
+        let text: Vec<char> = str_arg(interpreter, name, args, 1)?.chars().collect();
+        let matches = matches(&regex, &text, usize::MAX)
+            .iter()
+            .map(|captures| match_struct(&regex, &text, captures))
+            .collect();
+        Ok(new_list(matches))
+    });
+
+    // replace(pattern, s, replacement, count?), replaces the first
+    // `count` matches, all of them by default. The replacement is a
+    // string where $1 or ${name} stand for a group, $0 for the whole
+    // match and $$ for a dollar sign, or a function given the match
+    // that returns a string.
+    regex.native("replace", Arity::Range(3, 4), |interpreter, name, args| {
+        let regex = compile(interpreter, name, args)?;
+        let text: Vec<char> = str_arg(interpreter, name, args, 1)?.chars().collect();
+        let replacement = match &args[2] {
+            VariTypes::String(template) => {
+                Replacement::Template(parse_template(interpreter, name, &regex, template)?)
+            }
+            VariTypes::Callable(procedure) => Replacement::Function(procedure.clone()),
+            _ => {
+                return Err(type_error(
+                    interpreter,
+                    name,
+                    "a string or function",
+                    args,
+                    2,
+                ))
+            }
+        };
+        let count = match args.get(3) {
+            Some(_) => whole_arg(interpreter, name, args, 3)?.max(0) as usize,
+            None => usize::MAX,
+        };
+
+        let mut out = String::new();
+        let mut last = 0;
+        for captures in matches(&regex, &text, count) {
+            let (start, end) = captures[0].unwrap();
+            out.extend(&text[last..start]);
+            replacement.write(interpreter, name, &regex, &text, &captures, &mut out)?;
+            interpreter.check_memory(out.len())?;
+            last = end;
+        }
+        out.extend(&text[last..]);
+        Ok(VariTypes::String(out))
+    });
+
+    regex.define(interpreter);
+}
+
+// the pattern in the first argument
+fn compile(interpreter: &Interpreter, name: &str, args: &[VariTypes]) -> Result<Regex, VariError> {
+    let pattern = str_arg(interpreter, name, args, 0)?;
+    Regex::new(pattern).map_err(|err| {
+        interpreter.error(format!(
+            "'{}' couldn't compile \"{}\": {}.",
+            name, pattern, err
+        ))
+    })
+}
+
+// up to `count` matches, after an empty match the next one is looked
+// for a character later so it can't match there again
+fn matches(regex: &Regex, text: &[char], count: usize) -> Vec<Captures> {
+    let mut found = vec![];
+    let mut start = 0;
+    while found.len() < count && start <= text.len() {
+        let Some(captures) = regex.search(text, start) else {
+            break;
+        };
+        let (match_start, match_end) = captures[0].unwrap();
+        start = if match_end == match_start {
+            match_end + 1
+        } else {
+            match_end
+        };
+        found.push(captures);
+    }
+    found
+}
+
+fn match_struct(regex: &Regex, text: &[char], captures: &Captures) -> VariTypes {
+    let group = |index: usize| match captures[index] {
+        Some((start, end)) => VariTypes::String(text[start..end].iter().collect()),
+        None => VariTypes::Nil,
+    };
+    let (start, end) = captures[0].unwrap();
+
+    let named = regex
+        .names()
+        .iter()
+        .map(|(name, index)| (name.clone(), group(*index)))
+        .collect();
+    let mut fields = Fields::new();
+    fields.insert("text".to_owned(), group(0));
+    fields.insert("start".to_owned(), VariTypes::Num(start as f64));
+    fields.insert("end".to_owned(), VariTypes::Num(end as f64));
+    fields.insert(
+        "groups".to_owned(),
+        new_list((1..regex.groups()).map(group).collect()),
+    );
+    fields.insert("named".to_owned(), VariTypes::Struct(named));
+    VariTypes::Struct(fields)
+}
+
+enum Replacement {
+    Template(Vec<Part>),
+    Function(Procedure),
+}
+
+enum Part {
+    Text(String),
+    Group(usize),
+}
+
+impl Replacement {
+    // writes what replaces the match to `out`
+    fn write(
+        &self,
+        interpreter: &mut Interpreter,
+        name: &str,
+        regex: &Regex,
+        text: &[char],
+        captures: &Captures,
+        out: &mut String,
+    ) -> Result<(), VariError> {
+        let parts = match self {
+            Replacement::Template(parts) => parts,
+            Replacement::Function(procedure) => {
+                let found = match_struct(regex, text, captures);
+                return match call(interpreter, procedure, vec![found])? {
+                    VariTypes::String(s) => {
+                        out.push_str(&s);
+                        Ok(())
+                    }
+                    other => Err(interpreter.error(format!(
+                        "'{}' expects the replacement function to return a string, got {}.",
+                        name,
+                        other.type_name()
+                    ))),
+                };
+            }
+        };
+
+        for part in parts {
+            match part {
+                Part::Text(s) => out.push_str(s),
+                Part::Group(group) => {
+                    if let Some((start, end)) = captures[*group] {
+                        out.extend(&text[start..end]);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn parse_template(
+    interpreter: &Interpreter,
+    name: &str,
+    regex: &Regex,
+    template: &str,
+) -> Result<Vec<Part>, VariError> {
+    let error = |msg: String| {
+        interpreter.error(format!(
+            "'{}' can't use the replacement \"{}\": {}.",
+            name, template, msg
+        ))
+    };
+
+    let mut parts = vec![];
+    let mut text = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '$' {
+            text.push(c);
+            continue;
+        }
+
+        let reference = match chars.peek() {
+            Some('$') => {
+                chars.next();
+                text.push('$');
+                continue;
+            }
+            Some('{') => {
+                chars.next();
+                let mut reference = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break reference,
+                        Some(c) => reference.push(c),
+                        None => return Err(error("a '${' isn't closed".to_owned())),
+                    }
+                }
+            }
+            Some(c) if c.is_ascii_digit() => {
+                let mut reference = String::new();
+                while let Some(&c) = chars.peek().filter(|c| c.is_ascii_digit()) {
+                    reference.push(c);
+                    chars.next();
+                }
+                reference
+            }
+            _ => return Err(error("a '$' has to be followed by a group".to_owned())),
+        };
+
+        let group = match reference.parse::<usize>() {
+            Ok(index) if index < regex.groups() => Some(index),
+            Ok(_) => None,
+            Err(_) => regex
+                .names()
+                .iter()
+                .find(|(group, _)| *group == reference)
+                .map(|&(_, index)| index),
+        };
+        match group {
+            Some(group) => {
+                parts.push(Part::Text(std::mem::take(&mut text)));
+                parts.push(Part::Group(group));
+            }
+            None => return Err(error(format!("there's no group {}", reference))),
+        }
+    }
+    parts.push(Part::Text(text));
+    Ok(parts)
+}
@@ -0,0 +1,215 @@
+use crate::interpreter::Interpreter;
+use crate::procedure::Arity;
+use crate::stdlib::{list_arg, new_list, str_arg, whole_arg, Module};
+use crate::vari::{VariError, VariTypes};
+
+type Transform = fn(&str) -> String;
+type Test = fn(&str, &str) -> bool;
+
+// Positions and lengths count characters, not bytes, so
+// `string.substring("héllo", 1, 2)` is "é".
+pub fn define(interpreter: &mut Interpreter) {
+    let mut string = Module::new("string");
+
+    let transforms: &[(&str, Transform)] = &[
+        ("upper", str::to_uppercase),
+        ("lower", str::to_lowercase),
+        ("trim", |s| s.trim().to_owned()),
+        ("trim_start", |s| s.trim_start().to_owned()),
+        ("trim_end", |s| s.trim_end().to_owned()),
+    ];
+    for &(name, transform) in transforms {
+        string.native(name, Arity::Exact(1), move |interpreter, name, args| {
+            let s = str_arg(interpreter, name, args, 0)?;
+            Ok(VariTypes::String(transform(s)))
+        });
+    }
+
+    let tests: &[(&str, Test)] = &[
+        ("contains", |s, part| s.contains(part)),
+        ("starts_with", |s, part| s.starts_with(part)),
+        ("ends_with", |s, part| s.ends_with(part)),
+    ];
+    for &(name, test) in tests {
+        string.native(name, Arity::Exact(2), move |interpreter, name, args| {
+            let s = str_arg(interpreter, name, args, 0)?;
+            let part = str_arg(interpreter, name, args, 1)?;
+            Ok(VariTypes::Boolean(test(s, part)))
+        });
+    }
+
+    // split(s, separator?), on runs of whitespace without a separator
+    string.native("split", Arity::Range(1, 2), |interpreter, name, args| {
+        let s = str_arg(interpreter, name, args, 0)?;
+        let parts: Vec<&str> = match args.get(1) {
+            Some(_) => {
+                let separator = str_arg(interpreter, name, args, 1)?;
+                if separator.is_empty() {
+                    return Err(interpreter.error(format!(
+                        "'{}' needs a separator that isn't empty, use 'string.chars' to split \
+                         into characters.",
+                        name
+                    )));
+                }
+                s.split(separator).collect()
+            }
+            None => s.split_whitespace().collect(),
+        };
+
+        let parts = parts
+            .into_iter()
+            .map(|part| VariTypes::String(part.to_owned()))
+            .collect();
+        Ok(new_list(parts))
+    });
+
+    // join(list, separator?), items that aren't strings are printed as by `print`
+    string.native("join", Arity::Range(1, 2), |interpreter, name, args| {
+        let items = list_arg(interpreter, name, args, 0)?;
+        let separator = match args.get(1) {
+            Some(_) => str_arg(interpreter, name, args, 1)?,
+            None => "",
+        };
+
+        let items: Vec<String> = items
+            .borrow()
+            .iter()
+            .map(|item| interpreter.stringify(item.clone()))
+            .collect();
+        Ok(VariTypes::String(items.join(separator)))
+    });
+
+    // replace(s, from, to), every occurrence
+    string.native("replace", Arity::Exact(3), |interpreter, name, args| {
+        let s = str_arg(interpreter, name, args, 0)?;
+        let from = str_arg(interpreter, name, args, 1)?;
+        let to = str_arg(interpreter, name, args, 2)?;
+        Ok(VariTypes::String(s.replace(from, to)))
+    });
+
+    // find(s, part), where `part` first starts, or nil
+    string.native("find", Arity::Exact(2), |interpreter, name, args| {
+        let s = str_arg(interpreter, name, args, 0)?;
+        let part = str_arg(interpreter, name, args, 1)?;
+        match s.find(part) {
+            Some(byte) => Ok(VariTypes::Num(s[..byte].chars().count() as f64)),
+            None => Ok(VariTypes::Nil),
+        }
+    });
+
+    // substring(s, start, end?), up to but not including `end`, negative
+    // positions count back from the end and both are clamped to the string
+    string.native(
+        "substring",
+        Arity::Range(2, 3),
+        |interpreter, name, args| {
+            let s = str_arg(interpreter, name, args, 0)?;
+            let len = s.chars().count();
+            let start = clamp(whole_arg(interpreter, name, args, 1)?, len);
+            let end = match args.get(2) {
+                Some(_) => clamp(whole_arg(interpreter, name, args, 2)?, len),
+                None => len,
+            };
+
+            let substring = s
+                .chars()
+                .skip(start)
+                .take(end.saturating_sub(start))
+                .collect();
+            Ok(VariTypes::String(substring))
+        },
+    );
+
+    // repeat(s, count)
+    string.native("repeat", Arity::Exact(2), |interpreter, name, args| {
+        let s = str_arg(interpreter, name, args, 0)?;
+        let count = whole_arg(interpreter, name, args, 1)?;
+        if count < 0 {
+            return Err(
+                interpreter.error(format!("'{}' can't repeat a string {} times.", name, count))
+            );
+        }
+
+        interpreter.check_memory(s.len().saturating_mul(count as usize))?;
+        Ok(VariTypes::String(s.repeat(count as usize)))
+    });
+
+    // pad_left(s, width, fill?), pad_right(s, width, fill?), fill
+    // is a single character, a space by default
+    for (name, left) in [("pad_left", true), ("pad_right", false)] {
+        string.native(name, Arity::Range(2, 3), move |interpreter, name, args| {
+            let s = str_arg(interpreter, name, args, 0)?;
+            let width = whole_arg(interpreter, name, args, 1)?.max(0) as usize;
+            let fill = match args.get(2) {
+                Some(_) => single_char(interpreter, name, str_arg(interpreter, name, args, 2)?)?,
+                None => ' ',
+            };
+
+            let missing = width.saturating_sub(s.chars().count());
+            interpreter.check_memory(
+                s.len()
+                    .saturating_add(missing.saturating_mul(fill.len_utf8())),
+            )?;
+            let padding: String = std::iter::repeat_n(fill, missing).collect();
+            let padded = if left {
+                padding + s
+            } else {
+                s.to_owned() + &padding
+            };
+            Ok(VariTypes::String(padded))
+        });
+    }
+
+    // chars(s), a list of one character strings
+    string.native("chars", Arity::Exact(1), |interpreter, name, args| {
+        let s = str_arg(interpreter, name, args, 0)?;
+        let chars = s
+            .chars()
+            .map(|c| VariTypes::String(c.to_string()))
+            .collect();
+        Ok(new_list(chars))
+    });
+
+    // ord(c), the Unicode code point of a one character string
+    string.native("ord", Arity::Exact(1), |interpreter, name, args| {
+        let c = single_char(interpreter, name, str_arg(interpreter, name, args, 0)?)?;
+        Ok(VariTypes::Num(c as u32 as f64))
+    });
+
+    // chr(n), the character with code point `n`
+    string.native("chr", Arity::Exact(1), |interpreter, name, args| {
+        let code = whole_arg(interpreter, name, args, 0)?;
+        match u32::try_from(code).ok().and_then(char::from_u32) {
+            Some(c) => Ok(VariTypes::String(c.to_string())),
+            None => Err(interpreter.error(format!(
+                "'{}' got {}, which isn't a Unicode code point.",
+                name, code
+            ))),
+        }
+    });
+
+    string.define(interpreter);
+}
+
+// a position for `substring`, counting back from the end when
+// negative and kept within 0..=len
+fn clamp(position: i64, len: usize) -> usize {
+    let len = len as i64;
+    let position = if position < 0 {
+        position + len
+    } else {
+        position
+    };
+    position.clamp(0, len) as usize
+}
+
+fn single_char(interpreter: &Interpreter, name: &str, s: &str) -> Result<char, VariError> {
+    let mut chars = s.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(c),
+        _ => Err(interpreter.error(format!(
+            "'{}' expects a single character, got \"{}\".",
+            name, s
+        ))),
+    }
+}
@@ -0,0 +1,448 @@
+use std::sync::OnceLock;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::fields::Fields;
+use crate::interpreter::Interpreter;
+use crate::procedure::Arity;
+use crate::stdlib::{num_arg, str_arg, type_error, Module};
+use crate::vari::{VariError, VariTypes};
+
+const NANOS_PER_SEC: i128 = 1_000_000_000;
+const NANOS_PER_MS: f64 = 1e6;
+const SECS_PER_DAY: i128 = 86_400;
+
+// Dates are structs of whole numbers in UTC, {year, month, day, hour,
+// minute, second, nanos}, and durations are milliseconds like `clock`
+// gives.
+pub fn define(interpreter: &mut Interpreter) {
+    let mut time = Module::new("time");
+
+    time.native("now", Arity::Exact(0), |interpreter, name, _| {
+        let now = match SystemTime::now().duration_since(UNIX_EPOCH) {
+            Ok(since) => since.as_nanos() as i128,
+            Err(err) => -(err.duration().as_nanos() as i128),
+        };
+        Ok(DateTime::from_nanos(interpreter, name, now)?.to_struct())
+    });
+
+    // from_timestamp(ms), the date `ms` after the unix epoch
+    time.native(
+        "from_timestamp",
+        Arity::Exact(1),
+        |interpreter, name, args| {
+            let nanos = ms_arg(interpreter, name, args, 0)?;
+            Ok(DateTime::from_nanos(interpreter, name, nanos)?.to_struct())
+        },
+    );
+
+    // timestamp(dt), milliseconds since the unix epoch
+    time.native("timestamp", Arity::Exact(1), |interpreter, name, args| {
+        let dt = DateTime::from_arg(interpreter, name, args, 0)?;
+        Ok(VariTypes::Num(dt.to_nanos() as f64 / NANOS_PER_MS))
+    });
+
+    // add(dt, ms), the date `ms` later, earlier when it's negative
+    time.native("add", Arity::Exact(2), |interpreter, name, args| {
+        let dt = DateTime::from_arg(interpreter, name, args, 0)?;
+        let nanos = ms_arg(interpreter, name, args, 1)?;
+        let later = dt.to_nanos().saturating_add(nanos);
+        Ok(DateTime::from_nanos(interpreter, name, later)?.to_struct())
+    });
+
+    // diff(a, b), the milliseconds from b to a
+    time.native("diff", Arity::Exact(2), |interpreter, name, args| {
+        let a = DateTime::from_arg(interpreter, name, args, 0)?;
+        let b = DateTime::from_arg(interpreter, name, args, 1)?;
+        Ok(VariTypes::Num(
+            (a.to_nanos() - b.to_nanos()) as f64 / NANOS_PER_MS,
+        ))
+    });
+
+    // format(dt, fmt), with %Y, %m, %d, %H, %M, %S, %f (nanoseconds)
+    // and %% for a percent sign
+    time.native("format", Arity::Exact(2), |interpreter, name, args| {
+        let dt = DateTime::from_arg(interpreter, name, args, 0)?;
+        let fmt = str_arg(interpreter, name, args, 1)?;
+
+        let mut out = String::new();
+        let mut chars = fmt.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                out.push(c);
+                continue;
+            }
+
+            let spec = chars.next();
+            let value = match spec {
+                Some('%') => {
+                    out.push('%');
+                    continue;
+                }
+                Some(spec) => Field::of(spec).map(|field| field.get(&dt)),
+                None => None,
+            };
+            match value {
+                Some((value, width)) => out.push_str(&format!("{:0width$}", value)),
+                None => return Err(unknown_spec(interpreter, name, spec)),
+            }
+        }
+        Ok(VariTypes::String(out))
+    });
+
+    // parse(s, fmt), the date in `s` laid out like `format` would with
+    // `fmt`, anything `fmt` leaves out is taken from 1970-01-01 00:00
+    time.native("parse", Arity::Exact(2), |interpreter, name, args| {
+        let s = str_arg(interpreter, name, args, 0)?;
+        let fmt = str_arg(interpreter, name, args, 1)?;
+        let dt = parse(interpreter, name, s, fmt)?;
+        dt.check()
+            .map_err(|problem| interpreter.error(format!("'{}' got {}.", name, problem)))?;
+        Ok(dt.to_struct())
+    });
+
+    // sleep(ms), cut short if the script runs out of time
+    time.native("sleep", Arity::Exact(1), |interpreter, name, args| {
+        let ms = num_arg(interpreter, name, args, 0)?;
+        if !(0.0..=u64::MAX as f64).contains(&ms) {
+            return Err(type_error(interpreter, name, "a positive number", args, 0));
+        }
+        interpreter.sleep(Duration::from_secs_f64(ms / 1000.0))?;
+        Ok(VariTypes::Nil)
+    });
+
+    // perf_counter(), milliseconds from a fixed point that only moves
+    // forward, for timing things rather than telling the time
+    time.native("perf_counter", Arity::Exact(0), |_, _, _| {
+        static START: OnceLock<Instant> = OnceLock::new();
+        let elapsed = START.get_or_init(Instant::now).elapsed();
+        Ok(VariTypes::Num(elapsed.as_secs_f64() * 1000.0))
+    });
+
+    time.define(interpreter);
+}
+
+fn parse(interpreter: &Interpreter, name: &str, s: &str, fmt: &str) -> Result<DateTime, VariError> {
+    let mismatch = |expected: String, at: usize| {
+        interpreter.error(format!(
+            "'{}' couldn't read \"{}\" as \"{}\", expected {} at character {}.",
+            name,
+            s,
+            fmt,
+            expected,
+            at + 1
+        ))
+    };
+
+    let mut dt = DateTime::EPOCH;
+    let input: Vec<char> = s.chars().collect();
+    let mut pos = 0;
+    let mut chars = fmt.chars();
+    while let Some(c) = chars.next() {
+        let spec = match c {
+            '%' => chars.next(),
+            c => {
+                if input.get(pos) != Some(&c) {
+                    return Err(mismatch(format!("'{}'", c), pos));
+                }
+                pos += 1;
+                continue;
+            }
+        };
+        if spec == Some('%') {
+            if input.get(pos) != Some(&'%') {
+                return Err(mismatch("'%'".to_owned(), pos));
+            }
+            pos += 1;
+            continue;
+        }
+
+        let field = spec
+            .and_then(Field::of)
+            .ok_or_else(|| unknown_spec(interpreter, name, spec))?;
+        let (_, width) = field.get(&dt);
+        let digits = input[pos..]
+            .iter()
+            .take(width)
+            .take_while(|c| c.is_ascii_digit())
+            .count();
+        // nanoseconds may be cut short, like a decimal fraction
+        if digits == 0 || (digits < width && field != Field::Nanos) {
+            let expected = format!("{} digits for %{}", width, spec.unwrap_or_default());
+            return Err(mismatch(expected, pos));
+        }
+
+        let text: String = input[pos..pos + digits].iter().collect();
+        let mut value: i64 = text.parse().unwrap_or_default();
+        if field == Field::Nanos {
+            value *= 10i64.pow((width - digits) as u32);
+        }
+        field.set(&mut dt, value);
+        pos += digits;
+    }
+    if pos < input.len() {
+        return Err(mismatch("the end".to_owned(), pos));
+    }
+
+    Ok(dt)
+}
+
+// a duration argument in milliseconds, as nanoseconds
+fn ms_arg(
+    interpreter: &Interpreter,
+    name: &str,
+    args: &[VariTypes],
+    index: usize,
+) -> Result<i128, VariError> {
+    let ms = num_arg(interpreter, name, args, index)?;
+    if !ms.is_finite() {
+        return Err(type_error(
+            interpreter,
+            name,
+            "a finite number",
+            args,
+            index,
+        ));
+    }
+    // whole milliseconds are scaled apart from the fraction so big
+    // timestamps keep their precision, out of range values saturate
+    // and are caught by `from_nanos`
+    let whole = (ms.trunc() as i128).saturating_mul(NANOS_PER_MS as i128);
+    Ok(whole.saturating_add((ms.fract() * NANOS_PER_MS).round() as i128))
+}
+
+fn unknown_spec(interpreter: &Interpreter, name: &str, spec: Option<char>) -> VariError {
+    match spec {
+        Some(spec) => interpreter.error(format!("'{}' doesn't know \"%{}\".", name, spec)),
+        None => interpreter.error(format!("'{}' got a '%' at the end of the format.", name)),
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+struct DateTime {
+    year: i64,
+    month: i64,
+    day: i64,
+    hour: i64,
+    minute: i64,
+    second: i64,
+    nanos: i64,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Field {
+    Year,
+    Month,
+    Day,
+    Hour,
+    Minute,
+    Second,
+    Nanos,
+}
+
+const FIELDS: [(&str, Field); 7] = [
+    ("year", Field::Year),
+    ("month", Field::Month),
+    ("day", Field::Day),
+    ("hour", Field::Hour),
+    ("minute", Field::Minute),
+    ("second", Field::Second),
+    ("nanos", Field::Nanos),
+];
+
+impl Field {
+    fn of(spec: char) -> Option<Field> {
+        match spec {
+            'Y' => Some(Field::Year),
+            'm' => Some(Field::Month),
+            'd' => Some(Field::Day),
+            'H' => Some(Field::Hour),
+            'M' => Some(Field::Minute),
+            'S' => Some(Field::Second),
+            'f' => Some(Field::Nanos),
+            _ => None,
+        }
+    }
+
+    // the value and how many digits it's written with
+    fn get(self, dt: &DateTime) -> (i64, usize) {
+        match self {
+            Field::Year => (dt.year, 4),
+            Field::Month => (dt.month, 2),
+            Field::Day => (dt.day, 2),
+            Field::Hour => (dt.hour, 2),
+            Field::Minute => (dt.minute, 2),
+            Field::Second => (dt.second, 2),
+            Field::Nanos => (dt.nanos, 9),
+        }
+    }
+
+    fn set(self, dt: &mut DateTime, value: i64) {
+        match self {
+            Field::Year => dt.year = value,
+            Field::Month => dt.month = value,
+            Field::Day => dt.day = value,
+            Field::Hour => dt.hour = value,
+            Field::Minute => dt.minute = value,
+            Field::Second => dt.second = value,
+            Field::Nanos => dt.nanos = value,
+        }
+    }
+}
+
+impl DateTime {
+    const EPOCH: DateTime = DateTime {
+        year: 1970,
+        month: 1,
+        day: 1,
+        hour: 0,
+        minute: 0,
+        second: 0,
+        nanos: 0,
+    };
+
+    // the date argument at `index`, every field has to be there
+    fn from_arg(
+        interpreter: &Interpreter,
+        name: &str,
+        args: &[VariTypes],
+        index: usize,
+    ) -> Result<DateTime, VariError> {
+        let fields = match args[index].fields() {
+            Some(fields) => fields,
+            None => return Err(type_error(interpreter, name, "a date", args, index)),
+        };
+
+        let mut dt = DateTime::EPOCH;
+        for (field_name, field) in FIELDS {
+            match fields.get(field_name) {
+                Some(VariTypes::Num(n)) if n.fract() == 0.0 => field.set(&mut dt, *n as i64),
+                _ => {
+                    return Err(interpreter.error(format!(
+                        "'{}' expects a date as argument {}, its '{}' isn't a whole number.",
+                        name,
+                        index + 1,
+                        field_name
+                    )))
+                }
+            }
+        }
+
+        dt.check().map_err(|problem| {
+            interpreter.error(format!(
+                "'{}' got {} as argument {}.",
+                name,
+                problem,
+                index + 1
+            ))
+        })?;
+        Ok(dt)
+    }
+
+    // what's wrong with the date, if anything
+    fn check(&self) -> Result<(), String> {
+        let ranges = [
+            ("year", self.year, 0, 9999),
+            ("month", self.month, 1, 12),
+            ("day", self.day, 1, days_in_month(self.year, self.month)),
+            ("hour", self.hour, 0, 23),
+            ("minute", self.minute, 0, 59),
+            ("second", self.second, 0, 59),
+            ("nanos", self.nanos, 0, 999_999_999),
+        ];
+
+        for (field, value, min, max) in ranges {
+            if !(min..=max).contains(&value) {
+                return Err(format!(
+                    "{} {}, which should be from {} to {}",
+                    field, value, min, max
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    // the date `nanos` after the unix epoch
+    fn from_nanos(interpreter: &Interpreter, name: &str, nanos: i128) -> Result<Self, VariError> {
+        let secs = nanos.div_euclid(NANOS_PER_SEC);
+        let (year, month, day) = civil_from_days(secs.div_euclid(SECS_PER_DAY));
+        let secs = secs.rem_euclid(SECS_PER_DAY) as i64;
+
+        let dt = DateTime {
+            year,
+            month,
+            day,
+            hour: secs / 3600,
+            minute: secs / 60 % 60,
+            second: secs % 60,
+            nanos: nanos.rem_euclid(NANOS_PER_SEC) as i64,
+        };
+        if !(0..=9999).contains(&year) {
+            return Err(interpreter.error(format!(
+                "'{}' can only give dates from year 0 to 9999.",
+                name
+            )));
+        }
+        Ok(dt)
+    }
+
+    fn to_nanos(self) -> i128 {
+        let days = days_from_civil(self.year, self.month, self.day) as i128;
+        let secs =
+            days * SECS_PER_DAY + (self.hour * 3600 + self.minute * 60 + self.second) as i128;
+        secs * NANOS_PER_SEC + self.nanos as i128
+    }
+
+    fn to_struct(self) -> VariTypes {
+        let fields: Fields<VariTypes> = FIELDS
+            .iter()
+            .map(|&(name, field)| (name.to_owned(), VariTypes::Num(field.get(&self).0 as f64)))
+            .collect();
+        VariTypes::Struct(fields)
+    }
+}
+
+fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Days since 1970-01-01 and back, from Howard Hinnant's "chrono-Compatible
+// Low-Level Date Algorithms". Years are counted from March so the leap
+// day comes last.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+fn civil_from_days(days: i128) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let march_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * march_month + 2) / 5 + 1;
+    let month = if march_month < 10 {
+        march_month + 3
+    } else {
+        march_month - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    // years far outside what `from_nanos` accepts are clamped
+    let year = year.clamp(i64::MIN as i128, i64::MAX as i128) as i64;
+    (year, month as i64, day as i64)
+}
@@ -4,18 +4,39 @@ use crate::{expr::Expr, token::Token};
 pub enum Stmt {
     Block(Vec<Stmt>),
     Expression(Expr),
-    Print(Expr),
+    // `print` or `eprint` keyword, value
+    Print(Token, Expr),
     // name, initializer
     Var(Token, Option<Expr>),
-    // condition, if branch, else branch
-    If(Expr, Box<Stmt>, Option<Box<Stmt>>),
-    // condition, body
-    While(Expr, Box<Stmt>),
+    // keyword, condition, if branch, else branch
+    If(Token, Expr, Box<Stmt>, Option<Box<Stmt>>),
+    // keyword, condition, body
+    While(Token, Expr, Box<Stmt>),
     // name, parameters, body, closure
     Function(Token, Vec<Token>, Vec<Stmt>),
+    // name, fields with their defaults
+    Struct(Token, Vec<(Token, Option<Expr>)>),
     Return(Token, Expr),
 }
 
+impl Stmt {
+    // the source line a debugger would show for this statement,
+    // None for blocks and expressions made only of literals
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            Stmt::Block(_) => None,
+            Stmt::Expression(expr) => expr.line(),
+            Stmt::Print(keyword, _)
+            | Stmt::If(keyword, ..)
+            | Stmt::While(keyword, ..)
+            | Stmt::Return(keyword, _) => Some(keyword.line),
+            Stmt::Var(name, _) | Stmt::Function(name, ..) | Stmt::Struct(name, _) => {
+                Some(name.line)
+            }
+        }
+    }
+}
+
 pub trait StmtVisitor<T> {
     fn visit_stmt(&mut self, stmt: Stmt) -> T;
 }
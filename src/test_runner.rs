@@ -0,0 +1,195 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::interpreter::Interpreter;
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+use crate::stmt::Stmt;
+use crate::token::Token;
+use crate::vari::{VariError, VariTypes};
+
+const TEST_FILE_SUFFIX: &str = "_test.vari";
+const TEST_FUNCTION_PREFIX: &str = "test_";
+
+// a failed test or a test file that couldn't be run
+pub struct Failure {
+    pub path: PathBuf,
+    // None when the whole file failed
+    pub test: Option<String>,
+    pub line: usize,
+    pub msg: String,
+}
+
+#[derive(Default)]
+pub struct Summary {
+    pub passed: usize,
+    pub failures: Vec<Failure>,
+}
+
+// runs `vari test [path...]`, returning the process exit code,
+// directories are searched for test files, "." by default
+pub fn run(paths: &[String]) -> i32 {
+    let paths = if paths.is_empty() {
+        vec![".".to_owned()]
+    } else {
+        paths.to_vec()
+    };
+
+    let mut files = vec![];
+    for path in &paths {
+        let path = Path::new(path);
+        if path.is_dir() {
+            if let Err(err) = discover(path, &mut files) {
+                println!("{}: {}", path.display(), err);
+                return 1;
+            }
+        } else {
+            files.push(path.to_path_buf());
+        }
+    }
+
+    let mut summary = Summary::default();
+    for file in &files {
+        run_file(file, &mut summary);
+    }
+
+    if !summary.failures.is_empty() {
+        println!("\nfailures:");
+    }
+    for failure in &summary.failures {
+        let name = failure.test.as_deref().unwrap_or("<file>");
+        println!(
+            "  {}:{}: {}: {}",
+            failure.path.display(),
+            failure.line,
+            name,
+            failure.msg
+        );
+    }
+
+    println!(
+        "\n{} passed, {} failed, {} file(s)",
+        summary.passed,
+        summary.failures.len(),
+        files.len()
+    );
+
+    if summary.failures.is_empty() {
+        0
+    } else {
+        1
+    }
+}
+
+// collects every `*_test.vari` file under `dir`, sorted by path
+fn discover(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    let mut entries: Vec<_> = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<_, _>>()?;
+    entries.sort();
+
+    for path in entries {
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or("");
+        if name.starts_with('.') {
+            continue;
+        }
+
+        if path.is_dir() {
+            discover(&path, files)?;
+        } else if name.ends_with(TEST_FILE_SUFFIX) {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+pub fn run_file(path: &Path, summary: &mut Summary) {
+    println!("{}", path.display());
+
+    let failure = |test: Option<&str>, line, msg| Failure {
+        path: path.to_path_buf(),
+        test: test.map(str::to_owned),
+        line,
+        msg,
+    };
+
+    let source = match fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(err) => {
+            println!("  FAIL  {}", err);
+            summary.failures.push(failure(None, 0, err.to_string()));
+            return;
+        }
+    };
+
+    let mut lexer = Lexer::new(source);
+    let mut parser = Parser::new(lexer.scan_tokens());
+    let statements = parser.parse();
+
+    if let Some(err) = lexer.errors().iter().chain(parser.errors()).next() {
+        println!("  FAIL  syntax error");
+        summary
+            .failures
+            .push(failure(None, err.line, err.msg.clone()));
+        return;
+    }
+
+    for name in test_functions(&statements) {
+        match run_test(&statements, &name) {
+            Ok(()) => {
+                println!("  ok    {}", name.lexeme);
+                summary.passed += 1;
+            }
+            Err((line, msg)) => {
+                println!("  FAIL  {}", name.lexeme);
+                summary
+                    .failures
+                    .push(failure(Some(&name.lexeme), line, msg));
+            }
+        }
+    }
+}
+
+// the names of top level `fn test_*()` declarations
+fn test_functions(statements: &[Stmt]) -> Vec<Token> {
+    statements
+        .iter()
+        .filter_map(|stmt| match stmt {
+            Stmt::Function(name, params, _)
+                if name.lexeme.starts_with(TEST_FUNCTION_PREFIX) && params.is_empty() =>
+            {
+                Some(name.clone())
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+// runs the file's top level code and then the test in a fresh
+// interpreter, so one test can't leave state behind for another
+fn run_test(statements: &[Stmt], name: &Token) -> Result<(), (usize, String)> {
+    let mut interpreter = Interpreter::new();
+
+    let result = interpreter.interpret(statements.to_vec()).and_then(|_| {
+        let test = interpreter.environment().borrow_mut().get(name.clone())?;
+        match test {
+            VariTypes::Callable(test) => test.call(&mut interpreter, vec![]),
+            _ => Err(VariError::RuntimeError(
+                name.line,
+                format!("'{}' is not a function.", name.lexeme),
+            )),
+        }
+    });
+
+    match result {
+        Err(VariError::Exit(line, code)) => Err((line, format!("Test called exit({}).", code))),
+        result => match result.err().and_then(VariError::report) {
+            Some(failure) => Err(failure),
+            None => Ok(()),
+        },
+    }
+}
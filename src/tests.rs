@@ -1,7 +1,23 @@
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 mod tests {
-    use crate::{lexer::Lexer, vari::Vari, interpreter::Interpreter};
+    use crate::lexer::Lexer;
+    use std::cell::RefCell;
+    use std::io::Write;
+    use std::rc::Rc;
 
+    // lets a test read what was written after the writer is dropped
+    struct Output(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Output {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
 
     #[test]
     pub fn ast_test() {
@@ -19,6 +35,7 @@ mod tests {
                     lexeme: "-".to_owned(),
                     literal: None,
                     line: 1,
+                    column: 0,
                 },
                 rhs: Box::new(Expr::Literal {
                     value: Box::new(VariTypes::Num(25.0)),
@@ -29,6 +46,7 @@ mod tests {
                 lexeme: "*".to_owned(),
                 literal: None,
                 line: 1,
+                column: 3,
             },
             rhs: Box::new(Expr::Grouping {
                 expr: Box::new(Expr::Literal {
@@ -41,7 +59,7 @@ mod tests {
     }
 
     #[test]
-    pub fn fib_lexer_test(){
+    pub fn fib_lexer_test() {
         let source = "
         fun fib(n) {                    \
             if (n <= 1) return 1;       \
@@ -52,16 +70,10 @@ mod tests {
             print fib(i);               \
         }";
 
-        let vari: Vari = Vari {
-            had_error: false,
-            interpreter: Interpreter::new(),
-        };
-
-        let mut lexer = Lexer::new(String::from(source), vari);
+        let mut lexer = Lexer::new(String::from(source));
 
         let result = "IDENTIFIER fun None IDENTIFIER fib None LPAREN ( None IDENTIFIER n None RPAREN ) None LBRACE { None IF if None LPAREN ( None IDENTIFIER n None LE <= None NUMBER 1 Some(Num(1.0)) RPAREN ) None RETURN return None NUMBER 1 Some(Num(1.0)) SEMICOLON ; None RETURN return None IDENTIFIER fib None LPAREN ( None IDENTIFIER n None MINUS - None NUMBER 1 Some(Num(1.0)) RPAREN ) None PLUS + None IDENTIFIER fib None LPAREN ( None IDENTIFIER n None MINUS - None NUMBER 2 Some(Num(2.0)) RPAREN ) None SEMICOLON ; None RBRACE } None FOR for None LPAREN ( None LET let None IDENTIFIER i None EQUAL = None NUMBER 0 Some(Num(0.0)) SEMICOLON ; None IDENTIFIER i None LT < None NUMBER 10 Some(Num(10.0)) SEMICOLON ; None IDENTIFIER i None EQUAL = None IDENTIFIER i None PLUS + None NUMBER 1 Some(Num(1.0)) RPAREN ) None LBRACE { None PRINT print None IDENTIFIER fib None LPAREN ( None IDENTIFIER i None RPAREN ) None SEMICOLON ; None RBRACE } None EOF  None ";
 
-
         let tokens = lexer.scan_tokens();
         let mut tokenstr = String::new();
         for token in tokens {
@@ -70,4 +82,1642 @@ mod tests {
         assert!(tokenstr == result);
     }
 
+    fn parse(source: &str) -> (Vec<crate::stmt::Stmt>, Vec<crate::vari::SyntaxError>) {
+        use crate::parser::Parser;
+
+        let mut lexer = Lexer::new(String::from(source));
+        let mut parser = Parser::new(lexer.scan_tokens());
+        let statements = parser.parse();
+        (statements, parser.errors().to_vec())
+    }
+
+    #[test]
+    pub fn resolver_test() {
+        use crate::resolver::{Resolver, SymbolKind};
+
+        let source = "fn fib(n) {\n  if (n <= 1) return 1;\n  return fib(n-1) + fib(n-2);\n}\nprint fib(x);\nreturn;";
+        let (statements, _) = parse(source);
+        let resolution = Resolver::new(vec!["clock".to_owned()]).resolve(&statements);
+
+        let fib = &resolution.symbols[0];
+        assert_eq!(fib.name.lexeme, "fib");
+        assert_eq!(fib.kind, SymbolKind::Function);
+        assert_eq!(fib.references.len(), 3);
+        assert_eq!(resolution.symbols[1].references.len(), 3);
+
+        // the `n` in `n-2` on line 3
+        assert_eq!(resolution.symbol_at(3, 24), Some(1));
+
+        let messages: Vec<_> = resolution
+            .diagnostics
+            .iter()
+            .map(|d| d.msg.as_str())
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                "Can't return from top-level code.",
+                "Undefined variable 'x'."
+            ]
+        );
+    }
+
+    #[test]
+    pub fn lsp_test() {
+        let mut input = Vec::new();
+        let mut send = |msg: &str| {
+            input.extend(format!("Content-Length: {}\r\n\r\n{}", msg.len(), msg).bytes());
+        };
+
+        send(r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#);
+        send(
+            r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"uri":"file:///t.vari","text":"let a = 1;\nprint a + b;"}}}"#,
+        );
+        send(
+            r#"{"jsonrpc":"2.0","id":2,"method":"textDocument/definition","params":{"textDocument":{"uri":"file:///t.vari"},"position":{"line":1,"character":6}}}"#,
+        );
+        send(r#"{"jsonrpc":"2.0","id":3,"method":"shutdown"}"#);
+        send(r#"{"jsonrpc":"2.0","method":"exit"}"#);
+
+        let mut output = Vec::new();
+        let code = crate::lsp::serve(input.as_slice(), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert_eq!(code, 0);
+        assert!(output.contains(r#""message":"Undefined variable 'b'.""#));
+        assert!(output.contains(
+            r#""id":2,"result":{"uri":"file:///t.vari","range":{"start":{"line":0,"character":4},"end":{"line":0,"character":5}}}"#
+        ));
+    }
+
+    #[test]
+    pub fn linter_test() {
+        let source = "\
+let g = 1;
+fn f(a, b) {
+  let g = a; # vari-lint: allow(shadowing)
+  return g;
+  print b;
+}
+if (g = 2) print f(g);
+print g < g;";
+
+        let (_, diagnostics) = crate::linter::check(source, &[]);
+        let found: Vec<_> = diagnostics
+            .iter()
+            .map(|d| (d.line, d.rule.unwrap_or("error")))
+            .collect();
+
+        assert_eq!(
+            found,
+            [
+                (5, "unreachable-code"),
+                (7, "assign-in-condition"),
+                (7, "wrong-arity"),
+                (8, "self-comparison"),
+            ]
+        );
+
+        // the dead code is what's reported, and what an allow comment goes on
+        let source = "\
+fn f() {
+  return 1;
+  {
+    print 2; # vari-lint: allow(unreachable-code)
+  }
+}
+fn g() {
+  return 1;
+
+  print 2;
+}";
+        let (_, diagnostics) = crate::linter::check(source, &[]);
+        let found: Vec<_> = diagnostics
+            .iter()
+            .map(|d| (d.line, d.column, d.len))
+            .collect();
+        assert_eq!(found, [(10, 2, 8)]);
+    }
+
+    #[test]
+    pub fn debugger_test() {
+        use crate::debugger::Debugger;
+        use crate::interpreter::Interpreter;
+        use crate::vari::VariError;
+
+        let source = "\
+fn add(a, b) {
+  let s = a + b;
+  return s;
+}
+let y = add(1, 2);";
+
+        let commands = "b 2\nc\nl\np a * 10\np let z = 1;\nbt\nl all\nq\nc\n";
+        let output = Rc::new(RefCell::new(vec![]));
+        let debugger = Debugger::new(
+            source,
+            Box::new(commands.as_bytes()),
+            Box::new(Output(output.clone())),
+        );
+
+        let mut interpreter = Interpreter::new();
+        interpreter.set_hook(Some(Box::new(debugger)));
+        // quitting stops the program instead of the whole process
+        assert!(matches!(
+            interpreter.interpret(parse(source).0),
+            Err(VariError::Exit(2, 0))
+        ));
+
+        let output = String::from_utf8(output.borrow().clone()).unwrap();
+        assert!(output.contains("Breakpoint set at line 2."));
+        assert!(output.contains("   2 |   let s = a + b;"));
+        assert!(output.contains(
+            "locals:\n  a = 1\n  b = 2\nenclosing scope 1:\n  add = <fn add>\n(vdb) 10\n"
+        ));
+        assert!(output.contains("globals:\n"));
+        assert!(output.contains("#0 add (declared on line 1) at line 2\n#1 <script> at line 5"));
+    }
+
+    #[test]
+    pub fn profiler_test() {
+        use crate::interpreter::Interpreter;
+        use crate::profiler::{FunctionKey, Profiler};
+
+        let source = "\
+fn fib(n) {
+  if (n < 2) return n;
+  return fib(n - 1) + fib(n - 2);
+}
+print fib(5);";
+
+        let profiler = Rc::new(RefCell::new(Profiler::new()));
+        let output = Rc::new(RefCell::new(vec![]));
+        let mut interpreter = Interpreter::new();
+        interpreter.set_hook(Some(Box::new(Rc::clone(&profiler))));
+        interpreter.set_output(Box::new(Output(Rc::clone(&output))));
+        assert!(interpreter.interpret(parse(source).0).is_ok());
+        assert_eq!(String::from_utf8_lossy(&output.borrow()), "5\n");
+
+        let mut profiler = profiler.borrow_mut();
+        profiler.finish();
+
+        let fib = profiler.functions[&FunctionKey {
+            name: "fib".to_owned(),
+            line: 1,
+        }];
+        assert_eq!(fib.calls, 15);
+        assert!(fib.exclusive <= fib.inclusive && fib.inclusive <= profiler.total);
+
+        // the `if` and, for the 8 base cases, the `return` inside it
+        assert_eq!(profiler.lines[&2].hits, 15 + 8);
+        assert_eq!(profiler.lines[&3].hits, 7);
+        assert_eq!(profiler.lines[&5].hits, 1);
+
+        let mut folded = vec![];
+        profiler.write_folded(&mut folded).unwrap();
+        let folded = String::from_utf8(folded).unwrap();
+        let stacks: Vec<_> = folded
+            .lines()
+            .map(|line| line.rsplit_once(' ').unwrap().0)
+            .collect();
+        assert_eq!(
+            stacks,
+            [
+                "<script>",
+                "<script>;fib:1",
+                "<script>;fib:1;fib:1",
+                "<script>;fib:1;fib:1;fib:1",
+                "<script>;fib:1;fib:1;fib:1;fib:1",
+                "<script>;fib:1;fib:1;fib:1;fib:1;fib:1",
+            ]
+        );
+    }
+
+    #[test]
+    pub fn test_runner_test() {
+        use crate::test_runner::{run_file, Summary};
+
+        let source = "\
+let calls = 0;
+fn test_first() {
+  calls = calls + 1;
+  assert_eq(calls, 1);
+}
+fn test_fresh_interpreter() {
+  calls = calls + 1;
+  assert_eq(calls, 1);
+}
+fn test_assert() {
+  assert(calls == 1, \"calls should start at 1\");
+}
+fn test_runtime_error() {
+  print -\"x\";
+}
+fn helper() {
+  assert(false, \"not a test\");
+}
+fn test_structs() {
+  assert_eq({a: 1, b: [2]}, {b: [2], a: 1});
+  assert({a: 1} != {a: 2} and {a: 1} != {a: 1, b: 2});
+}
+fn quit() {
+  exit(3);
+}
+fn test_exit() {
+  quit();
+}";
+
+        let path = std::env::temp_dir().join(format!("vari_{}_test.vari", std::process::id()));
+        std::fs::write(&path, source).unwrap();
+
+        let mut summary = Summary::default();
+        run_file(&path, &mut summary);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(summary.passed, 3);
+        let failures: Vec<_> = summary
+            .failures
+            .iter()
+            .map(|f| (f.test.as_deref().unwrap(), f.line, f.msg.as_str()))
+            .collect();
+        assert_eq!(
+            failures,
+            [
+                (
+                    "test_assert",
+                    11,
+                    "Assertion failed: calls should start at 1"
+                ),
+                ("test_runtime_error", 14, "Operand must be a number."),
+                // the line of the exit, not of the call that led to it
+                ("test_exit", 24, "Test called exit(3)."),
+            ]
+        );
+    }
+
+    #[test]
+    pub fn engine_test() {
+        use crate::{Engine, Error, Value};
+
+        let mut engine = Engine::new();
+        assert_eq!(engine.eval("1 + 2").unwrap(), Value::Num(3.0));
+        // the last expression's ';' is optional, whatever follows it
+        assert_eq!(engine.eval("1 + 2 # sum").unwrap(), Value::Num(3.0));
+        assert_eq!(
+            engine.eval("let p = {a: 1};\np.a\n# done\n").unwrap(),
+            Value::Num(1.0)
+        );
+        assert!(matches!(engine.eval("let a = 1"), Err(Error::Syntax(_))));
+
+        engine.set_global("base", Value::Num(10.0));
+        engine
+            .eval("let total = 0; fn add(n) { total = total + n; return base + n; }")
+            .unwrap();
+        assert_eq!(
+            engine.call_function("add", &[Value::Num(5.0)]).unwrap(),
+            Value::Num(15.0)
+        );
+        assert_eq!(engine.get_global("total"), Some(Value::Num(5.0)));
+        assert_eq!(engine.get_global("missing"), None);
+
+        match engine.eval("let a = 1;\nprint a + nil;") {
+            Err(Error::Runtime { line, .. }) => assert_eq!(line, 2),
+            _ => panic!("expected a runtime error"),
+        }
+        assert!(matches!(engine.eval("let = 1;"), Err(Error::Syntax(_))));
+        assert!(matches!(
+            engine.call_function("add", &[]),
+            Err(Error::Call(_))
+        ));
+        assert!(matches!(
+            engine.call_function("total", &[]),
+            Err(Error::Call(_))
+        ));
+    }
+
+    #[test]
+    pub fn host_function_test() {
+        use crate::{Engine, Error, Value};
+        use std::cell::Cell;
+
+        let mut engine = Engine::new();
+        engine.register("add", |a: f64, b: f64| a + b);
+        engine.register("greet", |name: String, greeting: Option<String>| {
+            format!("{}, {}!", greeting.unwrap_or("Hello".to_owned()), name)
+        });
+        engine.register("checked_div", |a: f64, b: f64| {
+            if b == 0.0 {
+                Err("division by zero")
+            } else {
+                Ok(a / b)
+            }
+        });
+        engine.register_variadic("sum", 0, |args: &[Value]| {
+            args.iter()
+                .map(|arg| match arg {
+                    Value::Num(n) => *n,
+                    _ => 0.0,
+                })
+                .sum::<f64>()
+        });
+
+        // host functions can keep state between calls
+        let calls = Rc::new(Cell::new(0));
+        let counter = Rc::clone(&calls);
+        engine.register("tick", move || counter.set(counter.get() + 1));
+
+        assert_eq!(engine.eval("add(1, 2)").unwrap(), Value::Num(3.0));
+        assert_eq!(
+            engine.eval("greet(\"vari\")").unwrap(),
+            Value::from("Hello, vari!")
+        );
+        assert_eq!(
+            engine.eval("greet(\"vari\", \"Hi\")").unwrap(),
+            Value::from("Hi, vari!")
+        );
+        assert_eq!(engine.eval("sum(1, 2, 3, 4)").unwrap(), Value::Num(10.0));
+        engine.eval("tick(); tick();").unwrap();
+        assert_eq!(calls.get(), 2);
+
+        let runtime_error = |result: Result<Value, Error>| match result {
+            Err(Error::Runtime { msg, .. }) => msg,
+            _ => panic!("expected a runtime error"),
+        };
+        assert_eq!(
+            runtime_error(engine.eval("add(1, \"2\")")),
+            "'add' expects a number as argument 2, got string."
+        );
+        assert_eq!(
+            runtime_error(engine.eval("greet()")),
+            "Expected 1 to 2 arguments but got 0."
+        );
+        assert_eq!(
+            runtime_error(engine.eval("checked_div(1, 0)")),
+            "division by zero"
+        );
+    }
+
+    #[test]
+    pub fn limits_test() {
+        use crate::{Engine, Error, Limit, Limits};
+        use std::time::Duration;
+
+        let hit_limit = |limits: Limits, source: &str| {
+            let mut engine = Engine::new();
+            engine.set_limits(limits);
+            match engine.eval(source) {
+                Err(Error::Limit { limit, .. }) => Some(limit),
+                _ => None,
+            }
+        };
+
+        let forever = "while (true) {}";
+        let steps = Limits {
+            max_steps: Some(1000),
+            ..Limits::default()
+        };
+        assert_eq!(hit_limit(steps, forever), Some(Limit::Steps));
+        assert_eq!(hit_limit(steps, "let a = 1; a = a + 1;"), None);
+
+        let time = Limits {
+            max_time: Some(Duration::from_millis(20)),
+            ..Limits::default()
+        };
+        assert_eq!(hit_limit(time, forever), Some(Limit::Time));
+
+        let depth = Limits {
+            max_depth: Some(20),
+            ..Limits::default()
+        };
+        let recurse = "fn down(n) { if (n > 0) down(n - 1); } down(19); down(20);";
+        assert_eq!(hit_limit(depth, recurse), Some(Limit::Depth));
+
+        let memory = Limits {
+            max_memory: Some(1024),
+            ..Limits::default()
+        };
+        let grow = "let s = \"ab\"; while (true) s = s + s;";
+        assert_eq!(hit_limit(memory, grow), Some(Limit::Memory));
+
+        // the usage is reset for every call into the engine
+        let mut engine = Engine::new();
+        engine.set_limits(steps);
+        engine
+            .eval("fn count(n) { while (n > 0) n = n - 1; }")
+            .unwrap();
+        for _ in 0..10 {
+            engine.call_function("count", &[100.0.into()]).unwrap();
+        }
+    }
+    #[test]
+    pub fn default_limits_test() {
+        use crate::interpreter::Interpreter;
+        use crate::vari::VariError;
+        use crate::{Engine, Error, Limit};
+        use std::thread;
+
+        // a host thread gets 2 MB of stack unless it asks for more,
+        // going too deep has to be an error and not an abort
+        let run = thread::Builder::new()
+            .stack_size(2 * 1024 * 1024)
+            .spawn(|| {
+                let mut engine = Engine::new();
+                let recurse = "fn down(n) { return down(n + 1) + 1; } down(0)";
+                assert!(matches!(
+                    engine.eval(recurse),
+                    Err(Error::Limit {
+                        limit: Limit::Depth,
+                        ..
+                    })
+                ));
+
+                // the same for the interpreter the tools and the tests use
+                let mut interpreter = Interpreter::new();
+                let recurse = parse("fn down(n) { return down(n + 1) + 1; } down(0);").0;
+                assert!(matches!(
+                    interpreter.interpret(recurse),
+                    Err(VariError::LimitExceeded(_, Limit::Depth))
+                ));
+
+                let nested = [
+                    format!("{}1{}", "(".repeat(10_000), ")".repeat(10_000)),
+                    format!("{}1", "-".repeat(10_000)),
+                    format!("{}1", "1 + ".repeat(100_000)),
+                    format!("{}{}", "{".repeat(10_000), "}".repeat(10_000)),
+                    format!("{}1{}", "[".repeat(10_000), "]".repeat(10_000)),
+                ];
+                for source in nested {
+                    assert!(matches!(engine.eval(&source), Err(Error::Syntax(_))));
+                }
+
+                // as deep as the parser allows still runs
+                let deep = format!("{}1{}", "(".repeat(30), ")".repeat(30));
+                assert_eq!(engine.eval(&deep).unwrap(), 1.0.into());
+
+                // a long chain isn't nested, in a function body or not
+                let long = format!("{}1", "1 + ".repeat(400));
+                assert_eq!(engine.eval(&long).unwrap(), 401.0.into());
+                let long = format!(
+                    "fn all() {{ return {}true; }} all()",
+                    "true and ".repeat(400)
+                );
+                assert_eq!(engine.eval(&long).unwrap(), true.into());
+            })
+            .unwrap();
+        run.join().unwrap();
+    }
+    #[test]
+    pub fn deep_values_test() {
+        use crate::{Engine, Error, Limit, Value};
+        use std::thread;
+
+        // lists nest as deep as a script builds them, comparing, printing,
+        // converting and dropping them mustn't run out of stack
+        let run = thread::Builder::new()
+            .stack_size(2 * 1024 * 1024)
+            .spawn(|| {
+                let mut engine = Engine::new();
+                engine
+                    .eval(
+                        "let a = []; let b = []; let i = 0; \
+                         while (i < 200000) { a = [a]; b = [b]; i = i + 1; }",
+                    )
+                    .unwrap();
+
+                for source in ["a == b", "a != b", "json.stringify(a)"] {
+                    assert!(matches!(
+                        engine.eval(source),
+                        Err(Error::Limit {
+                            limit: Limit::Depth,
+                            ..
+                        })
+                    ));
+                }
+
+                let shown = engine.eval("str(a)").unwrap();
+                assert!(
+                    matches!(shown, Value::String(s) if s.starts_with("[[[") && s.contains("..."))
+                );
+                assert!(matches!(engine.eval("a").unwrap(), Value::List(_)));
+
+                // shallow enough still compares
+                assert_eq!(engine.eval("[[[1]]] == [[[1]]]").unwrap(), true.into());
+
+                // structs are copied, they can't be built that deep at all
+                assert!(matches!(
+                    engine.eval("let s = {}; i = 0; while (i < 5000) { s = {a: s}; i = i + 1; }"),
+                    Err(Error::Limit {
+                        limit: Limit::Depth,
+                        ..
+                    })
+                ));
+                assert_eq!(engine.eval("i").unwrap(), 511.0.into());
+
+                // dropping the lists with the engine
+                drop(engine);
+            })
+            .unwrap();
+        run.join().unwrap();
+    }
+    #[test]
+    pub fn streams_test() {
+        use crate::vari::Vari;
+        use crate::Engine;
+        use std::io::Cursor;
+
+        let output = Rc::new(RefCell::new(vec![]));
+        let error_output = Rc::new(RefCell::new(vec![]));
+        let mut engine = Engine::new();
+        engine.set_output(Output(Rc::clone(&output)));
+        engine.set_error_output(Output(Rc::clone(&error_output)));
+
+        engine
+            .eval("print \"out\"; eprint \"err\"; print 1 + 2;")
+            .unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.borrow()), "out\n3\n");
+        assert_eq!(String::from_utf8_lossy(&error_output.borrow()), "err\n");
+
+        // the prompt reads lines from the interpreter's input
+        let output = Rc::new(RefCell::new(vec![]));
+        let mut vari = Vari::new();
+        vari.interpreter
+            .set_output(Box::new(Output(Rc::clone(&output))));
+        vari.interpreter
+            .set_input(Box::new(Cursor::new("let a = 2;\nprint a * 21;\n")));
+        vari.run_prompt();
+        assert_eq!(String::from_utf8_lossy(&output.borrow()), "> > 42\n> \n");
+
+        // input that isn't UTF-8 ends the prompt with an error
+        let output = Rc::new(RefCell::new(vec![]));
+        let mut vari = Vari::new();
+        vari.interpreter
+            .set_output(Box::new(Output(Rc::clone(&output))));
+        vari.interpreter
+            .set_input(Box::new(Cursor::new(b"print 1;\n\xff\n".to_vec())));
+        vari.run_prompt();
+        assert!(vari.had_error);
+        assert_eq!(
+            String::from_utf8_lossy(&output.borrow()),
+            "> 1\n> \nCouldn't read input: stream did not contain valid UTF-8.\n"
+        );
+
+        // so does output that can't be written
+        struct Closed;
+        impl Write for Closed {
+            fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+                Err(std::io::ErrorKind::BrokenPipe.into())
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+        let error_output = Rc::new(RefCell::new(vec![]));
+        let mut vari = Vari::new();
+        vari.interpreter.set_output(Box::new(Closed));
+        vari.interpreter
+            .set_error_output(Box::new(Output(Rc::clone(&error_output))));
+        vari.interpreter
+            .set_input(Box::new(Cursor::new("print 1;\n")));
+        vari.run_prompt();
+        assert!(vari.had_error);
+        assert_eq!(
+            String::from_utf8_lossy(&error_output.borrow()),
+            "Couldn't write output: broken pipe.\n"
+        );
+    }
+
+    #[test]
+    pub fn math_test() {
+        use crate::{Engine, Error, Value};
+
+        let mut engine = Engine::new();
+        let mut eval = |source: &str| engine.eval(source);
+
+        assert_eq!(eval("math.sqrt(16)").unwrap(), Value::Num(4.0));
+        assert_eq!(eval("math.pow(2, 10)").unwrap(), Value::Num(1024.0));
+        assert_eq!(eval("math.round(-2.5)").unwrap(), Value::Num(-3.0));
+        assert_eq!(eval("math.min(3, -1, 2)").unwrap(), Value::Num(-1.0));
+        assert_eq!(eval("math.max(3, -1, 2)").unwrap(), Value::Num(3.0));
+        assert_eq!(eval("math.clamp(12, 0, 10)").unwrap(), Value::Num(10.0));
+        assert_eq!(eval("math.log(8, 2)").unwrap(), Value::Num(3.0));
+        assert_eq!(eval("math.log(math.E)").unwrap(), Value::Num(1.0));
+        assert_eq!(eval("math.hypot(3, 4)").unwrap(), Value::Num(5.0));
+        assert_eq!(eval("math.cos(math.PI)").unwrap(), Value::Num(-1.0));
+        assert_eq!(eval("math.is_nan(math.NAN)").unwrap(), Value::Bool(true));
+        assert_eq!(
+            eval("math.is_finite(math.INF)").unwrap(),
+            Value::Bool(false)
+        );
+
+        match eval("math.sqrt(\"4\")") {
+            Err(Error::Runtime { msg, .. }) => assert_eq!(
+                msg,
+                "'math.sqrt' expects a number as argument 1, got string."
+            ),
+            other => panic!("expected a type error, got {:?}", other),
+        }
+        assert!(eval("math.max(1, nil)").is_err());
+        assert!(eval("math.clamp(1, 10, 0)").is_err());
+    }
+
+    #[test]
+    pub fn string_test() {
+        use crate::{Engine, Error, Value};
+
+        let mut engine = Engine::new();
+        engine.register_variadic("count", 0, |args: &[Value]| args.len() as f64);
+        let mut eval = |source: &str| engine.eval(source);
+        let string = |s: &str| Value::String(s.to_owned());
+
+        assert_eq!(eval("string.upper(\"héllo\")").unwrap(), string("HÉLLO"));
+        assert_eq!(eval("string.trim(\"  a b  \")").unwrap(), string("a b"));
+        assert_eq!(eval("len(\"héllo\")").unwrap(), Value::Num(5.0));
+        assert_eq!(
+            eval("string.split(\"a,b,,c\", \",\")").unwrap(),
+            Value::List(vec![string("a"), string("b"), string(""), string("c")])
+        );
+        assert_eq!(
+            eval("string.join(string.split(\" x  y \"), \"-\")").unwrap(),
+            string("x-y")
+        );
+        assert_eq!(
+            eval("string.join([1, \"a\", nil])").unwrap(),
+            string("1anil")
+        );
+        assert_eq!(
+            eval("string.find(\"héllo\", \"l\")").unwrap(),
+            Value::Num(2.0)
+        );
+        assert_eq!(eval("string.find(\"hello\", \"z\")").unwrap(), Value::Nil);
+        assert_eq!(
+            eval("string.substring(\"héllo\", 1, 2)").unwrap(),
+            string("é")
+        );
+        assert_eq!(
+            eval("string.substring(\"hello\", -3)").unwrap(),
+            string("llo")
+        );
+        assert_eq!(
+            eval("string.substring(\"hello\", 3, 99)").unwrap(),
+            string("lo")
+        );
+        assert_eq!(
+            eval("string.pad_left(\"7\", 3, \"0\")").unwrap(),
+            string("007")
+        );
+        assert_eq!(eval("string.pad_right(\"ab\", 4)").unwrap(), string("ab  "));
+        assert_eq!(eval("string.repeat(\"ab\", 3)").unwrap(), string("ababab"));
+        assert_eq!(eval("string.ord(\"A\")").unwrap(), Value::Num(65.0));
+        assert_eq!(eval("string.chr(233)").unwrap(), string("é"));
+        assert_eq!(
+            eval("string.replace(\"a-b-c\", \"-\", \"+\")").unwrap(),
+            string("a+b+c")
+        );
+        assert_eq!(
+            eval("string.starts_with(\"vari\", \"va\") and string.ends_with(\"vari\", \"ri\")")
+                .unwrap(),
+            Value::Bool(true)
+        );
+
+        // strings compare by code point
+        assert_eq!(eval("\"apple\" < \"banana\"").unwrap(), Value::Bool(true));
+        assert_eq!(eval("\"b\" >= \"ab\"").unwrap(), Value::Bool(true));
+        assert!(matches!(eval("\"a\" < 1"), Err(Error::Runtime { .. })));
+
+        // lists are shared and can be indexed from either end
+        assert_eq!(
+            eval("let xs = string.chars(\"abc\"); let ys = xs; ys[0] = \"z\"; xs[0] + xs[-1]")
+                .unwrap(),
+            string("zc")
+        );
+        assert_eq!(eval("\"héllo\"[1]").unwrap(), string("é"));
+        assert_eq!(
+            eval("[1, 2] + [3] == [1, 2, 3]").unwrap(),
+            Value::Bool(true)
+        );
+        assert!(matches!(eval("xs[3]"), Err(Error::Runtime { .. })));
+
+        // lists that contain themselves can be compared and handed out
+        assert_eq!(
+            eval("let a = [1]; a[0] = a; let b = [1]; b[0] = b; [a == b, a == [a], a == [1]]")
+                .unwrap(),
+            Value::List(vec![
+                Value::Bool(true),
+                Value::Bool(true),
+                Value::Bool(false)
+            ])
+        );
+        assert_eq!(eval("a").unwrap(), Value::List(vec![string("<cycle>")]));
+        assert_eq!(eval("count(a, b)").unwrap(), Value::Num(2.0));
+        assert!(matches!(
+            eval("string.ord(\"ab\")"),
+            Err(Error::Runtime { .. })
+        ));
+        assert!(matches!(
+            eval("string.upper(1)"),
+            Err(Error::Runtime { .. })
+        ));
+    }
+
+    #[test]
+    pub fn conversion_test() {
+        use crate::{Engine, Error, Value};
+
+        let mut engine = Engine::new();
+        let mut eval = |source: &str| engine.eval(source);
+        let string = |s: &str| Value::String(s.to_owned());
+
+        let types =
+            "[type(1), type(\"a\"), type(true), type(nil), type({a: 1}), type([]), type(len)]";
+        assert_eq!(
+            eval(types).unwrap(),
+            Value::List(
+                ["num", "string", "bool", "nil", "struct", "list", "function"]
+                    .into_iter()
+                    .map(string)
+                    .collect()
+            )
+        );
+
+        assert_eq!(eval("num(\" 42 \") + 1").unwrap(), Value::Num(43.0));
+        assert_eq!(eval("num(true)").unwrap(), Value::Num(1.0));
+        match eval("num(\"4x\")") {
+            Err(Error::Runtime { msg, .. }) => assert_eq!(msg, "Can't convert \"4x\" to a number."),
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+        assert!(matches!(eval("num(nil)"), Err(Error::Runtime { .. })));
+
+        assert_eq!(eval("str(1.5) + str(nil)").unwrap(), string("1.5nil"));
+        assert_eq!(
+            eval("[bool(0), bool(nil), bool(\"\")]").unwrap(),
+            Value::List(vec![
+                Value::Bool(true),
+                Value::Bool(false),
+                Value::Bool(true),
+            ])
+        );
+
+        assert_eq!(eval("fn f(a, b) {} arity(f)").unwrap(), Value::Num(2.0));
+        assert_eq!(eval("arity(math.log)").unwrap(), Value::Num(1.0));
+        assert_eq!(
+            eval("fields({b: 1, a: 2})").unwrap(),
+            Value::List(vec![string("b"), string("a")])
+        );
+        assert!(matches!(eval("fields(1)"), Err(Error::Runtime { .. })));
+    }
+
+    #[test]
+    pub fn fs_test() {
+        use crate::{Capability, Engine, Error, Value};
+
+        // scripts only get `fs` when the host grants it
+        assert!(matches!(
+            Engine::new().eval("fs.exists(\".\")"),
+            Err(Error::Runtime { .. })
+        ));
+
+        let dir = std::env::temp_dir().join(format!("vari_fs_test_{}", std::process::id()));
+        let dir = dir.to_str().unwrap().to_owned();
+        let mut engine = Engine::with_capabilities(&[Capability::FileSystem]);
+        engine.set_global("dir", Value::String(dir.clone()));
+        let mut eval = |source: &str| engine.eval(source);
+
+        eval("fs.mkdir(dir + \"/sub\"); let path = dir + \"/a.txt\";").unwrap();
+        eval("fs.write_file(path, \"one\n\"); fs.append_file(path, \"two\n\");").unwrap();
+        assert_eq!(
+            eval("fs.read_file(path)").unwrap(),
+            Value::String("one\ntwo\n".to_owned())
+        );
+        assert_eq!(
+            eval("fs.read_lines(path)").unwrap(),
+            Value::List(vec!["one".into(), "two".into()])
+        );
+
+        eval("let out = fs.open(dir + \"/b.txt\", \"w\"); out.write(\"x\ny\"); out.close();")
+            .unwrap();
+        eval("let file = fs.open(dir + \"/b.txt\");").unwrap();
+        assert_eq!(
+            eval("[file.read_line(), file.read_line(), file.read_line()]").unwrap(),
+            Value::List(vec!["x".into(), "y".into(), Value::Nil])
+        );
+        eval("file.close();").unwrap();
+        assert!(matches!(
+            eval("file.read_line()"),
+            Err(Error::Runtime { .. })
+        ));
+
+        eval("fs.rename(dir + \"/b.txt\", dir + \"/c.txt\");").unwrap();
+        assert_eq!(
+            eval("fs.list_dir(dir)").unwrap(),
+            Value::List(vec!["a.txt".into(), "c.txt".into(), "sub".into()])
+        );
+
+        // failures name the function and path, and `try` catches them
+        let missing = format!("'fs.read_file' failed on '{}/missing.txt'", dir);
+        match eval("fs.read_file(dir + \"/missing.txt\")") {
+            Err(Error::Runtime { msg, .. }) => assert!(msg.starts_with(&missing), "{}", msg),
+            other => panic!("expected an I/O error, got {:?}", other),
+        }
+        assert_eq!(
+            eval("let r = try(fs.read_file, dir + \"/missing.txt\"); r.ok").unwrap(),
+            Value::Bool(false)
+        );
+        assert_eq!(
+            eval("try(fs.exists, path).value").unwrap(),
+            Value::Bool(true)
+        );
+
+        for name in ["a.txt", "c.txt", "sub"] {
+            eval(&format!("fs.remove(dir + \"/{}\");", name)).unwrap();
+        }
+        eval("fs.remove(dir);").unwrap();
+        assert_eq!(eval("fs.exists(dir)").unwrap(), Value::Bool(false));
+    }
+
+    #[test]
+    pub fn script_io_test() {
+        use crate::vari::Vari;
+        use crate::{Engine, Error, Value};
+        use std::io::Cursor;
+
+        let output = Rc::new(RefCell::new(vec![]));
+        let mut vari = Vari::new();
+        vari.interpreter
+            .set_output(Box::new(Output(Rc::clone(&output))));
+        vari.interpreter
+            .set_input(Box::new(Cursor::new("Ada\r\nrest\nof it\n")));
+        vari.interpreter
+            .set_args(&["-n".to_owned(), "2".to_owned()]);
+
+        vari.run(
+            "
+print args;
+let name = input(\"name? \");
+print \"hello \" + name;
+print string.split(read_all_stdin());
+print input();
+exit(num(args[1]) + 1);
+print \"not reached\";",
+        );
+        assert_eq!(vari.status(), 3);
+        assert_eq!(
+            String::from_utf8_lossy(&output.borrow()),
+            "[\"-n\", \"2\"]\nname? hello Ada\n[\"rest\", \"of\", \"it\"]\nnil\n"
+        );
+
+        // hosts see the exit code, `try` doesn't catch it
+        let mut engine = Engine::new();
+        assert_eq!(engine.eval("args").unwrap(), Value::List(vec![]));
+        assert!(matches!(engine.eval("exit()"), Err(Error::Exit(0))));
+        assert!(matches!(engine.eval("try(exit, 4)"), Err(Error::Exit(4))));
+        assert!(matches!(
+            engine.eval("exit(1.5)"),
+            Err(Error::Runtime { .. })
+        ));
+    }
+
+    #[test]
+    pub fn os_test() {
+        use crate::{Capability, Engine, Error, Value};
+
+        assert!(matches!(
+            Engine::with_capabilities(&[Capability::FileSystem]).eval("os.pid()"),
+            Err(Error::Runtime { .. })
+        ));
+
+        let mut engine = Engine::with_capabilities(&[Capability::Process]);
+        let mut eval = |source: &str| engine.eval(source);
+
+        assert_eq!(
+            eval("os.pid()").unwrap(),
+            Value::Num(std::process::id() as f64)
+        );
+        assert_eq!(
+            eval("os.platform()").unwrap(),
+            Value::String(std::env::consts::OS.to_owned())
+        );
+        assert_eq!(
+            eval("os.cwd()").unwrap(),
+            Value::String(std::env::current_dir().unwrap().display().to_string())
+        );
+
+        assert_eq!(eval("os.env_get(\"VARI_OS_TEST\")").unwrap(), Value::Nil);
+        eval("os.env_set(\"VARI_OS_TEST\", \"on\");").unwrap();
+        assert_eq!(
+            eval("os.env_get(\"VARI_OS_TEST\") + os.env_vars().VARI_OS_TEST").unwrap(),
+            Value::String("onon".to_owned())
+        );
+        assert!(matches!(
+            eval("os.env_set(\"A=B\", \"x\")"),
+            Err(Error::Runtime { .. })
+        ));
+
+        if cfg!(unix) {
+            eval("let r = os.run(\"sh\", [\"-c\", \"echo out; echo err >&2; exit 3\"]);").unwrap();
+            assert_eq!(
+                eval("[r.stdout, r.stderr, r.status]").unwrap(),
+                Value::List(vec!["out\n".into(), "err\n".into(), Value::Num(3.0)])
+            );
+        }
+        assert!(matches!(
+            eval("os.run(\"vari-no-such-command\")"),
+            Err(Error::Runtime { .. })
+        ));
+    }
+
+    #[test]
+    pub fn json_test() {
+        use crate::{Engine, Error, Value};
+
+        let mut engine = Engine::new();
+        engine.set_global(
+            "text",
+            Value::String(r#"{"name": "vari", "tags": ["a", 1.5, true, null], "n": {}}"#.into()),
+        );
+        let mut eval = |source: &str| engine.eval(source);
+
+        assert_eq!(
+            eval("let doc = json.parse(text); [doc.name, doc.tags[1], doc.tags[3]]").unwrap(),
+            Value::List(vec!["vari".into(), Value::Num(1.5), Value::Nil])
+        );
+        assert_eq!(
+            eval("json.stringify(doc)").unwrap(),
+            Value::String(r#"{"name":"vari","tags":["a",1.5,true,null],"n":{}}"#.into())
+        );
+        assert_eq!(
+            eval("json.stringify([1, [\"x\"]], 2)").unwrap(),
+            Value::String("[\n  1,\n  [\n    \"x\"\n  ]\n]".into())
+        );
+
+        let error = |result: Result<Value, Error>| match result {
+            Err(Error::Runtime { msg, .. }) => msg,
+            other => panic!("expected a runtime error, got {:?}", other),
+        };
+        assert_eq!(
+            error(eval("json.parse(\"[1,\n 2 x]\")")),
+            "'json.parse' failed: Expected ',' or ']' in array at line 2 column 4."
+        );
+        assert_eq!(
+            error(eval("json.stringify([clock])")),
+            "'json.stringify' can't write a function, JSON has no functions."
+        );
+        assert_eq!(
+            error(eval("let a = [1]; a[0] = [a]; json.stringify(a)")),
+            "'json.stringify' can't write a list that contains itself."
+        );
+        assert_eq!(
+            error(eval("json.stringify({x: [0 / 0]})")),
+            "'json.stringify' can't write nan, JSON only has finite numbers."
+        );
+        assert_eq!(
+            error(eval("json.stringify(-1 / 0)")),
+            "'json.stringify' can't write -inf, JSON only has finite numbers."
+        );
+        // the same list twice isn't a cycle
+        assert_eq!(
+            eval("let b = [1]; json.stringify([b, b])").unwrap(),
+            Value::String("[[1],[1]]".into())
+        );
+        assert!(error(eval("json.parse(\"[\" + string.repeat(\"[\", 600))"))
+            .contains("Nested too deeply"));
+    }
+
+    #[test]
+    pub fn higher_order_test() {
+        use crate::{Engine, Error, Value};
+
+        let mut engine = Engine::new();
+        let mut eval = |source: &str| engine.eval(source);
+        let nums = |items: &[f64]| Value::List(items.iter().map(|&n| Value::Num(n)).collect());
+
+        eval(
+            "fn double(n) { return n * 2; }
+             fn odd(n) { return n - 2 * math.floor(n / 2) == 1; }
+             fn add(a, b) { return a + b; }
+             fn desc(a, b) { return b - a; }
+             fn first(pair) { return pair[0]; }
+             let xs = [3, 1, 2];",
+        )
+        .unwrap();
+
+        assert_eq!(eval("map(xs, double)").unwrap(), nums(&[6.0, 2.0, 4.0]));
+        assert_eq!(eval("filter(xs, odd)").unwrap(), nums(&[3.0, 1.0]));
+        assert_eq!(eval("reduce(xs, add)").unwrap(), Value::Num(6.0));
+        assert_eq!(eval("reduce([], add, 10)").unwrap(), Value::Num(10.0));
+        assert_eq!(
+            eval("[any(xs, odd), all(xs, odd), any([nil, false]), all([])]").unwrap(),
+            Value::List(vec![true.into(), false.into(), false.into(), true.into()])
+        );
+        assert_eq!(eval("find(xs, odd)").unwrap(), Value::Num(3.0));
+        assert_eq!(eval("find([2], odd)").unwrap(), Value::Nil);
+        assert_eq!(
+            eval("zip(xs, [\"a\", \"b\"])").unwrap(),
+            Value::List(vec![
+                Value::List(vec![Value::Num(3.0), "a".into()]),
+                Value::List(vec![Value::Num(1.0), "b".into()]),
+            ])
+        );
+        assert_eq!(
+            eval("enumerate([\"a\"])").unwrap(),
+            Value::List(vec![Value::List(vec![Value::Num(0.0), "a".into()])])
+        );
+        assert_eq!(
+            eval("let total = 0; fn count(n) { total = total + n; } each(xs, count); total")
+                .unwrap(),
+            Value::Num(6.0)
+        );
+
+        // sorting makes a new list and is stable
+        assert_eq!(eval("sort(xs)").unwrap(), nums(&[1.0, 2.0, 3.0]));
+        assert_eq!(eval("xs").unwrap(), nums(&[3.0, 1.0, 2.0]));
+        assert_eq!(eval("sort(xs, desc)").unwrap(), nums(&[3.0, 2.0, 1.0]));
+        assert_eq!(
+            eval("map(sort([[2, \"a\"], [1, \"b\"], [2, \"c\"], [1, \"d\"]], first), str)")
+                .unwrap(),
+            Value::List(vec![
+                "[1, \"b\"]".into(),
+                "[1, \"d\"]".into(),
+                "[2, \"a\"]".into(),
+                "[2, \"c\"]".into(),
+            ])
+        );
+
+        let error = |result: Result<Value, Error>| match result {
+            Err(Error::Runtime { msg, .. }) => msg,
+            other => panic!("expected a runtime error, got {:?}", other),
+        };
+        let mut eval = |source: &str| engine.eval(source);
+        assert_eq!(
+            error(eval("sort([1, \"a\"])")),
+            "'sort' can only order numbers or strings, got string and number."
+        );
+        assert_eq!(
+            error(eval("map(xs, add)")),
+            "'add' expects 2 arguments but got 1."
+        );
+        assert_eq!(
+            error(eval("reduce([], add)")),
+            "'reduce' needs an initial value for an empty list."
+        );
+    }
+
+    #[test]
+    pub fn time_test() {
+        use crate::{Engine, Error, Limits, Value};
+
+        let mut engine = Engine::new();
+        let mut eval = |source: &str| engine.eval(source);
+
+        eval("let dt = time.from_timestamp(951782400123.5);").unwrap();
+        assert_eq!(
+            eval("[dt.year, dt.month, dt.day, dt.hour, dt.nanos]").unwrap(),
+            Value::List(
+                [2000.0, 2.0, 29.0, 0.0, 123_500_000.0]
+                    .map(Value::Num)
+                    .to_vec()
+            )
+        );
+        assert_eq!(
+            eval("time.format(dt, \"%Y-%m-%d %H:%M:%S.%f 100%%\")").unwrap(),
+            Value::String("2000-02-29 00:00:00.123500000 100%".into())
+        );
+        assert_eq!(
+            eval("time.format(time.add(dt, -86400000), \"%d/%m\")").unwrap(),
+            Value::String("28/02".into())
+        );
+        assert_eq!(
+            eval("time.diff(time.parse(\"2000-03-01 12:30:00.5\", \"%Y-%m-%d %H:%M:%S.%f\"), dt)")
+                .unwrap(),
+            Value::Num(131_400_376.5)
+        );
+        assert_eq!(
+            eval("time.timestamp(time.parse(\"1969-12-31\", \"%Y-%m-%d\"))").unwrap(),
+            Value::Num(-86_400_000.0)
+        );
+        assert_eq!(
+            eval("let now = time.now(); time.timestamp(now) <= clock() + 1").unwrap(),
+            Value::Bool(true)
+        );
+        assert_eq!(
+            eval("let t = time.perf_counter(); time.sleep(5); time.perf_counter() - t >= 5")
+                .unwrap(),
+            Value::Bool(true)
+        );
+
+        let error = |result: Result<Value, Error>| match result {
+            Err(Error::Runtime { msg, .. }) => msg,
+            other => panic!("expected a runtime error, got {:?}", other),
+        };
+        let mut eval = |source: &str| engine.eval(source);
+        assert_eq!(
+            error(eval("time.parse(\"2001-2-03\", \"%Y-%m-%d\")")),
+            "'time.parse' couldn't read \"2001-2-03\" as \"%Y-%m-%d\", expected 2 digits for %m at character 6."
+        );
+        assert_eq!(
+            error(eval("time.parse(\"2001-02-29\", \"%Y-%m-%d\")")),
+            "'time.parse' got day 29, which should be from 1 to 28."
+        );
+        assert_eq!(
+            error(eval("time.format(dt, \"%q\")")),
+            "'time.format' doesn't know \"%q\"."
+        );
+
+        // sleeping stops at the time limit
+        engine.set_limits(Limits {
+            max_time: Some(std::time::Duration::from_millis(20)),
+            ..Limits::default()
+        });
+        assert!(matches!(
+            engine.eval("time.sleep(60000);"),
+            Err(Error::Limit { .. })
+        ));
+    }
+
+    #[test]
+    pub fn random_test() {
+        use crate::{Engine, Error, Value};
+
+        let mut engine = Engine::new();
+        let mut eval = |source: &str| engine.eval(source);
+
+        // the same seed gives the same numbers on every platform
+        let draws = "random.seed(42); [random.random(), random.randint(1, 6)]";
+        let expected = Value::List(vec![Value::Num(0.08386297105988216), Value::Num(1.0)]);
+        assert_eq!(eval(draws).unwrap(), expected);
+        assert_eq!(eval(draws).unwrap(), expected);
+
+        eval("random.seed(7); let xs = [1, 2, 3, 4, 5]; random.shuffle(xs);").unwrap();
+        assert_eq!(eval("sort(xs)").unwrap(), eval("[1, 2, 3, 4, 5]").unwrap());
+        assert_eq!(
+            eval("let c = random.choice(xs); c >= 1 and c <= 5").unwrap(),
+            Value::Bool(true)
+        );
+        assert_eq!(
+            eval(
+                "let ok = true;
+                 for (let i = 0; i < 200; i = i + 1) {
+                     let n = random.randint(-2, 2);
+                     let r = random.random();
+                     ok = ok and n >= -2 and n <= 2 and n == math.floor(n) and r >= 0 and r < 1;
+                 }
+                 ok"
+            )
+            .unwrap(),
+            Value::Bool(true)
+        );
+
+        eval(
+            "let total = 0;
+             for (let i = 0; i < 1000; i = i + 1) { total = total + random.gauss(10, 2); }",
+        )
+        .unwrap();
+        let mean = match eval("total / 1000").unwrap() {
+            Value::Num(mean) => mean,
+            other => panic!("expected a number, got {:?}", other),
+        };
+        assert!(
+            (mean - 10.0).abs() < 0.5,
+            "mean of gauss(10, 2) was {}",
+            mean
+        );
+
+        assert!(matches!(
+            eval("random.choice([])"),
+            Err(Error::Runtime { .. })
+        ));
+        assert!(matches!(
+            eval("random.randint(3, 1)"),
+            Err(Error::Runtime { .. })
+        ));
+    }
+
+    #[test]
+    pub fn regex_test() {
+        use crate::{Engine, Error, Value};
+
+        let mut engine = Engine::new();
+        engine.set_global("long", Value::String("a".repeat(5000)));
+        let mut eval = |source: &str| engine.eval(source);
+        let strings = |items: &[&str]| Value::List(items.iter().map(|&s| s.into()).collect());
+
+        eval(r#"let m = regex.search("(\d+)-(?P<month>\d\d)(-x)?", "on 2024-05-17");"#).unwrap();
+        assert_eq!(
+            eval("[m.text, m.start, m.end, m.named.month]").unwrap(),
+            Value::List(vec![
+                "2024-05".into(),
+                Value::Num(3.0),
+                Value::Num(10.0),
+                "05".into()
+            ])
+        );
+        assert_eq!(
+            eval("m.groups").unwrap(),
+            Value::List(vec!["2024".into(), "05".into(), Value::Nil])
+        );
+        assert_eq!(eval(r#"regex.match("\d", "a1")"#).unwrap(), Value::Nil);
+        // leftmost-first, like Perl and Python
+        assert_eq!(
+            eval("[regex.match(\"a|ab\", \"abc\").text, regex.search(\"a.*?b\", \"aXbYb\").text]")
+                .unwrap(),
+            strings(&["a", "aXb"])
+        );
+        assert_eq!(
+            eval("fn text(m) { return m.text; } map(regex.find_all(\"a*\", \"baaa\"), text)")
+                .unwrap(),
+            strings(&["", "aaa", ""])
+        );
+        assert_eq!(
+            eval(r#"regex.replace("(\w+)@(?<host>\w+)", "joe@site, ann@web", "${host}:$1 $$")"#)
+                .unwrap(),
+            Value::String("site:joe $, web:ann $".into())
+        );
+        assert_eq!(
+            eval("fn up(m) { return string.upper(m.text); } regex.replace(\"[aeiou]\", \"banana\", up, 2)")
+                .unwrap(),
+            Value::String("bAnAna".into())
+        );
+        // no backtracking blow up
+        assert_eq!(
+            eval("regex.search(\"(a|a)*(a*)*b\", long)").unwrap(),
+            Value::Nil
+        );
+
+        let error = |result: Result<Value, Error>| match result {
+            Err(Error::Runtime { msg, .. }) => msg,
+            other => panic!("expected a runtime error, got {:?}", other),
+        };
+        let mut eval = |source: &str| engine.eval(source);
+        assert_eq!(
+            error(eval("regex.search(\"a(b[c\", \"\")")),
+            "'regex.search' couldn't compile \"a(b[c\": unclosed character class at character 4."
+        );
+        assert_eq!(
+            error(eval("regex.search(\"x|*\", \"\")")),
+            "'regex.search' couldn't compile \"x|*\": nothing to repeat at character 3."
+        );
+        assert_eq!(
+            error(eval("regex.replace(\"a\", \"a\", \"$1\")")),
+            "'regex.replace' can't use the replacement \"$1\": there's no group 1."
+        );
+
+        // nesting is bounded, whether by groups or stacked repetitions
+        engine.set_global(
+            "groups",
+            format!("{}a{}", "(".repeat(100), ")".repeat(100)).into(),
+        );
+        assert_eq!(
+            engine.eval("regex.match(groups, \"ab\").text").unwrap(),
+            "a".into()
+        );
+        let deep = [
+            format!("{}a{}", "(".repeat(10_000), ")".repeat(10_000)),
+            format!("a{}", "{1}".repeat(10_000)),
+            format!("{}a{}", "(?:a|".repeat(10_000), ")".repeat(10_000)),
+        ];
+        for pattern in deep {
+            engine.set_global("pattern", pattern.into());
+            let msg = error(engine.eval("regex.search(pattern, \"a\")"));
+            assert!(msg.contains("\": pattern nested too deeply at character "));
+        }
+    }
+
+    #[test]
+    pub fn format_test() {
+        use crate::{Engine, Error, Value};
+
+        let mut engine = Engine::new();
+        let mut eval = |source: &str| engine.eval(source);
+        let mut format = |source: &str| match eval(source) {
+            Ok(Value::String(s)) => s,
+            other => panic!("expected a string, got {:?}", other),
+        };
+
+        assert_eq!(
+            format(r#"format("[{:>8.2}] [{}]", 3.14159, "vari")"#),
+            "[    3.14] [vari]"
+        );
+        assert_eq!(format(r#"format("{1}{0}{}", "a", "b")"#), "baa");
+        assert_eq!(
+            format(r#"format("{name} is {age:03}", {name: "Ann", age: 7})"#),
+            "Ann is 007"
+        );
+        assert_eq!(
+            format(r#"format("[{:*^9}] [{:<5}] [{:+}] [{:.3}]", "mid", true, 3, "truncate")"#),
+            "[***mid***] [true ] [+3] [tru]"
+        );
+        assert_eq!(
+            format(r#"format("{:x} {:#X} {:#b} {:o} {:#08x}", 255, 255, 5, 8, -255)"#),
+            "ff 0xFF 0b101 10 -0x000ff"
+        );
+        assert_eq!(
+            format(r#"format("{:e} {:.2E} {{}}", 123456, 0.000123)"#),
+            "1.23456e5 1.23E-4 {}"
+        );
+        assert_eq!(
+            format(r#"fn show() { return "<point>"; } format("{:>8}", {to_string: show})"#),
+            " <point>"
+        );
+
+        let error = |result: Result<Value, Error>| match result {
+            Err(Error::Runtime { msg, .. }) => msg,
+            other => panic!("expected a runtime error, got {:?}", other),
+        };
+        let mut eval = |source: &str| engine.eval(source);
+        assert_eq!(
+            error(eval(r#"format("{:x}", 1.5)"#)),
+            "'format' can only use 'x' with whole numbers, got 1.5."
+        );
+        assert_eq!(
+            error(eval(r#"format("{} {}", 1)"#)),
+            "'format' has no argument 1, it got 1."
+        );
+        assert_eq!(
+            error(eval(r#"format("ab{:q}", 1)"#)),
+            "'format' can't read the spec \"q\"."
+        );
+    }
+
+    #[test]
+    pub fn display_test() {
+        use crate::{Engine, Error, Value};
+
+        let output = Rc::new(RefCell::new(vec![]));
+        let mut engine = Engine::new();
+        engine.set_output(Output(Rc::clone(&output)));
+
+        engine
+            .eval(
+                r#"print {x: 1, y: "a", z: [true, nil]};
+                   print {};
+                   print [math.pow(10, 21), math.pow(10, 20), 0.0000001, 0.000001, -0, 2.5];
+                   let a = [1, "b"]; a[0] = a; print a;
+                   let b = [1]; print [b, b];
+                   print [clock];
+                   print 1 / 0; print -1 / 0; print 0 / 0;"#,
+            )
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(output.borrow().clone()).unwrap(),
+            "{ x: 1, y: \"a\", z: [true, nil] }\n\
+             {}\n\
+             [1e21, 100000000000000000000, 1e-7, 0.000001, 0, 2.5]\n\
+             [<cycle>, \"b\"]\n\
+             [[1], [1]]\n\
+             [<native fn clock>]\n\
+             inf\n\
+             -inf\n\
+             nan\n"
+        );
+
+        assert_eq!(
+            engine
+                .eval(r#"[str("a"), repr("a"), repr(1), repr({s: "x"})]"#)
+                .unwrap(),
+            Value::List(vec![
+                "a".into(),
+                "\"a\"".into(),
+                "1".into(),
+                "{ s: \"x\" }".into()
+            ])
+        );
+        assert_eq!(
+            engine
+                .eval(r#"[repr(0 / 0), repr([-1 / 0]), format("{:.2} {:e}", 0 / 0, 1 / 0)]"#)
+                .unwrap(),
+            Value::List(vec!["nan".into(), "[-inf]".into(), "nan inf".into()])
+        );
+
+        // error messages spell them the same way
+        let errors = [
+            (
+                "math.clamp(1, 0 / 0, 1 / 0)",
+                "'math.clamp' expects min to be no greater than max, got nan and inf.",
+            ),
+            (
+                r#"format("{:x}", -1 / 0)"#,
+                "'format' can only use 'x' with whole numbers, got -inf.",
+            ),
+            (
+                "[1][math.pow(10, 300)]",
+                "Index 1e300 is out of range for length 1.",
+            ),
+        ];
+        for (source, expected) in errors {
+            match engine.eval(source) {
+                Err(Error::Runtime { msg, .. }) => assert_eq!(msg, expected),
+                other => panic!("expected a runtime error, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    pub fn struct_order_test() {
+        use crate::{Engine, Error, Value};
+
+        let mut engine = Engine::new();
+        assert_eq!(
+            engine
+                .eval("let p = {z: 1, a: 2, m: {y: 3, b: 4}}; str(p)")
+                .unwrap(),
+            Value::String("{ z: 1, a: 2, m: { y: 3, b: 4 } }".into())
+        );
+        assert_eq!(
+            engine.eval("json.stringify(p)").unwrap(),
+            Value::String(r#"{"z":1,"a":2,"m":{"y":3,"b":4}}"#.into())
+        );
+        assert_eq!(
+            engine
+                .eval("fields(json.parse(json.stringify(p)))")
+                .unwrap(),
+            Value::List(vec!["z".into(), "a".into(), "m".into()])
+        );
+
+        // the host sees the same order
+        match engine.eval("p").unwrap() {
+            Value::Struct(fields) => {
+                let names: Vec<&String> = fields.keys().collect();
+                assert_eq!(names, ["z", "a", "m"]);
+            }
+            other => panic!("expected a struct, got {:?}", other),
+        }
+
+        match engine.eval("let q = {x: 1, y: 2, x: 3};") {
+            Err(Error::Syntax(errors)) => {
+                assert_eq!(errors[0].msg, "at 'x': Duplicate field 'x' in struct.");
+            }
+            other => panic!("expected a syntax error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    pub fn named_struct_test() {
+        use crate::{Engine, Error, Value};
+
+        let mut engine = Engine::new();
+        engine
+            .eval("let scale = 2; struct Point { x, y = scale * 5 }")
+            .unwrap();
+        assert_eq!(
+            engine
+                .eval("[str(Point(1)), str(Point { y: 3, x: 4 }), type(Point(1))]")
+                .unwrap(),
+            Value::List(vec![
+                "Point { x: 1, y: 10 }".into(),
+                "Point { x: 4, y: 3 }".into(),
+                "Point".into(),
+            ])
+        );
+        assert_eq!(
+            engine
+                .eval("[Point(1, 2) is Point, {x: 1, y: 2} is Point, arity(Point)]")
+                .unwrap(),
+            Value::List(vec![Value::Bool(true), Value::Bool(false), Value::Num(1.0)])
+        );
+        assert_eq!(
+            engine
+                .eval("[Point(1) == Point { y: 10, x: 1 }, Point(1) == Point(2), Point(1) == {x: 1, y: 10}]")
+                .unwrap(),
+            Value::List(vec![Value::Bool(true), Value::Bool(false), Value::Bool(false)])
+        );
+        // setting a field changes the struct where it's kept
+        assert_eq!(
+            engine
+                .eval(
+                    "let q = Point(1, 2); q.y = 9; let s = {a: 1}; s.a = 2;
+                     let box = {p: Point(1)}; box.p.x = 5;
+                     let ps = [Point(1)]; ps[0].y = 7;
+                     [q.y, s.a, box.p.x, ps[0].y]"
+                )
+                .unwrap(),
+            Value::List(vec![
+                Value::Num(9.0),
+                Value::Num(2.0),
+                Value::Num(5.0),
+                Value::Num(7.0)
+            ])
+        );
+        // and gives the value it set, like assigning to a variable does
+        assert_eq!(
+            engine
+                .eval("let r = Point(1); let t = {a: 1}; [r.x = 4, t.a = t.a + 1, t.b = r.x = 6, r.x]")
+                .unwrap(),
+            Value::List(vec![
+                Value::Num(4.0),
+                Value::Num(2.0),
+                Value::Num(6.0),
+                Value::Num(6.0)
+            ])
+        );
+        // a brace after a name in a condition still opens a block
+        assert_eq!(
+            engine.eval("let n = 0; if (n == 0) { n = 1; } n").unwrap(),
+            Value::Num(1.0)
+        );
+
+        let error = |engine: &mut Engine, source: &str| match engine.eval(source) {
+            Err(Error::Runtime { msg, .. }) => msg,
+            other => panic!("expected a runtime error, got {:?}", other),
+        };
+        assert_eq!(
+            error(&mut engine, "Point { x: 1, z: 2 };"),
+            "Point has no field 'z'."
+        );
+        assert_eq!(
+            error(&mut engine, "Point { y: 1 };"),
+            "Point needs a value for 'x'."
+        );
+        assert_eq!(
+            error(&mut engine, "let p = Point(1); p.z = 3;"),
+            "Point has no field 'z'."
+        );
+        assert_eq!(
+            error(&mut engine, "Point(1).x = 3;"),
+            "Only fields of variables, fields and list items can be assigned to."
+        );
+        assert_eq!(
+            error(&mut engine, "Point(1) is 1"),
+            "Right side of 'is' must be a struct type."
+        );
+        assert_eq!(
+            error(&mut engine, "Point(1, 2, 3)"),
+            "Expected 1 to 2 arguments but got 3."
+        );
+        assert!(matches!(
+            engine.eval("struct Bad { a, a }"),
+            Err(Error::Syntax(_))
+        ));
+    }
+
+    #[test]
+    pub fn match_test() {
+        use crate::{Engine, Error, Value};
+
+        let mut engine = Engine::new();
+        engine
+            .eval(
+                r#"
+                struct Point { x, y = 0 }
+                fn describe(v) {
+                    return match (v) {
+                        1 => "one",
+                        "a" | "b" => "a or b",
+                        -2 => "minus two",
+                        Point {x: 0, y} => "point at " + str(y),
+                        {x: 0, y} => "struct at " + str(y),
+                        [] => "empty",
+                        [first, ..rest] => str(first) + " then " + str(rest),
+                        nil | true => "nil or true",
+                        n if type(n) == "num" and n > 10 => "big",
+                        _ => "other",
+                    };
+                }
+                "#,
+            )
+            .unwrap();
+
+        let cases = [
+            ("1", "one"),
+            ("\"b\"", "a or b"),
+            ("-2", "minus two"),
+            ("Point(0, 4)", "point at 4"),
+            ("{x: 0, y: 7, z: 1}", "struct at 7"),
+            ("{x: 1, y: 7}", "other"),
+            ("[]", "empty"),
+            ("[1, 2, 3]", "1 then [2, 3]"),
+            ("nil", "nil or true"),
+            ("42", "big"),
+            ("3", "other"),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(
+                engine.eval(&format!("describe({})", value)).unwrap(),
+                Value::String(expected.into()),
+                "describe({})",
+                value
+            );
+        }
+
+        // bindings stay inside their arm and the rest can sit in the middle
+        assert_eq!(
+            engine
+                .eval("let a = 0; [match ([1, 2, 3, 4]) { [a, .., z] => a + z }, a]")
+                .unwrap(),
+            Value::List(vec![Value::Num(5.0), Value::Num(0.0)])
+        );
+        // `match` is a keyword but still works as a property name
+        assert_eq!(
+            engine.eval(r#"regex.match("a+", "aab").text"#).unwrap(),
+            Value::String("aa".into())
+        );
+
+        match engine.eval("match (5) { 1 => 1 };") {
+            Err(Error::Runtime { msg, .. }) => assert_eq!(msg, "No arm of 'match' matches 5."),
+            other => panic!("expected a runtime error, got {:?}", other),
+        }
+        assert!(matches!(
+            engine.eval("match (1) { [.., ..] => 1 };"),
+            Err(Error::Syntax(_))
+        ));
+    }
 }
@@ -3,6 +3,7 @@ use std::fmt::{Display, Formatter};
 
 use crate::vari::VariTypes;
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum TokenType {
     // Single-character tokens.
@@ -10,8 +11,11 @@ pub enum TokenType {
     RPAREN,
     LBRACE,
     RBRACE,
+    LBRACKET,
+    RBRACKET,
     COMMA,
     DOT,
+    DOTDOT,
     MINUS,
     PLUS,
     SEMICOLON,
@@ -19,11 +23,13 @@ pub enum TokenType {
     SLASH,
     STAR,
     MODULO,
+    PIPE,
     // One or two character tokens.
     NOT,
     NE,
     EQUAL,
     ISEQ,
+    ARROW,
     GT,
     GE,
     LT,
@@ -40,14 +46,17 @@ pub enum TokenType {
     FUN,
     FOR,
     IF,
+    IS,
     NIL,
     OR,
     PRINT,
+    EPRINT,
     RETURN,
     SUPER,
     THIS,
     TRUE,
     LET,
+    MATCH,
     WHILE,
     EOF,
 }
@@ -63,27 +72,31 @@ pub struct Token {
     pub token_type: TokenType,
     pub lexeme: String,
     pub line: usize,
+    // zero based byte offset from the start of `line`
+    pub column: usize,
     pub literal: Option<Box<VariTypes>>,
 }
 
 impl Token {
-    pub fn new(t: TokenType, lex: String, lno: usize, lit: Option<Box<VariTypes>>) -> Self {
+    pub fn new(
+        t: TokenType,
+        lex: String,
+        lno: usize,
+        col: usize,
+        lit: Option<Box<VariTypes>>,
+    ) -> Self {
         Token {
             token_type: t,
             lexeme: lex,
             line: lno,
+            column: col,
             literal: lit,
         }
     }
 
     // used by tests
-    #[allow(dead_code)]
+    #[allow(dead_code, clippy::inherent_to_string, clippy::wrong_self_convention)]
     pub fn to_string(self) -> String {
-        format!(
-            "{} {} {:?}",
-            self.token_type.to_string(),
-            self.lexeme,
-            self.literal
-        )
+        format!("{} {} {:?}", self.token_type, self.lexeme, self.literal)
     }
 }
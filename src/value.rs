@@ -0,0 +1,161 @@
+use std::fmt;
+use std::rc::Rc;
+
+use crate::fields::Fields;
+use crate::procedure::{Arity, Procedure};
+use crate::sandbox::MAX_VALUE_DEPTH;
+use crate::vari::{Items, VariTypes};
+
+// A vari value as seen from Rust, returned by and passed into `Engine`.
+#[derive(Debug, Clone)]
+pub enum Value {
+    Nil,
+    Num(f64),
+    String(String),
+    Bool(bool),
+    Struct(Fields<Value>),
+    // a copy of the list, changing it doesn't change the script's list
+    List(Vec<Value>),
+    Function(Function),
+}
+
+impl Value {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Nil => "nil",
+            Value::Num(_) => "number",
+            Value::String(_) => "string",
+            Value::Bool(_) => "boolean",
+            Value::Struct(_) => "struct",
+            Value::List(_) => "list",
+            Value::Function(_) => "function",
+        }
+    }
+}
+
+// A function defined by a script or built into the interpreter. It
+// can be handed back to the engine but its body stays opaque.
+#[derive(Clone)]
+pub struct Function(pub(crate) Procedure);
+
+impl Function {
+    pub fn name(&self) -> &str {
+        self.0.name()
+    }
+
+    pub fn arity(&self) -> Arity {
+        self.0.arity()
+    }
+}
+
+impl fmt::Debug for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<fn {}>", self.name())
+    }
+}
+
+// functions never compare equal, like `==` in vari itself
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Nil, Value::Nil) => true,
+            (Value::Num(l), Value::Num(r)) => l == r,
+            (Value::String(l), Value::String(r)) => l == r,
+            (Value::Bool(l), Value::Bool(r)) => l == r,
+            (Value::Struct(l), Value::Struct(r)) => l == r,
+            (Value::List(l), Value::List(r)) => l == r,
+            _ => false,
+        }
+    }
+}
+
+impl From<VariTypes> for Value {
+    fn from(value: VariTypes) -> Self {
+        to_value(value, &mut vec![], 0)
+    }
+}
+
+// `open` holds the lists being converted, a list met again inside
+// itself becomes the string "<cycle>" and one nested too deep "...",
+// as they're shown when printed
+fn to_value(value: VariTypes, open: &mut Vec<*const Items>, depth: usize) -> Value {
+    match value {
+        VariTypes::Struct(_) | VariTypes::Instance(..) | VariTypes::List(_)
+            if depth >= MAX_VALUE_DEPTH =>
+        {
+            Value::String("...".to_owned())
+        }
+        VariTypes::Nil => Value::Nil,
+        VariTypes::Num(n) => Value::Num(n),
+        VariTypes::String(s) => Value::String(s),
+        VariTypes::Boolean(b) => Value::Bool(b),
+        // the host sees an instance as a plain struct
+        VariTypes::Struct(fields) | VariTypes::Instance(_, fields) => Value::Struct(
+            fields
+                .into_iter()
+                .map(|(name, value)| (name, to_value(value, open, depth + 1)))
+                .collect(),
+        ),
+        VariTypes::List(items) => {
+            let ptr = Rc::as_ptr(&items);
+            if open.contains(&ptr) {
+                return Value::String("<cycle>".to_owned());
+            }
+
+            open.push(ptr);
+            let list = items
+                .borrow()
+                .iter()
+                .map(|item| to_value(item.clone(), open, depth + 1))
+                .collect();
+            open.pop();
+            Value::List(list)
+        }
+        VariTypes::Callable(procedure) => Value::Function(Function(procedure)),
+    }
+}
+
+impl From<Value> for VariTypes {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Nil => VariTypes::Nil,
+            Value::Num(n) => VariTypes::Num(n),
+            Value::String(s) => VariTypes::String(s),
+            Value::Bool(b) => VariTypes::Boolean(b),
+            Value::Struct(fields) => VariTypes::Struct(
+                fields
+                    .into_iter()
+                    .map(|(name, value)| (name, value.into()))
+                    .collect(),
+            ),
+            Value::List(items) => VariTypes::List(Rc::new(Items::new(
+                items.into_iter().map(VariTypes::from).collect(),
+            ))),
+            Value::Function(function) => VariTypes::Callable(function.0),
+        }
+    }
+}
+
+impl From<f64> for Value {
+    fn from(n: f64) -> Self {
+        Value::Num(n)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Bool(b)
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::String(s)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::String(s.to_owned())
+    }
+}
@@ -1,19 +1,54 @@
+use crate::fields::Fields;
 use crate::interpreter::Interpreter;
-use crate::procedure::Procedure;
+use crate::procedure::{Procedure, StructType};
+use crate::sandbox::{Limit, MAX_VALUE_DEPTH};
 use crate::{lexer::Lexer, parser::Parser};
 
-use std::collections::HashMap;
+use std::cell::RefCell;
+use std::fmt;
+use std::fs;
 use std::io;
-use std::{fs, io::Write};
+use std::mem;
+use std::ops::Deref;
+use std::rc::Rc;
 
-#[derive(Debug, Clone)]
 pub struct Vari {
     pub had_error: bool,
+    // set once the program calls `exit`
+    pub exit_code: Option<i32>,
     pub interpreter: Interpreter,
 }
 
 pub enum VariError {
     Return(VariTypes),
+    // line, message
+    RuntimeError(usize, String),
+    // line, and the limit from `Interpreter::set_limits` that was hit
+    LimitExceeded(usize, Limit),
+    // line, and the code `exit(code)` was called with, unwinds like
+    // `Return` but all the way out
+    Exit(usize, i32),
+}
+
+impl VariError {
+    // the line and message of an error that stopped the program,
+    // None for a `return` that reached the top level or an `exit`
+    pub fn report(self) -> Option<(usize, String)> {
+        match self {
+            VariError::Return(_) | VariError::Exit(..) => None,
+            VariError::RuntimeError(line, msg) => Some((line, msg)),
+            VariError::LimitExceeded(line, limit) => Some((line, limit.to_string())),
+        }
+    }
+}
+
+// an error found while scanning or parsing source code
+#[derive(Debug, Clone)]
+pub struct SyntaxError {
+    pub line: usize,
+    // zero based byte offset from the start of `line`
+    pub column: usize,
+    pub msg: String,
 }
 
 #[derive(Debug, Clone)]
@@ -22,29 +57,230 @@ pub enum VariTypes {
     Num(f64),
     String(String),
     Boolean(bool),
-    Struct(HashMap<String, VariTypes>),
+    Struct(Fields<VariTypes>),
+    // a struct built from a type declared with `struct`
+    Instance(Rc<StructType>, Fields<VariTypes>),
+    // shared, so changes made through one variable show in every other
+    List(Rc<Items>),
     Callable(Procedure),
 }
 
+// A list's items. Lists can nest as deep as a script likes, so
+// dropping one takes the lists in it apart in a loop instead of
+// letting each drop recurse into the next.
+#[derive(Debug, Default)]
+pub struct Items(RefCell<Vec<VariTypes>>);
+
+impl Items {
+    pub fn new(items: Vec<VariTypes>) -> Self {
+        Self(RefCell::new(items))
+    }
+}
+
+impl Deref for Items {
+    type Target = RefCell<Vec<VariTypes>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Drop for Items {
+    fn drop(&mut self) {
+        let mut pending = mem::take(self.0.get_mut());
+        while let Some(value) = pending.pop() {
+            match value {
+                // only the last reference empties the list
+                VariTypes::List(items) => {
+                    if let Ok(mut items) = Rc::try_unwrap(items) {
+                        pending.append(items.0.get_mut());
+                    }
+                }
+                VariTypes::Struct(fields) | VariTypes::Instance(_, fields) => {
+                    pending.extend(fields.into_iter().map(|(_, value)| value))
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl VariTypes {
+    // the name scripts see in type errors
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            VariTypes::Nil => "nil",
+            VariTypes::Num(_) => "number",
+            VariTypes::String(_) => "string",
+            VariTypes::Boolean(_) => "boolean",
+            VariTypes::Struct(_) | VariTypes::Instance(..) => "struct",
+            VariTypes::List(_) => "list",
+            VariTypes::Callable(_) => "function",
+        }
+    }
+
+    // the fields of a struct, whether or not it has a declared type
+    pub fn fields(&self) -> Option<&Fields<VariTypes>> {
+        match self {
+            VariTypes::Struct(fields) | VariTypes::Instance(_, fields) => Some(fields),
+            _ => None,
+        }
+    }
+
+    // like `to_string` but with strings quoted, the way they're shown
+    // inside lists and structs
+    pub fn repr(&self) -> String {
+        struct Repr<'a>(&'a VariTypes);
+
+        impl fmt::Display for Repr<'_> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write_value(f, self.0, true, &mut vec![], 0)
+            }
+        }
+
+        Repr(self).to_string()
+    }
+}
+
+// how `print` and `str` show values
+impl fmt::Display for VariTypes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_value(f, self, false, &mut vec![], 0)
+    }
+}
+
+// `open` holds the lists being written, a list met again inside
+// itself is shown as <cycle>, and one nested too deep as ...
+fn write_value(
+    f: &mut fmt::Formatter<'_>,
+    value: &VariTypes,
+    quoted: bool,
+    open: &mut Vec<*const Items>,
+    depth: usize,
+) -> fmt::Result {
+    match value {
+        VariTypes::Struct(_) | VariTypes::Instance(..) | VariTypes::List(_)
+            if depth >= MAX_VALUE_DEPTH =>
+        {
+            write!(f, "...")
+        }
+        VariTypes::Nil => write!(f, "nil"),
+        VariTypes::Num(n) => write!(f, "{}", format_number(*n)),
+        VariTypes::Boolean(b) => write!(f, "{}", b),
+        VariTypes::String(s) if quoted => write!(f, "{:?}", s),
+        VariTypes::String(s) => write!(f, "{}", s),
+        VariTypes::Struct(fields) => write_fields(f, fields, open, depth),
+        VariTypes::Instance(struct_type, fields) => {
+            write!(f, "{} ", struct_type.name())?;
+            write_fields(f, fields, open, depth)
+        }
+        VariTypes::List(items) => {
+            let ptr = Rc::as_ptr(items);
+            if open.contains(&ptr) {
+                return write!(f, "<cycle>");
+            }
+
+            open.push(ptr);
+            write!(f, "[")?;
+            for (i, item) in items.borrow().iter().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+                write_value(f, item, true, open, depth + 1)?;
+            }
+            open.pop();
+            write!(f, "]")
+        }
+        VariTypes::Callable(Procedure::User { name, .. }) => write!(f, "<fn {}>", name.lexeme),
+        VariTypes::Callable(Procedure::Native { name, .. }) => write!(f, "<native fn {}>", name),
+        VariTypes::Callable(Procedure::Struct(struct_type)) => {
+            write!(f, "<struct {}>", struct_type.name())
+        }
+    }
+}
+
+fn write_fields(
+    f: &mut fmt::Formatter<'_>,
+    fields: &Fields<VariTypes>,
+    open: &mut Vec<*const Items>,
+    depth: usize,
+) -> fmt::Result {
+    if fields.is_empty() {
+        return write!(f, "{{}}");
+    }
+
+    write!(f, "{{ ")?;
+    for (i, (name, value)) in fields.iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}: ", name)?;
+        write_value(f, value, true, open, depth + 1)?;
+    }
+    write!(f, " }}")
+}
+
+// Whole numbers have no fraction and very big or small ones use an
+// exponent, the same cut offs as JavaScript: 1e21 but 100000 and
+// 1e-7 but 0.000001. The rest are inf, -inf and nan.
+pub fn format_number(n: f64) -> String {
+    if n == 0.0 {
+        // -0 too
+        return "0".to_owned();
+    }
+    if n.is_nan() {
+        return "nan".to_owned();
+    }
+
+    let magnitude = n.abs();
+    if magnitude.is_finite() && !(1e-6..1e21).contains(&magnitude) {
+        return format!("{:e}", n);
+    }
+    n.to_string()
+}
+
 impl Vari {
-    fn report(&self, line: usize, location: &str, msg: &str) -> () {
+    pub fn new() -> Self {
+        Self {
+            had_error: false,
+            exit_code: None,
+            interpreter: Interpreter::new(),
+        }
+    }
+
+    fn report(&self, line: usize, location: &str, msg: &str) {
         println!("Error on line {}:  {} {}", line, location, msg);
     }
 
-    fn run(&mut self, source: &str) {
-        let mut lexer: Lexer = Lexer::new(source.to_owned(), (*self).clone());
+    pub fn run(&mut self, source: &str) {
+        let mut lexer: Lexer = Lexer::new(source.to_owned());
         let tokens = lexer.scan_tokens();
 
         let mut parser: Parser = Parser::new(tokens);
         let statements = parser.parse();
 
-        self.interpreter.interpret(statements).ok();
-        // let printer = AstPrinter::new();
-        // printer.print(Expr::new(statements));
+        for err in lexer.errors().iter().chain(parser.errors()) {
+            self.error(err.line, &err.msg);
+            self.had_error = true;
+        }
 
         if self.had_error {
-            std::process::exit(1);
+            return;
+        }
+
+        match self.interpreter.interpret(statements) {
+            Err(VariError::Exit(_, code)) => self.exit_code = Some(code),
+            result => {
+                if let Some((line, msg)) = result.err().and_then(VariError::report) {
+                    self.error(line, &msg);
+                    self.had_error = true;
+                }
+            }
         }
+        // whatever the program wrote before stopping
+        let _ = self.interpreter.output().flush();
+        // let printer = AstPrinter::new();
+        // printer.print(Expr::new(statements));
     }
 
     fn read_source(&self, file_path: &str) -> Result<String, Box<dyn std::error::Error>> {
@@ -56,26 +292,62 @@ impl Vari {
         self.report(line, "", msg);
     }
 
-    pub fn run_prompt(&mut self) -> () {
+    pub fn run_prompt(&mut self) {
+        // nobody would see the prompts once the output can't be
+        // written, that ends the session like unreadable input
+        if let Err(err) = self.prompt() {
+            let error_output = self.interpreter.error_output();
+            let _ = writeln!(error_output, "Couldn't write output: {}.", err);
+            self.had_error = true;
+        }
+    }
+
+    fn prompt(&mut self) -> io::Result<()> {
         loop {
             let mut user_inp = String::new();
-            print!("> ");
-            io::stdout().flush().unwrap();
-            match io::stdin().read_line(&mut user_inp) {
+            let output = self.interpreter.output();
+            write!(output, "> ")?;
+            output.flush()?;
+            match self.interpreter.input().read_line(&mut user_inp) {
+                // end of input
+                Ok(0) => return writeln!(self.interpreter.output()),
                 Ok(_) => {
                     self.run(user_inp.as_str());
+                    if self.exit_code.is_some() {
+                        return Ok(());
+                    }
+                    // a mistake shouldn't end the session
+                    self.had_error = false;
+                }
+                // input that can't be read now won't be readable later
+                Err(err) => {
+                    self.had_error = true;
+                    return writeln!(self.interpreter.output(), "\nCouldn't read input: {}.", err);
                 }
-                Err(_) => todo!(),
             }
         }
     }
 
-    pub fn run_file(&mut self, file_path: &str) {
+    // the process exit code for what has run so far
+    pub fn status(&self) -> i32 {
+        match self.exit_code {
+            Some(code) => code,
+            None if self.had_error => 1,
+            None => 0,
+        }
+    }
+
+    // returns the process exit code
+    pub fn run_file(&mut self, file_path: &str) -> i32 {
         match self.read_source(file_path) {
             Ok(data) => {
                 self.run(data.as_str());
+                return self.status();
+            }
+            Err(err) => {
+                println!("{}: {}", file_path, err);
+                return 1;
             }
-            Err(_) => todo!(),
         }
     }
 }